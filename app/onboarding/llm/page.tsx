@@ -32,9 +32,11 @@ export default function LLMOnboardingPage() {
       if (savedConfig.base_url) {
           setConfig(prev => ({
               ...prev,
+              ...savedConfig,
               model: savedConfig.model || prev.model,
               base_url: savedConfig.base_url || prev.base_url,
-              api_key: savedConfig.api_key === "******" ? "" : savedConfig.api_key
+              // 已保存的密钥只返回占位符或引用类型，需重新输入
+              api_key: ["******", "env", "file", "cmd"].includes(savedConfig.api_key) ? "" : savedConfig.api_key
           }));
       }
     } catch (e) {
@@ -172,6 +172,7 @@ export default function RulesPage() {
           ...rule,
           preferred_input: preferredInput,
           is_ai_generated: false, // Mark as manual override
+          is_learned: false,
         };
       }
       return rule;
@@ -322,6 +323,11 @@ export default function RulesPage() {
                   {/* Name */}
                   <div className="w-[154px] text-sm font-medium text-[#18181b] dark:text-[#fafafa]">
                     {rule.app_name}
+                    {rule.is_learned && (
+                      <span className="ml-2 inline-flex items-center rounded-[6px] bg-[#f4f4f5] dark:bg-zinc-800 px-1.5 py-0.5 text-[10px] font-medium text-[#71717b] dark:text-zinc-400">
+                        已学习
+                      </span>
+                    )}
                   </div>
 
                   {/* Input Method Badge */}
@@ -4,6 +4,15 @@ export type InputSource = {
   category: string;
 };
 
+export type InputChangeOrigin = "smartime" | "user";
+
+/** `input_source_changed` 事件负载 */
+export type InputSourceChangedEvent = {
+  source: InputSource;
+  app: { bundle_id: string; app_name: string } | null;
+  origin: InputChangeOrigin;
+};
+
 export type InstalledApp = {
   name: string;
   bundle_id: string;
@@ -12,11 +21,24 @@ export type InstalledApp = {
 
 export type AppIconMap = Record<string, string>;
 
+export type InputMode = "ascii" | "native";
+
 export type AppRule = {
   bundle_id: string;
   app_name: string;
   preferred_input: string;
   is_ai_generated: boolean;
+  input_mode?: InputMode | null;
+  remember_last_input?: boolean;
+  is_learned?: boolean;
+  timing?: RuleTiming;
+};
+
+/** 规则的切换时机，全部为 0 时立即切换一次 */
+export type RuleTiming = {
+  delay_ms: number;
+  verify_retries: number;
+  enforce_ms: number;
 };
 
 export type AppConfig = {
@@ -26,14 +48,141 @@ export type AppConfig = {
   general: {
     auto_start: boolean;
     hide_dock_icon: boolean;
+    shortcuts?: ShortcutSettings;
   };
+  switching?: SwitchingSettings;
   rules: AppRule[];
 };
 
+/** 全局快捷键，格式如 "CmdOrCtrl+Shift+I"，null 表示不绑定 */
+export type ShortcutSettings = {
+  toggle_global_switch: string | null;
+  pause_switching: string | null;
+  pause_minutes: number;
+  cycle_input: string | null;
+  pin_current_input: string | null;
+};
+
+export type SwitchingSettings = {
+  settle_delay_ms: number;
+  remember_last_input: boolean;
+  /** 焦点切换时忽略的应用（启动器、截图工具等），后端提供内置默认值 */
+  ignored_apps?: string[];
+  /** 试运行：只记录切换，不修改系统输入法 */
+  dry_run?: boolean;
+};
+
+export type RuleSuggestion = {
+  bundle_id: string;
+  app_name: string;
+  current_input: string;
+  suggested_input: string;
+  corrections: number;
+};
+
+export type SwitchingPauseStatus = {
+  paused: boolean;
+  /** 自动恢复时间（Unix 毫秒），null 表示直到手动恢复或重启 */
+  resume_at_ms: number | null;
+};
+
+export type SwitchingMetrics = {
+  received: number;
+  dropped: number;
+  dispatched: number;
+  cancelled: number;
+};
+
+export type SwitchOutcome =
+  | { kind: "switched" | "unchanged" | "no_rule" | "superseded" | "paused" }
+  | { kind: "verified" | "verify_failed"; retries: number }
+  | { kind: "enforced"; reapplied: number };
+
+/** 录制的焦点变化，at_ms 为相对录制开始的毫秒数 */
+export type RecordedFocus = {
+  at_ms: number;
+  bundle_id: string;
+  app_name?: string | null;
+  pid?: number | null;
+  window_title?: string | null;
+};
+
+export type FocusRecording = {
+  initial_input?: string | null;
+  events: RecordedFocus[];
+};
+
+export type SimulatedDecision = {
+  at_ms: number;
+  bundle_id: string;
+  app_name: string;
+  target_input: string | null;
+  target_mode: InputMode | null;
+  /** 切换出错时为 null，原因见 error */
+  outcome: SwitchOutcome | null;
+  error: string | null;
+  input_after: string | null;
+};
+
+export type ReplayReport = {
+  decisions: SimulatedDecision[];
+  metrics: SwitchingMetrics;
+};
+
+export type AppIdentityMode = "name_and_bundle_id" | "bundle_id_only" | "name_only";
+
+export type LLMPrivacySettings = {
+  excluded_bundle_patterns: string[];
+  identity_mode: AppIdentityMode;
+};
+
+export type LLMUsageLimits = {
+  max_tokens_per_scan: number | null;
+  max_tokens_per_month: number | null;
+};
+
+export type LLMNetworkSettings = {
+  proxy_url: string | null;
+  no_proxy: string[];
+  extra_headers: Record<string, string>;
+  ca_bundle_path: string | null;
+  allow_http_localhost: boolean;
+};
+
 export type LLMConfig = {
   api_key: string;
   model: string;
   base_url: string;
+  privacy?: LLMPrivacySettings;
+  limits?: LLMUsageLimits;
+  network?: LLMNetworkSettings;
+};
+
+export type UsageTotals = {
+  calls: number;
+  failed_calls: number;
+  prompt_tokens: number;
+  completion_tokens: number;
+  total_tokens: number;
+  estimated_tokens: number;
+};
+
+export type LLMUsageSummary = {
+  month: string;
+  current_month: UsageTotals;
+  all_time: UsageTotals;
+};
+
+export type LLMRequestPreview = {
+  bundle_id: string;
+  app_name: string;
+  url: string;
+  body: unknown;
+};
+
+export type LLMPayloadPreview = {
+  requests: LLMRequestPreview[];
+  excluded_bundle_ids: string[];
 };
 
 type TauriWindow = {
@@ -46,6 +195,19 @@ const normalizeConfig = (config: AppConfig): AppConfig => ({
   general: {
     auto_start: config.general?.auto_start ?? false,
     hide_dock_icon: config.general?.hide_dock_icon ?? false,
+    shortcuts: {
+      toggle_global_switch: config.general?.shortcuts?.toggle_global_switch ?? null,
+      pause_switching: config.general?.shortcuts?.pause_switching ?? null,
+      pause_minutes: config.general?.shortcuts?.pause_minutes ?? 15,
+      cycle_input: config.general?.shortcuts?.cycle_input ?? null,
+      pin_current_input: config.general?.shortcuts?.pin_current_input ?? null,
+    },
+  },
+  switching: {
+    settle_delay_ms: config.switching?.settle_delay_ms ?? 120,
+    remember_last_input: config.switching?.remember_last_input ?? false,
+    ignored_apps: config.switching?.ignored_apps,
+    dry_run: config.switching?.dry_run ?? false,
   },
   rules: config.rules ?? [],
 });
@@ -228,6 +390,29 @@ export const API = {
     return API._invoke('cmd_save_rules', { rules });
   },
 
+  /**
+   * 获取由手动纠正生成的规则建议
+   */
+  listRuleSuggestions: async (): Promise<RuleSuggestion[]> => {
+    if (!API._isTauri()) return [];
+    return API._invoke('cmd_list_rule_suggestions');
+  },
+
+  /**
+   * 接受建议并写入为手动规则
+   */
+  acceptSuggestion: async (bundleId: string): Promise<AppRule> => {
+    return API._invoke('cmd_accept_suggestion', { bundleId });
+  },
+
+  /**
+   * 忽略建议，之后不再重复提示
+   */
+  dismissSuggestion: async (bundleId: string): Promise<boolean> => {
+    if (!API._isTauri()) return false;
+    return API._invoke('cmd_dismiss_suggestion', { bundleId });
+  },
+
   /**
    * 获取配置
    */
@@ -283,6 +468,7 @@ export const API = {
 
   /**
    * 获取 LLM 配置
+   * api_key 已脱敏：明文密钥为 "******"，引用只返回类型 "env" / "file" / "cmd"
    */
   getLLMConfig: async (): Promise<LLMConfig> => {
     if (!API._isTauri()) {
@@ -305,6 +491,67 @@ export const API = {
     return API._invoke('cmd_save_llm_config', { config });
   },
 
+  /**
+   * 预览扫描时将发送给 LLM 的请求内容（不会发起网络请求）
+   */
+  previewLLMPayloads: async (inputSources: InputSource[]): Promise<LLMPayloadPreview> => {
+    if (!API._isTauri()) {
+      return { requests: [], excluded_bundle_ids: [] };
+    }
+    return API._invoke('cmd_preview_llm_payloads', { inputSources });
+  },
+
+  /**
+   * 查询本地审计日志中的 LLM 调用与 token 用量汇总
+   */
+  getLLMUsageSummary: async (): Promise<LLMUsageSummary> => {
+    if (!API._isTauri()) {
+      const empty = { calls: 0, failed_calls: 0, prompt_tokens: 0, completion_tokens: 0, total_tokens: 0 };
+      return { month: "", current_month: empty, all_time: empty };
+    }
+    return API._invoke('cmd_get_llm_usage_summary');
+  },
+
+  /**
+   * 焦点合并统计，用于观察快速切换应用时丢弃了多少焦点事件
+   */
+  getSwitchingMetrics: async (): Promise<SwitchingMetrics> => {
+    if (!API._isTauri()) {
+      return { received: 0, dropped: 0, dispatched: 0, cancelled: 0 };
+    }
+    return API._invoke('cmd_get_switching_metrics');
+  },
+
+  /**
+   * 用当前规则回放焦点序列，返回切换引擎的决策，不修改系统输入法
+   */
+  simulateFocus: async (recording: FocusRecording): Promise<ReplayReport> => {
+    if (!API._isTauri()) {
+      return { decisions: [], metrics: { received: 0, dropped: 0, dispatched: 0, cancelled: 0 } };
+    }
+    return API._invoke('cmd_simulate_focus', { recording });
+  },
+
+  /**
+   * 暂停自动切换，minutes 为空时直到手动恢复或重启
+   */
+  pauseSwitching: async (minutes: number | null): Promise<SwitchingPauseStatus> => {
+    if (!API._isTauri()) {
+      return { paused: true, resume_at_ms: minutes === null ? null : Date.now() + minutes * 60_000 };
+    }
+    return API._invoke('cmd_pause_switching', { minutes });
+  },
+
+  resumeSwitching: async (): Promise<SwitchingPauseStatus> => {
+    if (!API._isTauri()) return { paused: false, resume_at_ms: null };
+    return API._invoke('cmd_resume_switching');
+  },
+
+  getSwitchingPause: async (): Promise<SwitchingPauseStatus> => {
+    if (!API._isTauri()) return { paused: false, resume_at_ms: null };
+    return API._invoke('cmd_get_switching_pause');
+  },
+
   /**
    * 检查 LLM 连接
    */
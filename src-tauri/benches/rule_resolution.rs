@@ -0,0 +1,77 @@
+//! 焦点切换热路径的规则解析延迟：`cargo bench --bench rule_resolution`
+
+use app_lib::config::{AppConfig, AppRule, RuleTiming};
+use app_lib::rule_snapshot::{RuleSnapshot, RuleSnapshots};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use std::hint::black_box;
+use std::sync::Mutex;
+
+const RULE_COUNTS: [usize; 2] = [1_000, 5_000];
+
+fn config(rule_count: usize) -> AppConfig {
+    AppConfig {
+        rules: (0..rule_count)
+            .map(|index| AppRule {
+                bundle_id: format!("com.example.app{index}"),
+                app_name: format!("App {index}"),
+                preferred_input: if index % 2 == 0 { "pinyin" } else { "abc" }.to_string(),
+                is_ai_generated: true,
+                input_mode: None,
+                remember_last_input: index % 7 == 0,
+                is_learned: false,
+                timing: RuleTiming::default(),
+            })
+            .collect(),
+        ..AppConfig::default()
+    }
+}
+
+/// 一半命中规则、一半没有规则的应用
+fn bundle_ids(rule_count: usize) -> Vec<String> {
+    (0..1_000)
+        .map(|index| format!("com.example.app{}", index * 7919 % (rule_count * 2)))
+        .collect()
+}
+
+fn bench_resolve(c: &mut Criterion) {
+    let mut group = c.benchmark_group("resolve");
+    for rule_count in RULE_COUNTS {
+        let config = config(rule_count);
+        let snapshots = RuleSnapshots::default();
+        snapshots.publish(RuleSnapshot::compile(&config));
+        let locked_config = Mutex::new(config);
+        let bundle_ids = bundle_ids(rule_count);
+        let mut next = bundle_ids.iter().cycle();
+
+        group.bench_function(BenchmarkId::new("snapshot", rule_count), |b| {
+            b.iter(|| {
+                let bundle_id = next.next().unwrap();
+                black_box(snapshots.load().should_remember(bundle_id));
+                snapshots.load().resolve(bundle_id).is_some()
+            })
+        });
+        // 快照之前的路径：加锁并克隆整份配置
+        group.bench_function(BenchmarkId::new("locked_config_clone", rule_count), |b| {
+            b.iter(|| {
+                let bundle_id = next.next().unwrap();
+                let config = locked_config.lock().unwrap().clone();
+                config.global_switch && snapshots.load().resolve(bundle_id).is_some()
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_compile(c: &mut Criterion) {
+    let mut group = c.benchmark_group("compile");
+    for rule_count in RULE_COUNTS {
+        let config = config(rule_count);
+        group.bench_function(BenchmarkId::from_parameter(rule_count), |b| {
+            b.iter(|| RuleSnapshot::compile(black_box(&config)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_resolve, bench_compile);
+criterion_main!(benches);
@@ -0,0 +1,51 @@
+// 在 GNOME Shell 会话总线连接上导出 /com/smartime/Focus，
+// 焦点窗口变化时发出 com.smartime.Focus1.FocusChanged(app_id, title, pid)
+import GLib from 'gi://GLib';
+import Gio from 'gi://Gio';
+import {Extension} from 'resource:///org/gnome/shell/extensions/extension.js';
+
+const OBJECT_PATH = '/com/smartime/Focus';
+const INTERFACE_XML = `
+<node>
+  <interface name="com.smartime.Focus1">
+    <signal name="FocusChanged">
+      <arg type="s" name="appId"/>
+      <arg type="s" name="title"/>
+      <arg type="u" name="pid"/>
+    </signal>
+  </interface>
+</node>`;
+
+export default class SmartImeFocusExtension extends Extension {
+    enable() {
+        this._exported = Gio.DBusExportedObject.wrapJSObject(INTERFACE_XML, {});
+        this._exported.export(Gio.DBus.session, OBJECT_PATH);
+        this._focusHandler = global.display.connect('notify::focus-window', () =>
+            this._publishFocus());
+        this._publishFocus();
+    }
+
+    disable() {
+        if (this._focusHandler) {
+            global.display.disconnect(this._focusHandler);
+            this._focusHandler = null;
+        }
+        this._exported?.unexport();
+        this._exported = null;
+    }
+
+    _publishFocus() {
+        const window = global.display.focus_window;
+        if (!window || !this._exported)
+            return;
+
+        // Wayland 原生窗口的 wm_class 即 app_id，XWayland 窗口为 X11 class
+        const appId = window.get_wm_class() || window.get_gtk_application_id() || '';
+        if (!appId)
+            return;
+
+        const pid = Math.max(window.get_pid(), 0);
+        this._exported.emit_signal('FocusChanged',
+            new GLib.Variant('(ssu)', [appId, window.get_title() ?? '', pid]));
+    }
+}
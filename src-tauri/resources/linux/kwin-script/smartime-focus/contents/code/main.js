@@ -0,0 +1,18 @@
+// KWin 脚本无法发出 D-Bus 信号，改为调用 SmartIME 提供的
+// com.smartime.Focus1.ReportFocus(app_id, title)
+function reportFocus(window) {
+    if (!window || !window.resourceClass) {
+        return;
+    }
+    callDBus("com.smartime.Focus", "/com/smartime/Focus", "com.smartime.Focus1",
+        "ReportFocus", String(window.resourceClass), String(window.caption || ""));
+}
+
+// KWin 6 使用 windowActivated/activeWindow，KWin 5 使用 clientActivated/activeClient
+if (workspace.windowActivated) {
+    workspace.windowActivated.connect(reportFocus);
+    reportFocus(workspace.activeWindow);
+} else {
+    workspace.clientActivated.connect(reportFocus);
+    reportFocus(workspace.activeClient);
+}
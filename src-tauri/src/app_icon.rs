@@ -1,15 +1,22 @@
 #![allow(deprecated)] // Keep using the existing cocoa/objc bridge until the project migrates to objc2.
 
 use crate::error::Result;
+#[cfg(target_os = "macos")]
 use base64::{engine::general_purpose::STANDARD, Engine as _};
+#[cfg(target_os = "macos")]
 use cocoa::appkit::{NSCompositingOperation, NSImage};
+#[cfg(target_os = "macos")]
 use cocoa::base::{id, nil};
+#[cfg(target_os = "macos")]
 use cocoa::foundation::{NSAutoreleasePool, NSPoint, NSRect, NSSize, NSString};
+#[cfg(target_os = "macos")]
 use objc::{class, msg_send, sel, sel_impl};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
+#[cfg(target_os = "macos")]
 const ICON_SIZE: f64 = 64.0;
+#[cfg(target_os = "macos")]
 const NSPNG_FILE_TYPE: usize = 4;
 
 pub fn app_icon_data_urls(apps: &[(String, PathBuf)]) -> Result<HashMap<String, String>> {
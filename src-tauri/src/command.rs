@@ -1,41 +1,82 @@
-use crate::config::{AppConfig, AppRule, AppState};
+use crate::config::{AppConfig, AppRule, AppState, RuleTiming};
+use crate::credentials::ApiKeySource;
 use crate::error::{AppError, Result};
 use crate::general_settings;
-use crate::input_source::{get_system_input_sources, select_input_source, InputSource};
-use crate::llm::LLMConfig;
+use crate::input_source::{DryRunBackend, InputSource, InputSourceBackend};
+use crate::llm::{LLMConfig, LLMPayloadPreview};
+use crate::llm_audit::{now_ms, AuditLog, UsageSummary};
+use crate::observer::{CoalescerMetrics, FocusRecording, ReplayReport};
+use crate::rule_suggestions::RuleSuggestion;
+use crate::switching_pause::{self, PauseStatus};
 use crate::system_apps::SystemApp;
+use crate::tray_menu;
 use std::collections::{HashMap, HashSet};
 use std::sync::atomic::Ordering;
 use std::sync::mpsc;
 use std::time::Duration;
-use tauri::{AppHandle, State};
+use tauri::{AppHandle, Manager, State};
 
 // Input Source Commands
 
 #[tauri::command]
 pub async fn cmd_get_system_input_sources(app: AppHandle) -> Result<Vec<InputSource>> {
-    run_input_source_task_on_main_thread_async(
+    run_backend_task_async(
         app,
         "input source scan",
         Duration::from_secs(5),
-        get_system_input_sources,
+        |backend| backend.list(),
     )
     .await
 }
 
 #[tauri::command]
 pub async fn cmd_select_input_source(id: String, app: AppHandle) -> Result<()> {
-    run_input_source_task_on_main_thread_async(
+    let dry_run = app.state::<AppState>().rules.load().dry_run();
+    run_backend_task_async(
         app,
         "input source selection",
         Duration::from_millis(500),
-        move || select_input_source(&id),
+        move |backend| {
+            if dry_run {
+                return DryRunBackend::new(backend).select(&id);
+            }
+            backend.select(&id)
+        },
     )
     .await
 }
 
 // Config Commands
 
+/// 暂停自动切换，`minutes` 为空时直到手动恢复或重启
+#[tauri::command]
+pub fn cmd_pause_switching(minutes: Option<u64>, app: AppHandle) -> PauseStatus {
+    let duration = minutes.map(|minutes| Duration::from_secs(minutes.saturating_mul(60)));
+    switching_pause::pause_switching(&app, duration)
+}
+
+#[tauri::command]
+pub fn cmd_resume_switching(app: AppHandle) -> PauseStatus {
+    switching_pause::resume_switching(&app)
+}
+
+#[tauri::command]
+pub fn cmd_get_switching_pause(state: State<'_, AppState>) -> PauseStatus {
+    state.switching_pause.status_at(now_ms())
+}
+
+/// 焦点合并统计：收到、丢弃、派发与取消的焦点数量
+#[tauri::command]
+pub fn cmd_get_switching_metrics(state: State<'_, AppState>) -> CoalescerMetrics {
+    state.focus_queue.metrics()
+}
+
+/// 用当前规则回放录制的焦点序列，返回切换引擎的决策，不修改系统输入法
+#[tauri::command]
+pub fn cmd_simulate_focus(recording: FocusRecording, state: State<'_, AppState>) -> ReplayReport {
+    crate::observer::replay(&state.rules.load(), &recording)
+}
+
 #[tauri::command]
 pub fn cmd_get_installed_apps() -> Result<Vec<SystemApp>> {
     crate::system_apps::get_installed_apps()
@@ -94,11 +135,17 @@ pub fn cmd_save_config(
         general_settings::apply_general_settings_delta(&app, &previous.general, &config.general)?;
     }
 
-    manager.set_config(config)
+    manager.set_config(config)?;
+    tray_menu::refresh(&app);
+    Ok(())
 }
 
 #[tauri::command]
-pub fn cmd_save_rules(rules: Vec<AppRule>, state: State<'_, AppState>) -> Result<()> {
+pub fn cmd_save_rules(
+    rules: Vec<AppRule>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<()> {
     let mut manager = state
         .config
         .lock()
@@ -106,7 +153,55 @@ pub fn cmd_save_rules(rules: Vec<AppRule>, state: State<'_, AppState>) -> Result
 
     let mut config = manager.get_config();
     config.rules = rules;
-    manager.set_config(config)
+    manager.set_config(config)?;
+    tray_menu::refresh(&app);
+    Ok(())
+}
+
+/// 由反复的手动纠正生成、等待用户确认的规则建议
+#[tauri::command]
+pub fn cmd_list_rule_suggestions(state: State<'_, AppState>) -> Result<Vec<RuleSuggestion>> {
+    let suggestions = state
+        .rule_suggestions
+        .lock()
+        .map_err(|e| AppError::Lock(e.to_string()))?;
+    Ok(suggestions.list())
+}
+
+/// 接受建议：写入为手动规则并返回该规则
+#[tauri::command]
+pub fn cmd_accept_suggestion(
+    bundle_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<AppRule> {
+    let suggestion = state
+        .rule_suggestions
+        .lock()
+        .map_err(|e| AppError::Lock(e.to_string()))?
+        .accept(&bundle_id)
+        .ok_or_else(|| AppError::Config(format!("No rule suggestion for {bundle_id}")))?;
+
+    let mut manager = state
+        .config
+        .lock()
+        .map_err(|e| AppError::Lock(e.to_string()))?;
+    let rule = manager.upsert_manual_rule(
+        &suggestion.bundle_id,
+        &suggestion.app_name,
+        &suggestion.suggested_input,
+    )?;
+    tray_menu::refresh(&app);
+    Ok(rule)
+}
+
+#[tauri::command]
+pub fn cmd_dismiss_suggestion(bundle_id: String, state: State<'_, AppState>) -> Result<bool> {
+    let mut suggestions = state
+        .rule_suggestions
+        .lock()
+        .map_err(|e| AppError::Lock(e.to_string()))?;
+    Ok(suggestions.dismiss(&bundle_id))
 }
 
 #[tauri::command]
@@ -130,17 +225,29 @@ pub fn cmd_has_config(state: State<'_, AppState>) -> Result<bool> {
 // LLM Commands
 
 #[tauri::command]
-pub async fn cmd_check_llm_connection(config: LLMConfig) -> Result<bool> {
+pub async fn cmd_check_llm_connection(
+    mut config: LLMConfig,
+    state: State<'_, AppState>,
+) -> Result<bool> {
+    let current_key = state
+        .llm
+        .lock()
+        .map_err(|e| crate::error::AppError::Lock(e.to_string()))?
+        .get_config()
+        .api_key;
+    restore_masked_api_key(&mut config, current_key);
     crate::llm::LLMClient::check_connection(&config).await?;
     Ok(true)
 }
 
 #[tauri::command]
-pub fn cmd_save_llm_config(config: LLMConfig, state: State<'_, AppState>) -> Result<()> {
+pub fn cmd_save_llm_config(mut config: LLMConfig, state: State<'_, AppState>) -> Result<()> {
     let mut llm = state
         .llm
         .lock()
         .map_err(|e| crate::error::AppError::Lock(e.to_string()))?;
+    let current_key = llm.get_config().api_key;
+    restore_masked_api_key(&mut config, current_key);
     llm.update_config(config)
 }
 
@@ -151,13 +258,46 @@ pub fn cmd_get_llm_config(state: State<'_, AppState>) -> Result<LLMConfig> {
         .lock()
         .map_err(|e| crate::error::AppError::Lock(e.to_string()))?;
     let mut config = llm.get_config();
-    // 脱敏处理
-    if !config.api_key.is_empty() {
-        config.api_key = "******".to_string();
-    }
+    config.api_key = masked_api_key(&config.api_key);
     Ok(config)
 }
 
+/// 脱敏处理：明文密钥返回占位符，引用只返回类型（env/file/cmd）
+fn masked_api_key(api_key: &str) -> String {
+    if api_key.is_empty() {
+        return String::new();
+    }
+    ApiKeySource::parse(api_key)
+        .kind()
+        .unwrap_or("******")
+        .to_string()
+}
+
+/// 前端原样回传脱敏值时换回已保存的密钥或引用
+fn restore_masked_api_key(config: &mut LLMConfig, current_key: String) {
+    if !config.api_key.is_empty() && config.api_key == masked_api_key(&current_key) {
+        config.api_key = current_key;
+    }
+}
+
+#[tauri::command]
+pub fn cmd_get_llm_usage_summary() -> Result<UsageSummary> {
+    AuditLog::new().summarize(now_ms())
+}
+
+#[tauri::command]
+pub fn cmd_preview_llm_payloads(
+    input_sources: Vec<InputSource>,
+    state: State<'_, AppState>,
+) -> Result<LLMPayloadPreview> {
+    let target_apps = get_target_apps()?;
+    let llm = state
+        .llm
+        .lock()
+        .map_err(|e| crate::error::AppError::Lock(e.to_string()))?;
+    llm.preview_payloads(&target_apps, &input_sources)
+}
+
 #[tauri::command]
 pub async fn cmd_scan_and_predict(
     input_sources: Vec<InputSource>,
@@ -191,7 +331,7 @@ pub async fn cmd_rescan_and_save_rules(
         flag: &state.is_rescanning,
     };
 
-    let input_sources = get_system_input_sources_on_main_thread(&app)?;
+    let input_sources = list_system_input_sources(&app)?;
     let target_apps = get_target_apps()?;
 
     let existing_rules = {
@@ -212,6 +352,7 @@ pub async fn cmd_rescan_and_save_rules(
     let mut config = manager.get_config();
     config.rules = aligned.clone();
     manager.set_config(config)?;
+    tray_menu::refresh(&app);
 
     Ok(aligned)
 }
@@ -231,15 +372,69 @@ impl Drop for RescanGuard<'_> {
     }
 }
 
-fn get_system_input_sources_on_main_thread(app: &AppHandle) -> Result<Vec<InputSource>> {
-    run_input_source_task_on_main_thread(
+fn list_system_input_sources(app: &AppHandle) -> Result<Vec<InputSource>> {
+    run_backend_task(
         app,
         "input source scan",
         Duration::from_secs(5),
-        get_system_input_sources,
+        |backend| backend.list(),
     )
 }
 
+/// 调用输入法后端，后端要求时（如 macOS TIS）切换到主线程执行
+pub fn run_backend_task<T, F>(
+    app: &AppHandle,
+    task_name: &'static str,
+    timeout: Duration,
+    task: F,
+) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&dyn InputSourceBackend) -> Result<T> + Send + 'static,
+{
+    let backend = app.state::<AppState>().input_source.clone();
+    if !backend.requires_main_thread() {
+        return task(backend.as_ref());
+    }
+
+    run_input_source_task_on_main_thread(app, task_name, timeout, move || task(backend.as_ref()))
+}
+
+/// 执行会切换输入法的后端任务，试运行时只打印不切换
+pub fn run_switch_task<T, F>(
+    app: &AppHandle,
+    task_name: &'static str,
+    timeout: Duration,
+    task: F,
+) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&dyn InputSourceBackend) -> Result<T> + Send + 'static,
+{
+    let dry_run = app.state::<AppState>().rules.load().dry_run();
+    run_backend_task(app, task_name, timeout, move |backend| {
+        if dry_run {
+            return task(&DryRunBackend::new(backend));
+        }
+        task(backend)
+    })
+}
+
+async fn run_backend_task_async<T, F>(
+    app: AppHandle,
+    task_name: &'static str,
+    timeout: Duration,
+    task: F,
+) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&dyn InputSourceBackend) -> Result<T> + Send + 'static,
+{
+    tauri::async_runtime::spawn_blocking(move || run_backend_task(&app, task_name, timeout, task))
+        .await
+        .map_err(|e| AppError::InputSource(format!("Failed to join {task_name}: {e}")))?
+}
+
 async fn run_input_source_task_on_main_thread_async<T, F>(
     app: AppHandle,
     task_name: &'static str,
@@ -319,11 +514,17 @@ async fn predict_rules_for_apps(
         guard.clone()
     };
 
+    let mut budget = llm_client.begin_scan_budget()?;
     let mut rules = Vec::new();
 
     for app in target_apps {
+        // 隐私排除名单中的应用不会离开本机
+        if llm_client.is_app_excluded(&app.bundle_id) {
+            continue;
+        }
+
         match llm_client
-            .predict(&app.name, &app.bundle_id, input_sources)
+            .predict(&app.name, &app.bundle_id, input_sources, &mut budget)
             .await
         {
             Ok(preferred_input) => {
@@ -332,8 +533,14 @@ async fn predict_rules_for_apps(
                     app_name: app.name.clone(),
                     preferred_input,
                     is_ai_generated: true,
+                    input_mode: None,
+                    remember_last_input: false,
+                    is_learned: false,
+                    timing: RuleTiming::default(),
                 });
             }
+            // 超出 token 上限时整体中止，不保存部分结果
+            Err(e @ AppError::TokenBudget(_)) => return Err(e),
             Err(e) => {
                 eprintln!("Failed to predict for {}: {}", app.name, e);
             }
@@ -353,12 +560,6 @@ fn align_rules_with_apps(
         .into_iter()
         .map(|rule| (rule.bundle_id.clone(), rule))
         .collect();
-    let manual_by_bundle: HashMap<String, AppRule> = existing_rules
-        .iter()
-        .filter(|rule| !rule.is_ai_generated)
-        .cloned()
-        .map(|rule| (rule.bundle_id.clone(), rule))
-        .collect();
     let existing_by_bundle: HashMap<String, AppRule> = existing_rules
         .iter()
         .cloned()
@@ -372,19 +573,27 @@ fn align_rules_with_apps(
     let mut aligned = Vec::with_capacity(target_apps.len());
 
     for app in target_apps {
-        let mut selected = if let Some(rule) = manual_by_bundle.get(&app.bundle_id) {
-            rule.clone()
-        } else if let Some(rule) = generated_by_bundle.get(&app.bundle_id) {
-            rule.clone()
-        } else if let Some(rule) = existing_by_bundle.get(&app.bundle_id) {
-            rule.clone()
-        } else {
-            AppRule {
+        let existing = existing_by_bundle.get(&app.bundle_id);
+        let generated = generated_by_bundle.get(&app.bundle_id);
+        let mut selected = match (existing, generated) {
+            (Some(rule), _) if !rule.is_ai_generated => rule.clone(),
+            // 只更新推荐的输入法，保留用户设置的子模式、切换时机与学习开关
+            (Some(rule), Some(generated)) => AppRule {
+                preferred_input: generated.preferred_input.clone(),
+                ..rule.clone()
+            },
+            (Some(rule), None) => rule.clone(),
+            (None, Some(generated)) => generated.clone(),
+            (None, None) => AppRule {
                 bundle_id: app.bundle_id.clone(),
                 app_name: app.name.clone(),
                 preferred_input: fallback_input.clone(),
                 is_ai_generated: true,
-            }
+                input_mode: None,
+                remember_last_input: false,
+                is_learned: false,
+                timing: RuleTiming::default(),
+            },
         };
 
         selected.bundle_id = app.bundle_id.clone();
@@ -482,6 +691,7 @@ fn request_accessibility_permission(prompt: bool) -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::input_source::InputMode;
     use std::path::PathBuf;
 
     #[test]
@@ -542,12 +752,20 @@ mod tests {
                 app_name: "TextEdit".to_string(),
                 preferred_input: "com.apple.keylayout.ABC".to_string(),
                 is_ai_generated: true,
+                input_mode: None,
+                remember_last_input: false,
+                is_learned: false,
+                timing: RuleTiming::default(),
             },
             AppRule {
                 bundle_id: "com.apple.Terminal".to_string(),
                 app_name: "Terminal".to_string(),
                 preferred_input: "com.apple.inputmethod.Korean.2SetKorean".to_string(),
                 is_ai_generated: false,
+                input_mode: None,
+                remember_last_input: false,
+                is_learned: false,
+                timing: RuleTiming::default(),
             },
         ];
 
@@ -570,6 +788,44 @@ mod tests {
         assert_eq!(normalized[1].preferred_input, "com.apple.keylayout.ABC");
     }
 
+    #[test]
+    fn test_masked_api_key_hides_reference_contents() {
+        assert_eq!(masked_api_key(""), "");
+        assert_eq!(masked_api_key("sk-secret"), "******");
+        assert_eq!(masked_api_key("env:OPENAI_API_KEY"), "env");
+        assert_eq!(masked_api_key("file:~/.secrets/openai"), "file");
+        assert_eq!(
+            masked_api_key("cmd:vault read -field=key --token=s.abc"),
+            "cmd"
+        );
+    }
+
+    #[test]
+    fn test_masked_api_key_is_restored_before_use() {
+        let form = |api_key: &str| LLMConfig {
+            api_key: api_key.to_string(),
+            ..LLMConfig::default()
+        };
+
+        // 连接测试与保存都用已保存的密钥或引用替换脱敏值
+        let mut config = form("******");
+        restore_masked_api_key(&mut config, "sk-secret".to_string());
+        assert_eq!(config.api_key, "sk-secret");
+
+        let mut config = form("env");
+        restore_masked_api_key(&mut config, "env:OPENAI_API_KEY".to_string());
+        assert_eq!(config.api_key, "env:OPENAI_API_KEY");
+
+        // 用户输入的新密钥保持不变
+        let mut config = form("sk-new");
+        restore_masked_api_key(&mut config, "sk-secret".to_string());
+        assert_eq!(config.api_key, "sk-new");
+
+        let mut config = form("");
+        restore_masked_api_key(&mut config, String::new());
+        assert_eq!(config.api_key, "");
+    }
+
     #[test]
     fn test_align_rules_with_apps_keeps_only_installed_apps_and_preserves_manual_rules() {
         let target_apps = vec![
@@ -600,6 +856,10 @@ mod tests {
             app_name: "Alpha".to_string(),
             preferred_input: "com.apple.inputmethod.SCIM.ITABC".to_string(),
             is_ai_generated: true,
+            input_mode: None,
+            remember_last_input: false,
+            is_learned: false,
+            timing: RuleTiming::default(),
         }];
 
         let existing = vec![
@@ -608,24 +868,40 @@ mod tests {
                 app_name: "Alpha".to_string(),
                 preferred_input: "com.apple.keylayout.ABC".to_string(),
                 is_ai_generated: false,
+                input_mode: None,
+                remember_last_input: false,
+                is_learned: false,
+                timing: RuleTiming::default(),
             },
             AppRule {
                 bundle_id: "com.example.beta".to_string(),
                 app_name: "Beta".to_string(),
                 preferred_input: "com.apple.keylayout.ABC".to_string(),
                 is_ai_generated: true,
+                input_mode: None,
+                remember_last_input: false,
+                is_learned: false,
+                timing: RuleTiming::default(),
             },
             AppRule {
                 bundle_id: "com.example.gamma".to_string(),
                 app_name: "Gamma".to_string(),
                 preferred_input: "com.apple.keylayout.ABC".to_string(),
                 is_ai_generated: false,
+                input_mode: None,
+                remember_last_input: false,
+                is_learned: false,
+                timing: RuleTiming::default(),
             },
             AppRule {
                 bundle_id: "com.apple.Safari".to_string(),
                 app_name: "Safari".to_string(),
                 preferred_input: "com.apple.inputmethod.SCIM.ITABC".to_string(),
                 is_ai_generated: false,
+                input_mode: None,
+                remember_last_input: false,
+                is_learned: false,
+                timing: RuleTiming::default(),
             },
         ];
 
@@ -666,4 +942,64 @@ mod tests {
             .iter()
             .any(|rule| rule.bundle_id == "com.example.gamma"));
     }
+
+    #[test]
+    fn test_align_rules_with_apps_keeps_rule_settings_of_ai_rules() {
+        let target_apps = vec![SystemApp {
+            name: "Beta".to_string(),
+            bundle_id: "com.example.beta".to_string(),
+            path: PathBuf::from("/Applications/Beta.app"),
+        }];
+        let generated = vec![AppRule {
+            bundle_id: "com.example.beta".to_string(),
+            app_name: "Beta".to_string(),
+            preferred_input: "com.apple.inputmethod.SCIM.ITABC".to_string(),
+            is_ai_generated: true,
+            input_mode: None,
+            remember_last_input: false,
+            is_learned: false,
+            timing: RuleTiming::default(),
+        }];
+        let timing = RuleTiming {
+            delay_ms: 150,
+            verify_retries: 2,
+            enforce_ms: 500,
+        };
+        let existing = vec![AppRule {
+            bundle_id: "com.example.beta".to_string(),
+            app_name: "Beta".to_string(),
+            preferred_input: "com.apple.keylayout.ABC".to_string(),
+            is_ai_generated: true,
+            input_mode: Some(InputMode::Ascii),
+            remember_last_input: true,
+            is_learned: true,
+            timing,
+        }];
+        let input_sources = vec![
+            InputSource {
+                id: "com.apple.keylayout.ABC".to_string(),
+                name: "ABC".to_string(),
+                category: "TISCategoryKeyboardInputSource".to_string(),
+            },
+            InputSource {
+                id: "com.apple.inputmethod.SCIM.ITABC".to_string(),
+                name: "Pinyin - Simplified".to_string(),
+                category: "TISCategoryKeyboardInputSource".to_string(),
+            },
+        ];
+
+        let aligned = align_rules_with_apps(&target_apps, generated, &existing, &input_sources);
+
+        // 只更新推荐的输入法，用户设置的子模式、切换时机与学习开关保留
+        assert_eq!(aligned.len(), 1);
+        assert_eq!(
+            aligned[0].preferred_input,
+            "com.apple.inputmethod.SCIM.ITABC"
+        );
+        assert!(aligned[0].is_ai_generated);
+        assert_eq!(aligned[0].input_mode, Some(InputMode::Ascii));
+        assert!(aligned[0].remember_last_input);
+        assert!(aligned[0].is_learned);
+        assert_eq!(aligned[0].timing, timing);
+    }
 }
@@ -1,10 +1,18 @@
 use crate::error::Result;
+use crate::input_source::{InputChangeTracker, InputMode, InputSourceBackend, TrackedBackend};
+use crate::observer::FocusQueue;
+use crate::rule_snapshot::{RuleSnapshot, RuleSnapshots};
+use crate::rule_suggestions::SuggestionEngine;
+use crate::switching_pause::SwitchingPause;
+use crate::tray_icon::TrayIcons;
+use crate::tray_menu::TrayMenuCache;
+use crate::tray_refresh::TrayRefreshQueue;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 use std::sync::atomic::AtomicBool;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppRule {
@@ -12,19 +20,119 @@ pub struct AppRule {
     pub app_name: String,
     pub preferred_input: String,
     pub is_ai_generated: bool,
+    /// 切换后设置的子模式（如拼音的英文/中文状态），仅部分输入法后端支持
+    #[serde(default)]
+    pub input_mode: Option<InputMode>,
+    /// 离开应用时记住当前输入法，返回时恢复
+    #[serde(default)]
+    pub remember_last_input: bool,
+    /// 偏好输入法由学习模式记录，而不是 AI 预测或手动设置
+    #[serde(default)]
+    pub is_learned: bool,
+    /// 切换时机：延迟、校验重试与强制窗口
+    #[serde(default)]
+    pub timing: RuleTiming,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+/// 规则的切换时机，全部为 0 时立即切换一次
+///
+/// 用于激活后会自行重置输入法、或需要稍等切换才生效的应用。
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct RuleTiming {
+    /// 焦点稳定后再等待多久切换（毫秒）
+    pub delay_ms: u64,
+    /// 切换后校验当前输入法，不一致时最多重试的次数
+    pub verify_retries: u32,
+    /// 切换后持续监视的时长（毫秒），应用改回输入法时重新切换
+    pub enforce_ms: u64,
+}
+
+/// 规则对应的切换目标
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleTarget {
+    pub input_id: String,
+    pub input_mode: Option<InputMode>,
+    pub timing: RuleTiming,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
 pub struct GeneralSettings {
     pub auto_start: bool,
     pub hide_dock_icon: bool,
+    #[serde(default)]
+    pub shortcuts: ShortcutSettings,
 }
 
-impl Default for GeneralSettings {
+/// 全局快捷键，格式如 `CmdOrCtrl+Shift+I`，为空表示不绑定
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct ShortcutSettings {
+    pub toggle_global_switch: Option<String>,
+    pub pause_switching: Option<String>,
+    /// 快捷键暂停的时长（分钟）
+    pub pause_minutes: u64,
+    pub cycle_input: Option<String>,
+    /// 把当前输入法固定为前台应用的规则
+    pub pin_current_input: Option<String>,
+}
+
+impl Default for ShortcutSettings {
     fn default() -> Self {
         Self {
-            auto_start: false,
-            hide_dock_icon: false,
+            toggle_global_switch: None,
+            pause_switching: None,
+            pause_minutes: 15,
+            cycle_input: None,
+            pin_current_input: None,
+        }
+    }
+}
+
+/// 内置忽略名单：启动器、截图工具、系统浮层与 SmartIME 自身
+pub const DEFAULT_IGNORED_APPS: &[&str] = &[
+    "com.smartime.app",
+    "smartime",
+    "com.apple.Spotlight",
+    "com.apple.screencaptureui",
+    "com.apple.screenshot.launcher",
+    "com.apple.controlcenter",
+    "com.apple.notificationcenterui",
+    "com.apple.loginwindow",
+    "com.runningwithcrayons.Alfred",
+    "com.raycast.macos",
+    "com.1password.1password-quick-access",
+    "org.kde.krunner",
+    "org.kde.spectacle",
+    "org.flameshot.Flameshot",
+    "flameshot",
+    "ulauncher",
+    "albert",
+    "rofi",
+    "xfce4-appfinder",
+];
+
+/// 焦点切换设置
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct SwitchingSettings {
+    /// 焦点保持多久后才切换输入法（毫秒），0 表示立即切换
+    pub settle_delay_ms: u64,
+    /// 对所有应用启用学习模式：记住离开时的输入法
+    pub remember_last_input: bool,
+    /// 用户添加的忽略应用，与内置名单合并生效，内置名单不写入配置
+    pub ignored_apps: Vec<String>,
+    /// 试运行：照常解析规则，但只记录切换、不修改系统输入法
+    pub dry_run: bool,
+}
+
+impl Default for SwitchingSettings {
+    fn default() -> Self {
+        Self {
+            settle_delay_ms: 120,
+            remember_last_input: false,
+            ignored_apps: Vec::new(),
+            dry_run: false,
         }
     }
 }
@@ -36,6 +144,8 @@ pub struct AppConfig {
     pub default_input: String, // "en", "zh", "keep"
     #[serde(default)]
     pub general: GeneralSettings,
+    #[serde(default)]
+    pub switching: SwitchingSettings,
     pub rules: Vec<AppRule>,
 }
 
@@ -46,41 +156,42 @@ impl Default for AppConfig {
             global_switch: true,
             default_input: "keep".to_string(),
             general: GeneralSettings::default(),
+            switching: SwitchingSettings::default(),
             rules: Vec::new(),
         }
     }
 }
 
-pub struct ConfigManager {
+pub(crate) struct ConfigManager {
     config: AppConfig,
     file_path: PathBuf,
-    // 内存缓存优化查询
-    rule_map: HashMap<String, String>,
+    // 焦点切换读取的编译快照，配置每次变化后重新发布
+    snapshots: RuleSnapshots,
 }
 
 impl ConfigManager {
     pub fn new() -> Self {
-        let config_dir = dirs::config_dir()
-            .unwrap_or_else(|| PathBuf::from("."))
-            .join("smartime");
-
-        if !config_dir.exists() {
-            let _ = fs::create_dir_all(&config_dir);
+        let file_path = default_config_path();
+        if let Some(config_dir) = file_path.parent().filter(|dir| !dir.exists()) {
+            let _ = fs::create_dir_all(config_dir);
         }
 
-        let file_path = config_dir.join("config.json");
         let config = Self::load_from_file(&file_path).unwrap_or_default();
+        Self::with_config(config, file_path)
+    }
 
+    /// 使用给定配置构建，不读取磁盘
+    pub fn with_config(config: AppConfig, file_path: PathBuf) -> Self {
         let mut manager = Self {
-            config: config.clone(),
+            config,
             file_path,
-            rule_map: HashMap::new(),
+            snapshots: RuleSnapshots::default(),
         };
         manager.rebuild_cache();
         manager
     }
 
-    fn load_from_file(path: &PathBuf) -> Result<AppConfig> {
+    pub fn load_from_file(path: &PathBuf) -> Result<AppConfig> {
         if !path.exists() {
             return Ok(AppConfig::default());
         }
@@ -96,11 +207,12 @@ impl ConfigManager {
     }
 
     fn rebuild_cache(&mut self) {
-        self.rule_map.clear();
-        for rule in &self.config.rules {
-            self.rule_map
-                .insert(rule.bundle_id.clone(), rule.preferred_input.clone());
-        }
+        self.snapshots.publish(RuleSnapshot::compile(&self.config));
+    }
+
+    /// 共享的规则快照句柄，之后的配置修改对它立即可见
+    pub fn snapshots(&self) -> RuleSnapshots {
+        self.snapshots.clone()
     }
 
     pub fn get_config(&self) -> AppConfig {
@@ -117,26 +229,148 @@ impl ConfigManager {
         self.save()
     }
 
-    pub fn get_rule(&self, bundle_id: &str) -> Option<String> {
-        if !self.config.global_switch {
-            return None;
+    pub fn settle_delay(&self) -> Duration {
+        Duration::from_millis(self.config.switching.settle_delay_ms)
+    }
+
+    /// 新建或覆盖应用的手动规则，保留子模式与学习开关
+    pub fn upsert_manual_rule(
+        &mut self,
+        bundle_id: &str,
+        app_name: &str,
+        input_id: &str,
+    ) -> Result<AppRule> {
+        let rule = match self
+            .config
+            .rules
+            .iter_mut()
+            .find(|rule| rule.bundle_id == bundle_id)
+        {
+            Some(rule) => {
+                rule.preferred_input = input_id.to_string();
+                rule.is_ai_generated = false;
+                rule.is_learned = false;
+                rule.clone()
+            }
+            None => {
+                let rule = AppRule {
+                    bundle_id: bundle_id.to_string(),
+                    app_name: app_name.to_string(),
+                    preferred_input: input_id.to_string(),
+                    is_ai_generated: false,
+                    input_mode: None,
+                    remember_last_input: false,
+                    is_learned: false,
+                    timing: RuleTiming::default(),
+                };
+                self.config.rules.push(rule.clone());
+                rule
+            }
+        };
+
+        self.rebuild_cache();
+        self.save()?;
+        Ok(rule)
+    }
+
+    /// 应用失去焦点时是否需要记录当前输入法
+    pub fn should_remember(&self, bundle_id: &str) -> bool {
+        self.snapshots.load().should_remember(bundle_id)
+    }
+
+    /// 记录应用离开时的输入法，没有规则时新建学习规则
+    ///
+    /// 只更新内存中的配置与快照，有变化时返回 `true`，由调用方合并后保存
+    pub fn learn_input(&mut self, bundle_id: &str, app_name: &str, input_id: &str) -> bool {
+        if !self.should_remember(bundle_id) {
+            return false;
+        }
+
+        match self
+            .config
+            .rules
+            .iter_mut()
+            .find(|rule| rule.bundle_id == bundle_id)
+        {
+            Some(rule) if rule.preferred_input == input_id => return false,
+            Some(rule) => {
+                rule.preferred_input = input_id.to_string();
+                rule.is_ai_generated = false;
+                rule.is_learned = true;
+            }
+            None => self.config.rules.push(AppRule {
+                bundle_id: bundle_id.to_string(),
+                app_name: app_name.to_string(),
+                preferred_input: input_id.to_string(),
+                is_ai_generated: false,
+                input_mode: None,
+                remember_last_input: false,
+                is_learned: true,
+                timing: RuleTiming::default(),
+            }),
         }
-        self.rule_map.get(bundle_id).cloned()
+
+        self.rebuild_cache();
+        true
     }
 }
 
+/// 用户配置文件的默认位置
+pub fn default_config_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("smartime")
+        .join("config.json")
+}
+
+/// 展开以 `~/` 开头的路径
+pub fn expand_home_path(path: &str) -> PathBuf {
+    if let Some(rest) = path.strip_prefix("~/") {
+        if let Some(home) = dirs::home_dir() {
+            return home.join(rest);
+        }
+    }
+    PathBuf::from(path)
+}
+
 // 供 Tauri 状态管理的线程安全容器
-pub struct AppState {
+pub(crate) struct AppState {
     pub config: Mutex<ConfigManager>,
+    /// 焦点切换热路径无锁读取的规则
+    pub rules: RuleSnapshots,
     pub llm: Mutex<crate::llm::LLMClient>,
+    pub input_source: Arc<dyn InputSourceBackend>,
+    pub input_changes: Arc<InputChangeTracker>,
+    pub focus_queue: Arc<FocusQueue>,
+    pub rule_suggestions: Mutex<SuggestionEngine>,
+    pub switching_pause: SwitchingPause,
+    pub tray_menu: TrayMenuCache,
+    pub tray_icons: TrayIcons,
+    pub tray_refresh: TrayRefreshQueue,
     pub is_rescanning: AtomicBool,
 }
 
 impl AppState {
     pub fn new() -> Self {
+        let config = ConfigManager::new();
+        let focus_queue = Arc::new(FocusQueue::new(config.settle_delay()));
+        let input_changes = Arc::new(InputChangeTracker::default());
+        let rules = config.snapshots();
         Self {
-            config: Mutex::new(ConfigManager::new()),
+            config: Mutex::new(config),
+            rules,
             llm: Mutex::new(crate::llm::LLMClient::new()),
+            input_source: Arc::new(TrackedBackend::new(
+                crate::input_source::default_backend(),
+                input_changes.clone(),
+            )),
+            input_changes,
+            focus_queue,
+            rule_suggestions: Mutex::new(SuggestionEngine::default()),
+            switching_pause: SwitchingPause::default(),
+            tray_menu: TrayMenuCache::default(),
+            tray_icons: TrayIcons::default(),
+            tray_refresh: TrayRefreshQueue::default(),
             is_rescanning: AtomicBool::new(false),
         }
     }
@@ -145,12 +379,15 @@ impl AppState {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::TempDir;
 
     #[test]
     fn test_general_settings_default() {
         let defaults = GeneralSettings::default();
         assert!(!defaults.auto_start);
         assert!(!defaults.hide_dock_icon);
+        assert_eq!(defaults.shortcuts.pause_minutes, 15);
+        assert_eq!(defaults.shortcuts.toggle_global_switch, None);
     }
 
     #[test]
@@ -163,5 +400,137 @@ mod tests {
         }"#;
         let parsed: AppConfig = serde_json::from_str(raw).expect("deserialize AppConfig");
         assert_eq!(parsed.general, GeneralSettings::default());
+        assert_eq!(parsed.switching, SwitchingSettings::default());
+    }
+
+    #[test]
+    fn test_app_rule_input_mode_is_optional() {
+        let legacy: AppRule = serde_json::from_str(
+            r#"{"bundle_id":"com.apple.Terminal","app_name":"Terminal","preferred_input":"pinyin","is_ai_generated":false}"#,
+        )
+        .expect("deserialize legacy AppRule");
+        assert_eq!(legacy.input_mode, None);
+        assert!(!legacy.remember_last_input);
+        assert!(!legacy.is_learned);
+
+        let with_mode: AppRule = serde_json::from_str(
+            r#"{"bundle_id":"com.apple.Terminal","app_name":"Terminal","preferred_input":"pinyin","is_ai_generated":false,"input_mode":"ascii"}"#,
+        )
+        .expect("deserialize AppRule with input mode");
+        assert_eq!(with_mode.input_mode, Some(InputMode::Ascii));
+    }
+
+    #[test]
+    fn test_learn_input_updates_or_creates_learned_rules() {
+        let dir = TempDir::new("learn-test");
+        let file_path = dir.join("config.json");
+        let rule = |bundle_id: &str, remember_last_input: bool| AppRule {
+            bundle_id: bundle_id.to_string(),
+            app_name: bundle_id.to_string(),
+            preferred_input: "abc".to_string(),
+            is_ai_generated: true,
+            input_mode: None,
+            remember_last_input,
+            is_learned: false,
+            timing: RuleTiming::default(),
+        };
+        let mut manager = ConfigManager::with_config(
+            AppConfig {
+                rules: vec![
+                    rule("com.tencent.xinWeChat", true),
+                    rule("com.apple.Terminal", false),
+                ],
+                ..AppConfig::default()
+            },
+            file_path.clone(),
+        );
+
+        // 仅对开启学习的规则生效
+        assert!(manager.learn_input("com.tencent.xinWeChat", "WeChat", "pinyin"));
+        assert!(!manager.learn_input("com.tencent.xinWeChat", "WeChat", "pinyin"));
+        assert!(!manager.learn_input("com.apple.Terminal", "Terminal", "pinyin"));
+        let snapshot = manager.snapshots().load();
+        let learned = snapshot.resolve("com.tencent.xinWeChat").unwrap();
+        assert_eq!(learned.input_id, "pinyin");
+
+        // 全局开启后，没有规则的应用也会生成学习规则
+        let mut config = manager.get_config();
+        config.switching.remember_last_input = true;
+        manager.set_config(config).unwrap();
+        assert!(manager.learn_input("com.apple.Notes", "Notes", "pinyin"));
+        manager.save().unwrap();
+
+        let saved = ConfigManager::load_from_file(&file_path).unwrap();
+        let notes = saved
+            .rules
+            .iter()
+            .find(|rule| rule.bundle_id == "com.apple.Notes")
+            .unwrap();
+        assert!(notes.is_learned);
+        assert!(!notes.is_ai_generated);
+        assert!(
+            saved
+                .rules
+                .iter()
+                .find(|rule| rule.bundle_id == "com.tencent.xinWeChat")
+                .unwrap()
+                .is_learned
+        );
+    }
+
+    #[test]
+    fn test_upsert_manual_rule() {
+        let dir = TempDir::new("upsert-test");
+        let file_path = dir.join("config.json");
+        let mut manager = ConfigManager::with_config(
+            AppConfig {
+                rules: vec![AppRule {
+                    bundle_id: "com.tinyspeck.slackmacgap".to_string(),
+                    app_name: "Slack".to_string(),
+                    preferred_input: "abc".to_string(),
+                    is_ai_generated: true,
+                    input_mode: Some(InputMode::Native),
+                    remember_last_input: false,
+                    is_learned: false,
+                    timing: RuleTiming::default(),
+                }],
+                ..AppConfig::default()
+            },
+            file_path.clone(),
+        );
+
+        let updated = manager
+            .upsert_manual_rule("com.tinyspeck.slackmacgap", "Slack", "pinyin")
+            .unwrap();
+        assert_eq!(updated.preferred_input, "pinyin");
+        assert!(!updated.is_ai_generated);
+        assert_eq!(updated.input_mode, Some(InputMode::Native));
+
+        manager
+            .upsert_manual_rule("com.apple.Notes", "Notes", "pinyin")
+            .unwrap();
+        assert_eq!(manager.get_config().rules.len(), 2);
+        assert_eq!(
+            manager
+                .snapshots()
+                .load()
+                .resolve("com.apple.Notes")
+                .map(|rule| rule.input_id.as_str()),
+            Some("pinyin")
+        );
+    }
+
+    #[test]
+    fn test_expand_home_path() {
+        if let Some(home) = dirs::home_dir() {
+            assert_eq!(
+                expand_home_path("~/.secrets/ca.pem"),
+                home.join(".secrets/ca.pem")
+            );
+        }
+        assert_eq!(
+            expand_home_path("/etc/ssl/ca.pem"),
+            PathBuf::from("/etc/ssl/ca.pem")
+        );
     }
 }
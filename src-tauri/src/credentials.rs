@@ -0,0 +1,298 @@
+use crate::config::expand_home_path;
+use crate::error::{AppError, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::process::{Child, Command, Stdio};
+use std::sync::{Mutex, OnceLock};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(5);
+const CACHE_TTL: Duration = Duration::from_secs(300);
+
+static RESOLVED_KEYS: OnceLock<Mutex<HashMap<String, (String, Instant)>>> = OnceLock::new();
+
+/// `LLMConfig.api_key` 的取值来源
+///
+/// 引用形式在请求时才解析，前端只能看到引用的类型：
+/// - `env:OPENAI_API_KEY` 读取环境变量
+/// - `file:~/.secrets/openai` 读取文件内容
+/// - `cmd:pass show openai` 执行命令并读取 stdout 第一行
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiKeySource<'a> {
+    Literal(&'a str),
+    Env(&'a str),
+    File(&'a str),
+    Command(&'a str),
+}
+
+impl<'a> ApiKeySource<'a> {
+    pub fn parse(raw: &'a str) -> Self {
+        let trimmed = raw.trim();
+        if let Some(name) = trimmed.strip_prefix("env:") {
+            Self::Env(name.trim())
+        } else if let Some(path) = trimmed.strip_prefix("file:") {
+            Self::File(path.trim())
+        } else if let Some(command) = trimmed.strip_prefix("cmd:") {
+            Self::Command(command.trim())
+        } else {
+            Self::Literal(raw)
+        }
+    }
+
+    pub fn is_reference(&self) -> bool {
+        !matches!(self, Self::Literal(_))
+    }
+
+    /// 引用的类型，`cmd:` 的命令行中可能带有密钥，因此不展示引用内容
+    pub fn kind(&self) -> Option<&'static str> {
+        match self {
+            Self::Literal(_) => None,
+            Self::Env(_) => Some("env"),
+            Self::File(_) => Some("file"),
+            Self::Command(_) => Some("cmd"),
+        }
+    }
+}
+
+/// 解析 API Key，引用形式的结果会缓存一段时间，避免每次请求都执行命令
+pub fn resolve_api_key(raw: &str) -> Result<String> {
+    let source = ApiKeySource::parse(raw);
+    if let ApiKeySource::Literal(key) = source {
+        return Ok(key.to_string());
+    }
+
+    let cache = RESOLVED_KEYS.get_or_init(|| Mutex::new(HashMap::new()));
+    if let Ok(guard) = cache.lock() {
+        if let Some((key, resolved_at)) = guard.get(raw) {
+            if resolved_at.elapsed() < CACHE_TTL {
+                return Ok(key.clone());
+            }
+        }
+    }
+
+    let key = resolve_source(source, COMMAND_TIMEOUT)?;
+    if let Ok(mut guard) = cache.lock() {
+        guard.insert(raw.to_string(), (key.clone(), Instant::now()));
+    }
+    Ok(key)
+}
+
+/// 配置变更后清空缓存，确保新的引用立即生效
+pub fn clear_resolved_api_keys() {
+    if let Some(cache) = RESOLVED_KEYS.get() {
+        if let Ok(mut guard) = cache.lock() {
+            guard.clear();
+        }
+    }
+}
+
+fn resolve_source(source: ApiKeySource<'_>, timeout: Duration) -> Result<String> {
+    match source {
+        ApiKeySource::Literal(key) => Ok(key.to_string()),
+        ApiKeySource::Env(name) => {
+            let value =
+                std::env::var(name).map_err(|e| AppError::CredentialEnv(format!("{name}: {e}")))?;
+            non_empty_key(value).ok_or_else(|| AppError::CredentialEnv(format!("{name} is empty")))
+        }
+        ApiKeySource::File(path) => {
+            let path = expand_home_path(path);
+            let content = fs::read_to_string(&path)
+                .map_err(|e| AppError::CredentialFile(format!("{}: {e}", path.display())))?;
+            non_empty_key(content)
+                .ok_or_else(|| AppError::CredentialFile(format!("{} is empty", path.display())))
+        }
+        ApiKeySource::Command(command) => run_credential_command(command, timeout),
+    }
+}
+
+fn run_credential_command(command: &str, timeout: Duration) -> Result<String> {
+    if command.is_empty() {
+        return Err(AppError::CredentialCommand("command is empty".to_string()));
+    }
+
+    let mut shell = Command::new("sh");
+    shell
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    // 放到独立进程组，超时时连同命令派生的子进程一起结束，管道才会关闭
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut shell, 0);
+    let mut child = shell
+        .spawn()
+        .map_err(|e| AppError::CredentialCommand(format!("failed to start: {e}")))?;
+
+    // stdout 与 stderr 都在独立线程中读取，避免输出较多时子进程阻塞在管道上
+    let stdout = read_pipe(child.stdout.take());
+    let stderr = read_pipe(child.stderr.take());
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break Ok(status),
+            Ok(None) if Instant::now() >= deadline => {
+                kill_process_group(&mut child);
+                break Err(AppError::CredentialTimeout(format!(
+                    "command did not finish within {}s",
+                    timeout.as_secs_f32()
+                )));
+            }
+            Ok(None) => std::thread::sleep(Duration::from_millis(20)),
+            Err(e) => {
+                kill_process_group(&mut child);
+                break Err(AppError::CredentialCommand(format!("failed to wait: {e}")));
+            }
+        }
+    };
+
+    let output = stdout.join().unwrap_or_default();
+    let errors = stderr.join().unwrap_or_default();
+    let status = status?;
+    if !status.success() {
+        return Err(AppError::CredentialCommand(format!(
+            "exited with {status}: {}",
+            errors.trim()
+        )));
+    }
+
+    output
+        .lines()
+        .next()
+        .and_then(|line| non_empty_key(line.to_string()))
+        .ok_or_else(|| AppError::CredentialCommand("command printed no key".to_string()))
+}
+
+fn read_pipe<R: Read + Send + 'static>(pipe: Option<R>) -> JoinHandle<String> {
+    std::thread::spawn(move || {
+        let mut output = String::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_string(&mut output);
+        }
+        output
+    })
+}
+
+fn kill_process_group(child: &mut Child) {
+    // 进程组 ID 即 sh 的 PID
+    #[cfg(unix)]
+    if let Ok(pgid) = libc::pid_t::try_from(child.id()) {
+        unsafe {
+            libc::kill(-pgid, libc::SIGKILL);
+        }
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+fn non_empty_key(value: String) -> Option<String> {
+    let trimmed = value.trim();
+    (!trimmed.is_empty()).then(|| trimmed.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn test_parse_api_key_source() {
+        assert_eq!(
+            ApiKeySource::parse("sk-test"),
+            ApiKeySource::Literal("sk-test")
+        );
+        assert_eq!(
+            ApiKeySource::parse("env:OPENAI_API_KEY"),
+            ApiKeySource::Env("OPENAI_API_KEY")
+        );
+        assert_eq!(
+            ApiKeySource::parse("file:~/.secrets/openai"),
+            ApiKeySource::File("~/.secrets/openai")
+        );
+        assert_eq!(
+            ApiKeySource::parse(" cmd: pass show openai"),
+            ApiKeySource::Command("pass show openai")
+        );
+        assert!(!ApiKeySource::parse("sk-test").is_reference());
+    }
+
+    #[test]
+    fn test_resolve_env_and_file_references() {
+        std::env::set_var("SMARTIME_TEST_CREDENTIAL", " sk-env \n");
+        assert_eq!(
+            resolve_source(
+                ApiKeySource::Env("SMARTIME_TEST_CREDENTIAL"),
+                COMMAND_TIMEOUT
+            )
+            .unwrap(),
+            "sk-env"
+        );
+        assert!(matches!(
+            resolve_source(ApiKeySource::Env("SMARTIME_TEST_MISSING"), COMMAND_TIMEOUT),
+            Err(AppError::CredentialEnv(_))
+        ));
+
+        let dir = TempDir::new("credential-test");
+        let path = dir.join("api-key");
+        fs::write(&path, "sk-file\n").expect("write key file");
+        let raw = format!("file:{}", path.display());
+        assert_eq!(resolve_api_key(&raw).unwrap(), "sk-file");
+
+        // 缓存期内文件变化不会触发重新读取
+        fs::write(&path, "sk-rotated\n").expect("rewrite key file");
+        assert_eq!(resolve_api_key(&raw).unwrap(), "sk-file");
+
+        fs::remove_file(&path).expect("remove key file");
+        assert!(matches!(
+            resolve_source(ApiKeySource::parse(&raw), COMMAND_TIMEOUT),
+            Err(AppError::CredentialFile(_))
+        ));
+    }
+
+    #[test]
+    fn test_resolve_command_reference() {
+        assert_eq!(
+            resolve_source(
+                ApiKeySource::Command("printf 'sk-cmd\\nsecond line\\n'"),
+                COMMAND_TIMEOUT
+            )
+            .unwrap(),
+            "sk-cmd"
+        );
+        assert!(matches!(
+            resolve_source(ApiKeySource::Command("exit 3"), COMMAND_TIMEOUT),
+            Err(AppError::CredentialCommand(_))
+        ));
+        assert!(matches!(
+            resolve_source(ApiKeySource::Command("sleep 5"), Duration::from_millis(100)),
+            Err(AppError::CredentialTimeout(_))
+        ));
+    }
+
+    #[test]
+    fn test_command_reference_with_verbose_stderr() {
+        // 超过管道缓冲区大小的 stderr 输出不能让命令卡住
+        assert_eq!(
+            resolve_source(
+                ApiKeySource::Command("head -c 262144 /dev/zero >&2; echo sk-verbose"),
+                COMMAND_TIMEOUT
+            )
+            .unwrap(),
+            "sk-verbose"
+        );
+
+        // 超时后连同派生的子进程一起结束，不会等到 sleep 完成
+        let started = Instant::now();
+        assert!(matches!(
+            resolve_source(
+                ApiKeySource::Command("sleep 5 & sleep 5; wait"),
+                Duration::from_millis(100)
+            ),
+            Err(AppError::CredentialTimeout(_))
+        ));
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+}
@@ -0,0 +1,86 @@
+use crate::test_support::{skip_integration_test, TempDir};
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Command, Stdio};
+
+/// 测试用的私有 D-Bus 会话总线，不依赖桌面环境或已安装的输入法
+pub struct PrivateBus {
+    child: Child,
+    address: String,
+    // 总线进程结束后随结构体一起删除
+    _dir: TempDir,
+}
+
+impl PrivateBus {
+    /// 启动私有 `dbus-daemon`，未安装时返回 `None`，调用方应直接跳过测试；
+    /// 设置 `SMARTIME_REQUIRE_INTEGRATION=1` 时改为失败
+    pub fn start() -> Option<Self> {
+        let dir = TempDir::new("dbus-test");
+
+        let config_path = dir.join("session.conf");
+        fs::write(
+            &config_path,
+            format!(
+                r#"<!DOCTYPE busconfig PUBLIC "-//freedesktop//DTD D-Bus Bus Configuration 1.0//EN"
+ "http://www.freedesktop.org/standards/dbus/1.0/busconfig.dtd">
+<busconfig>
+  <type>session</type>
+  <listen>unix:dir={}</listen>
+  <auth>EXTERNAL</auth>
+  <policy context="default">
+    <allow send_destination="*" eavesdrop="true"/>
+    <allow eavesdrop="true"/>
+    <allow own="*"/>
+  </policy>
+</busconfig>
+"#,
+                dir.display()
+            ),
+        )
+        .expect("write bus config");
+
+        let mut child = match Command::new("dbus-daemon")
+            .arg(format!("--config-file={}", config_path.display()))
+            .arg("--nofork")
+            .arg("--print-address=1")
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(e) => {
+                skip_integration_test(&format!("dbus-daemon unavailable: {e}"));
+                return None;
+            }
+        };
+
+        let mut address = String::new();
+        if let Some(stdout) = child.stdout.take() {
+            let _ = BufReader::new(stdout).read_line(&mut address);
+        }
+        let address = address.trim().to_string();
+
+        let bus = Self {
+            child,
+            address,
+            _dir: dir,
+        };
+        if bus.address.is_empty() {
+            skip_integration_test("dbus-daemon printed no address");
+            return None;
+        }
+        Some(bus)
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+}
+
+impl Drop for PrivateBus {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
@@ -15,9 +15,30 @@ pub enum AppError {
     #[error("Input Source error: {0}")]
     InputSource(String),
 
+    #[error("Focus observer error: {0}")]
+    Observer(String),
+
     #[error("LLM error: {0}")]
     Llm(String),
 
+    #[error("LLM token budget exceeded: {0}")]
+    TokenBudget(String),
+
+    #[error("API key environment reference error: {0}")]
+    CredentialEnv(String),
+
+    #[error("API key file reference error: {0}")]
+    CredentialFile(String),
+
+    #[error("API key command reference error: {0}")]
+    CredentialCommand(String),
+
+    #[error("API key command timed out: {0}")]
+    CredentialTimeout(String),
+
+    #[error("Secret store error: {0}")]
+    SecretStore(String),
+
     #[error("Lock error: {0}")]
     Lock(String),
 
@@ -1,7 +1,12 @@
 use crate::config::GeneralSettings;
 use crate::error::{AppError, Result};
+use crate::shortcuts;
+use crate::tray_menu;
+#[cfg(target_os = "macos")]
 use std::fs;
+#[cfg(target_os = "macos")]
 use std::path::{Path, PathBuf};
+#[cfg(target_os = "macos")]
 use std::process::Command;
 use tauri::image::Image;
 use tauri::{AppHandle, Manager};
@@ -13,6 +18,7 @@ pub fn apply_general_settings(app: &AppHandle, settings: &GeneralSettings) -> Re
     apply_dock_visibility(app, settings.hide_dock_icon)?;
     sync_tray_icon_visibility(app, settings.hide_dock_icon)?;
     apply_auto_start(app, settings.auto_start)?;
+    shortcuts::register_shortcuts(app, &settings.shortcuts)?;
     Ok(())
 }
 
@@ -21,6 +27,14 @@ pub fn apply_general_settings_delta(
     previous: &GeneralSettings,
     next: &GeneralSettings,
 ) -> Result<()> {
+    // 快捷键最先处理，失败时恢复原绑定，其余设置保持不变
+    if previous.shortcuts != next.shortcuts {
+        if let Err(e) = shortcuts::register_shortcuts(app, &next.shortcuts) {
+            let _ = shortcuts::register_shortcuts(app, &previous.shortcuts);
+            return Err(e);
+        }
+    }
+
     if previous.hide_dock_icon != next.hide_dock_icon {
         apply_dock_visibility(app, next.hide_dock_icon)?;
         sync_tray_icon_visibility(app, next.hide_dock_icon)?;
@@ -44,8 +58,17 @@ fn apply_dock_visibility(app: &AppHandle, hide_dock_icon: bool) -> Result<()> {
         true
     };
 
-    app.set_dock_visibility(should_show_dock)
-        .map_err(|e| AppError::Config(format!("Failed to update dock visibility: {}", e)))
+    #[cfg(target_os = "macos")]
+    {
+        app.set_dock_visibility(should_show_dock)
+            .map_err(|e| AppError::Config(format!("Failed to update dock visibility: {}", e)))
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        let _ = should_show_dock; // Dock 仅存在于 macOS
+        Ok(())
+    }
 }
 
 fn sync_tray_icon_visibility(app: &AppHandle, visible: bool) -> Result<()> {
@@ -60,6 +83,9 @@ fn sync_tray_icon_visibility(app: &AppHandle, visible: bool) -> Result<()> {
                 .icon(icon)
                 .tooltip("SmartIME")
                 .icon_as_template(true)
+                // 左键打开主窗口，右键显示菜单
+                .show_menu_on_left_click(false)
+                .on_menu_event(|app, event| tray_menu::handle_menu_event(app, event.id().as_ref()))
                 .on_tray_icon_event(|tray, event| {
                     if let TrayIconEvent::Click {
                         button: MouseButton::Left,
@@ -77,6 +103,8 @@ fn sync_tray_icon_visibility(app: &AppHandle, visible: bool) -> Result<()> {
                 })
                 .build(app)
                 .map_err(|e| AppError::Config(format!("Failed to create tray icon: {}", e)))?;
+            // 菜单需要读取输入法列表，在后台构建
+            tray_menu::refresh(app);
         }
 
         if let Some(tray) = app.tray_by_id(TRAY_ICON_ID) {
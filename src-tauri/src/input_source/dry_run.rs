@@ -0,0 +1,80 @@
+use super::{InputMode, InputSource, InputSourceBackend, InputSourceListener};
+use crate::error::Result;
+
+/// 试运行后端：查询照常转发，切换只打印不执行
+pub struct DryRunBackend<'a> {
+    inner: &'a dyn InputSourceBackend,
+}
+
+impl<'a> DryRunBackend<'a> {
+    pub fn new(inner: &'a dyn InputSourceBackend) -> Self {
+        Self { inner }
+    }
+}
+
+impl InputSourceBackend for DryRunBackend<'_> {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn list(&self) -> Result<Vec<InputSource>> {
+        self.inner.list()
+    }
+
+    fn current(&self) -> Result<InputSource> {
+        self.inner.current()
+    }
+
+    fn select(&self, source_id: &str) -> Result<()> {
+        eprintln!("Dry run: would select input source {}", source_id);
+        Ok(())
+    }
+
+    fn subscribe(&self, listener: InputSourceListener) -> Result<()> {
+        self.inner.subscribe(listener)
+    }
+
+    fn requires_main_thread(&self) -> bool {
+        self.inner.requires_main_thread()
+    }
+
+    fn supports_input_mode(&self) -> bool {
+        self.inner.supports_input_mode()
+    }
+
+    fn current_input_mode(&self) -> Result<Option<InputMode>> {
+        self.inner.current_input_mode()
+    }
+
+    fn set_input_mode(&self, mode: InputMode) -> Result<()> {
+        eprintln!("Dry run: would set input mode {:?}", mode);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input_source::MemoryBackend;
+
+    #[test]
+    fn test_dry_run_backend_does_not_switch() {
+        let inner = MemoryBackend::new(
+            ["abc", "pinyin"]
+                .into_iter()
+                .map(|id| InputSource {
+                    id: id.to_string(),
+                    name: id.to_string(),
+                    category: "TISCategoryKeyboardInputSource".to_string(),
+                })
+                .collect(),
+        );
+        let backend = DryRunBackend::new(&inner);
+
+        backend.select("pinyin").unwrap();
+        backend.set_input_mode(InputMode::Ascii).unwrap();
+        assert_eq!(backend.current().unwrap().id, "abc");
+        assert!(inner.selections().is_empty());
+        assert_eq!(inner.current_input_mode().unwrap(), None);
+    }
+}
@@ -0,0 +1,387 @@
+use super::{InputMode, InputSource, InputSourceBackend, InputSourceListener};
+use crate::error::{AppError, Result};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use zbus::blocking::{Connection, Proxy};
+
+const FCITX_SERVICE: &str = "org.fcitx.Fcitx5";
+const FCITX_PATH: &str = "/controller";
+const FCITX_INTERFACE: &str = "org.fcitx.Fcitx.Controller1";
+
+// Controller1 没有切换信号，只能轮询当前输入法
+const POLL_INTERVAL: Duration = Duration::from_millis(300);
+
+// `State()` 的返回值：0 无输入焦点，1 未激活（英文），2 已激活
+const STATE_INACTIVE: i32 = 1;
+const STATE_ACTIVE: i32 = 2;
+
+/// `AvailableInputMethods` 的条目：(唯一名, 名称, 本地名称, 图标, 标签, 语言, 可配置)
+type AvailableInputMethod = (String, String, String, String, String, String, bool);
+
+/// 通过 `org.fcitx.Fcitx.Controller1` 控制 Fcitx5，支持英文/中文子模式
+pub struct FcitxBackend {
+    connection: Connection,
+    listeners: Arc<Mutex<Vec<InputSourceListener>>>,
+    watching: AtomicBool,
+}
+
+impl FcitxBackend {
+    /// 连接会话总线上的 Fcitx5
+    pub fn connect() -> Result<Self> {
+        let connection = Connection::session().map_err(|e| fcitx_error("connection", e))?;
+        let backend = Self::with_connection(connection);
+        // 确认 Fcitx5 正在运行
+        current_input_method(&backend.proxy()?)?;
+        Ok(backend)
+    }
+
+    pub fn with_connection(connection: Connection) -> Self {
+        Self {
+            connection,
+            listeners: Arc::new(Mutex::new(Vec::new())),
+            watching: AtomicBool::new(false),
+        }
+    }
+
+    fn proxy(&self) -> Result<Proxy<'static>> {
+        Proxy::new(&self.connection, FCITX_SERVICE, FCITX_PATH, FCITX_INTERFACE)
+            .map_err(|e| fcitx_error("proxy", e))
+    }
+}
+
+impl InputSourceBackend for FcitxBackend {
+    fn name(&self) -> &'static str {
+        "fcitx5"
+    }
+
+    fn list(&self) -> Result<Vec<InputSource>> {
+        list_group_input_methods(&self.proxy()?)
+    }
+
+    fn current(&self) -> Result<InputSource> {
+        let proxy = self.proxy()?;
+        let current = current_input_method(&proxy)?;
+        Ok(find_input_method(&proxy, &current))
+    }
+
+    fn select(&self, source_id: &str) -> Result<()> {
+        let proxy = self.proxy()?;
+        // Fcitx5 对未知输入法静默忽略，先确认目标在当前分组中
+        if !list_group_input_methods(&proxy)?
+            .iter()
+            .any(|source| source.id == source_id)
+        {
+            return Err(AppError::InputSource(format!(
+                "Input method not in current Fcitx5 group: {source_id}"
+            )));
+        }
+
+        proxy
+            .call::<_, _, ()>("SetCurrentIM", &(source_id,))
+            .map_err(|e| fcitx_error("SetCurrentIM", e))
+    }
+
+    fn subscribe(&self, listener: InputSourceListener) -> Result<()> {
+        self.listeners
+            .lock()
+            .map_err(|e| AppError::Lock(e.to_string()))?
+            .push(listener);
+        if self.watching.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        let proxy = self.proxy()?;
+        let mut last = current_input_method(&proxy).ok();
+        let listeners = self.listeners.clone();
+
+        std::thread::spawn(move || loop {
+            std::thread::sleep(POLL_INTERVAL);
+            let Ok(current) = current_input_method(&proxy) else {
+                continue;
+            };
+            if last.as_deref() == Some(current.as_str()) {
+                continue;
+            }
+            last = Some(current.clone());
+
+            let source = find_input_method(&proxy, &current);
+            let listeners = match listeners.lock() {
+                Ok(guard) => guard.clone(),
+                Err(_) => continue,
+            };
+            for listener in listeners {
+                listener(&source);
+            }
+        });
+
+        Ok(())
+    }
+
+    fn supports_input_mode(&self) -> bool {
+        true
+    }
+
+    fn current_input_mode(&self) -> Result<Option<InputMode>> {
+        let state: i32 = self
+            .proxy()?
+            .call("State", &())
+            .map_err(|e| fcitx_error("State", e))?;
+        Ok(match state {
+            STATE_ACTIVE => Some(InputMode::Native),
+            STATE_INACTIVE => Some(InputMode::Ascii),
+            _ => None,
+        })
+    }
+
+    fn set_input_mode(&self, mode: InputMode) -> Result<()> {
+        let method = match mode {
+            InputMode::Native => "Activate",
+            InputMode::Ascii => "Deactivate",
+        };
+        self.proxy()?
+            .call::<_, _, ()>(method, &())
+            .map_err(|e| fcitx_error(method, e))
+    }
+}
+
+fn current_input_method(proxy: &Proxy<'_>) -> Result<String> {
+    proxy
+        .call("CurrentInputMethod", &())
+        .map_err(|e| fcitx_error("CurrentInputMethod", e))
+}
+
+/// 当前输入法分组中的输入法，按分组内顺序返回
+fn list_group_input_methods(proxy: &Proxy<'_>) -> Result<Vec<InputSource>> {
+    let group: String = proxy
+        .call("CurrentInputMethodGroup", &())
+        .map_err(|e| fcitx_error("CurrentInputMethodGroup", e))?;
+    let (_layout, items): (String, Vec<(String, String)>) = proxy
+        .call("InputMethodGroupInfo", &(group.as_str(),))
+        .map_err(|e| fcitx_error("InputMethodGroupInfo", e))?;
+    let available = available_input_methods(proxy)?;
+
+    Ok(items
+        .into_iter()
+        .map(|(name, _layout)| to_input_source(&name, available.get(&name)))
+        .collect())
+}
+
+fn available_input_methods(proxy: &Proxy<'_>) -> Result<HashMap<String, AvailableInputMethod>> {
+    let methods: Vec<AvailableInputMethod> = proxy
+        .call("AvailableInputMethods", &())
+        .map_err(|e| fcitx_error("AvailableInputMethods", e))?;
+    Ok(methods
+        .into_iter()
+        .map(|method| (method.0.clone(), method))
+        .collect())
+}
+
+fn find_input_method(proxy: &Proxy<'_>, name: &str) -> InputSource {
+    let available = available_input_methods(proxy).unwrap_or_default();
+    to_input_source(name, available.get(name))
+}
+
+/// id 为输入法唯一名，category 为语言代码
+fn to_input_source(name: &str, method: Option<&AvailableInputMethod>) -> InputSource {
+    let display_name = method
+        .map(|method| method.1.trim())
+        .filter(|display_name| !display_name.is_empty())
+        .unwrap_or(name);
+    InputSource {
+        id: name.to_string(),
+        name: display_name.to_string(),
+        category: method.map(|method| method.5.clone()).unwrap_or_default(),
+    }
+}
+
+fn fcitx_error(context: &str, e: zbus::Error) -> AppError {
+    AppError::InputSource(format!("Fcitx5 {context} failed: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dbus_test_support::PrivateBus;
+    use std::sync::mpsc;
+    use zbus::blocking::connection;
+
+    /// 模拟 Fcitx5 Controller1 的最小接口
+    struct FakeController {
+        // (唯一名, 名称, 语言)
+        input_methods: Vec<(&'static str, &'static str, &'static str)>,
+        group: Vec<&'static str>,
+        current: Arc<Mutex<String>>,
+        state: i32,
+    }
+
+    #[zbus::interface(name = "org.fcitx.Fcitx.Controller1")]
+    impl FakeController {
+        fn current_input_method(&self) -> String {
+            self.current.lock().unwrap().clone()
+        }
+
+        #[zbus(name = "SetCurrentIM")]
+        fn set_current_im(&mut self, name: String) {
+            // 与 Fcitx5 一致：未知输入法静默忽略
+            if self.group.contains(&name.as_str()) {
+                *self.current.lock().unwrap() = name;
+            }
+        }
+
+        fn current_input_method_group(&self) -> String {
+            "Default".to_string()
+        }
+
+        fn input_method_group_info(&self, name: String) -> (String, Vec<(String, String)>) {
+            assert_eq!(name, "Default");
+            (
+                "us".to_string(),
+                self.group
+                    .iter()
+                    .map(|im| (im.to_string(), String::new()))
+                    .collect(),
+            )
+        }
+
+        fn available_input_methods(&self) -> Vec<AvailableInputMethod> {
+            self.input_methods
+                .iter()
+                .map(|(unique, name, language)| {
+                    (
+                        unique.to_string(),
+                        name.to_string(),
+                        String::new(),
+                        String::new(),
+                        String::new(),
+                        language.to_string(),
+                        false,
+                    )
+                })
+                .collect()
+        }
+
+        fn state(&self) -> i32 {
+            self.state
+        }
+
+        fn activate(&mut self) {
+            self.state = STATE_ACTIVE;
+        }
+
+        fn deactivate(&mut self) {
+            self.state = STATE_INACTIVE;
+        }
+    }
+
+    fn start_fake_fcitx(bus: &PrivateBus, current: Arc<Mutex<String>>) -> Connection {
+        let controller = FakeController {
+            input_methods: vec![
+                ("keyboard-us", "English (US)", "en"),
+                ("pinyin", "Pinyin", "zh_CN"),
+                ("mozc", "Mozc", "ja"),
+            ],
+            group: vec!["keyboard-us", "pinyin"],
+            current,
+            state: STATE_INACTIVE,
+        };
+        connection::Builder::address(bus.address())
+            .expect("bus address")
+            .name(FCITX_SERVICE)
+            .expect("well-known name")
+            .serve_at(FCITX_PATH, controller)
+            .expect("serve fake controller")
+            .build()
+            .expect("fake Fcitx5 connection")
+    }
+
+    fn connect_backend(bus: &PrivateBus) -> FcitxBackend {
+        let connection = connection::Builder::address(bus.address())
+            .expect("bus address")
+            .build()
+            .expect("client connection");
+        FcitxBackend::with_connection(connection)
+    }
+
+    #[test]
+    fn test_fcitx_backend_lists_current_group_and_switches() {
+        let Some(bus) = PrivateBus::start() else {
+            return;
+        };
+        let current = Arc::new(Mutex::new("keyboard-us".to_string()));
+        let _service = start_fake_fcitx(&bus, current);
+        let backend = connect_backend(&bus);
+
+        // 仅返回当前分组内的输入法
+        assert_eq!(
+            backend.list().unwrap(),
+            vec![
+                InputSource {
+                    id: "keyboard-us".to_string(),
+                    name: "English (US)".to_string(),
+                    category: "en".to_string(),
+                },
+                InputSource {
+                    id: "pinyin".to_string(),
+                    name: "Pinyin".to_string(),
+                    category: "zh_CN".to_string(),
+                },
+            ]
+        );
+
+        backend.select("pinyin").unwrap();
+        assert_eq!(backend.current().unwrap().name, "Pinyin");
+        assert!(matches!(
+            backend.select("mozc"),
+            Err(AppError::InputSource(_))
+        ));
+    }
+
+    #[test]
+    fn test_fcitx_backend_sets_ascii_and_native_modes() {
+        let Some(bus) = PrivateBus::start() else {
+            return;
+        };
+        let _service = start_fake_fcitx(&bus, Arc::new(Mutex::new("pinyin".to_string())));
+        let backend = connect_backend(&bus);
+
+        assert!(backend.supports_input_mode());
+        assert_eq!(
+            backend.current_input_mode().unwrap(),
+            Some(InputMode::Ascii)
+        );
+        backend.set_input_mode(InputMode::Native).unwrap();
+        assert_eq!(
+            backend.current_input_mode().unwrap(),
+            Some(InputMode::Native)
+        );
+        backend.set_input_mode(InputMode::Ascii).unwrap();
+        assert_eq!(
+            backend.current_input_mode().unwrap(),
+            Some(InputMode::Ascii)
+        );
+    }
+
+    #[test]
+    fn test_fcitx_backend_polls_for_external_switches() {
+        let Some(bus) = PrivateBus::start() else {
+            return;
+        };
+        let current = Arc::new(Mutex::new("keyboard-us".to_string()));
+        let _service = start_fake_fcitx(&bus, current.clone());
+        let backend = connect_backend(&bus);
+
+        let (tx, rx) = mpsc::channel();
+        let tx = Mutex::new(tx);
+        backend
+            .subscribe(Arc::new(move |source: &InputSource| {
+                let _ = tx.lock().unwrap().send(source.id.clone());
+            }))
+            .unwrap();
+
+        // 模拟用户通过 Fcitx5 热键切换
+        *current.lock().unwrap() = "pinyin".to_string();
+        assert_eq!(rx.recv_timeout(Duration::from_secs(5)).unwrap(), "pinyin");
+    }
+}
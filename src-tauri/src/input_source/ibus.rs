@@ -0,0 +1,332 @@
+use super::{InputSource, InputSourceBackend, InputSourceListener};
+use crate::error::{AppError, Result};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use zbus::blocking::{connection, Connection, Proxy};
+use zbus::zvariant::{OwnedValue, Value};
+
+const IBUS_SERVICE: &str = "org.freedesktop.IBus";
+const IBUS_PATH: &str = "/org/freedesktop/IBus";
+const IBUS_INTERFACE: &str = "org.freedesktop.IBus";
+
+// IBusEngineDesc 序列化后的字段位置：(类型名, 附加数据, name, longname, description, language, ...)
+const ENGINE_DESC_TYPE: &str = "IBusEngineDesc";
+const ENGINE_NAME_FIELD: usize = 2;
+const ENGINE_LONGNAME_FIELD: usize = 3;
+const ENGINE_LANGUAGE_FIELD: usize = 5;
+
+/// 通过 IBus 自身的 D-Bus 总线枚举与切换引擎
+pub struct IbusBackend {
+    connection: Connection,
+    listeners: Arc<Mutex<Vec<InputSourceListener>>>,
+    watching: AtomicBool,
+}
+
+impl IbusBackend {
+    /// 连接当前用户的 IBus 守护进程，优先使用 `IBUS_ADDRESS`
+    pub fn connect() -> Result<Self> {
+        let builder = match std::env::var("IBUS_ADDRESS") {
+            Ok(address) if !address.trim().is_empty() => {
+                connection::Builder::address(address.trim())
+            }
+            _ => connection::Builder::ibus(),
+        }
+        .map_err(|e| ibus_error("address lookup", e))?;
+
+        let backend =
+            Self::with_connection(builder.build().map_err(|e| ibus_error("connection", e))?);
+        // 确认守护进程确实在线，避免残留地址导致后续调用全部失败
+        backend.proxy()?;
+        Ok(backend)
+    }
+
+    pub fn with_connection(connection: Connection) -> Self {
+        Self {
+            connection,
+            listeners: Arc::new(Mutex::new(Vec::new())),
+            watching: AtomicBool::new(false),
+        }
+    }
+
+    fn proxy(&self) -> Result<Proxy<'static>> {
+        Proxy::new(&self.connection, IBUS_SERVICE, IBUS_PATH, IBUS_INTERFACE)
+            .map_err(|e| ibus_error("proxy", e))
+    }
+}
+
+impl InputSourceBackend for IbusBackend {
+    fn name(&self) -> &'static str {
+        "ibus"
+    }
+
+    fn list(&self) -> Result<Vec<InputSource>> {
+        list_engines(&self.proxy()?)
+    }
+
+    fn current(&self) -> Result<InputSource> {
+        let engine: OwnedValue = self
+            .proxy()?
+            .call("GetGlobalEngine", &())
+            .map_err(|e| ibus_error("GetGlobalEngine", e))?;
+        engine_desc_to_input_source(&engine)
+            .ok_or_else(|| AppError::InputSource("Failed to parse IBus global engine".to_string()))
+    }
+
+    fn select(&self, source_id: &str) -> Result<()> {
+        self.proxy()?
+            .call::<_, _, ()>("SetGlobalEngine", &(source_id,))
+            .map_err(|e| ibus_error("SetGlobalEngine", e))
+    }
+
+    fn subscribe(&self, listener: InputSourceListener) -> Result<()> {
+        self.listeners
+            .lock()
+            .map_err(|e| AppError::Lock(e.to_string()))?
+            .push(listener);
+        if self.watching.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        // 先注册匹配规则再返回，保证订阅之后的切换不会漏掉
+        let proxy = self.proxy()?;
+        let signals = proxy
+            .receive_signal("GlobalEngineChanged")
+            .map_err(|e| ibus_error("GlobalEngineChanged subscription", e))?;
+        let listeners = self.listeners.clone();
+
+        std::thread::spawn(move || {
+            for message in signals {
+                let Ok(engine_name) = message.body().deserialize::<String>() else {
+                    continue;
+                };
+                let source = list_engines(&proxy)
+                    .ok()
+                    .and_then(|engines| engines.into_iter().find(|e| e.id == engine_name))
+                    .unwrap_or_else(|| InputSource {
+                        id: engine_name.clone(),
+                        name: engine_name,
+                        category: String::new(),
+                    });
+
+                let listeners = match listeners.lock() {
+                    Ok(guard) => guard.clone(),
+                    Err(_) => continue,
+                };
+                for listener in listeners {
+                    listener(&source);
+                }
+            }
+        });
+
+        Ok(())
+    }
+}
+
+/// 优先返回用户启用的引擎，旧版本 IBus 不支持时退回全部引擎
+fn list_engines(proxy: &Proxy<'_>) -> Result<Vec<InputSource>> {
+    let engines = match proxy.call::<_, _, Vec<OwnedValue>>("ListActiveEngines", &()) {
+        Ok(engines) if !engines.is_empty() => engines,
+        _ => proxy
+            .call("ListEngines", &())
+            .map_err(|e| ibus_error("ListEngines", e))?,
+    };
+
+    let mut seen = HashSet::new();
+    Ok(engines
+        .iter()
+        .filter_map(|engine| engine_desc_to_input_source(engine))
+        .filter(|source| seen.insert(source.id.clone()))
+        .collect())
+}
+
+/// 把序列化的 IBusEngineDesc 转成 `InputSource`：id 为引擎名，category 为语言
+fn engine_desc_to_input_source(value: &Value<'_>) -> Option<InputSource> {
+    let fields = match value {
+        Value::Value(inner) => return engine_desc_to_input_source(inner),
+        Value::Structure(structure) => structure.fields(),
+        _ => return None,
+    };
+
+    let string_at = |index: usize| match fields.get(index) {
+        Some(Value::Str(value)) => Some(value.as_str().to_string()),
+        _ => None,
+    };
+
+    if string_at(0).as_deref() != Some(ENGINE_DESC_TYPE) {
+        return None;
+    }
+
+    let id = string_at(ENGINE_NAME_FIELD).filter(|id| !id.is_empty())?;
+    let name = string_at(ENGINE_LONGNAME_FIELD)
+        .filter(|name| !name.trim().is_empty())
+        .unwrap_or_else(|| id.clone());
+    let category = string_at(ENGINE_LANGUAGE_FIELD).unwrap_or_default();
+
+    Some(InputSource { id, name, category })
+}
+
+fn ibus_error(context: &str, e: zbus::Error) -> AppError {
+    AppError::InputSource(format!("IBus {context} failed: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dbus_test_support::PrivateBus;
+    use std::collections::HashMap;
+    use std::sync::mpsc;
+    use std::time::Duration;
+    use zbus::object_server::SignalEmitter;
+    use zbus::zvariant::StructureBuilder;
+
+    /// 模拟 IBus 守护进程的最小接口
+    struct FakeIbus {
+        engines: Vec<(&'static str, &'static str, &'static str)>,
+        global_engine: String,
+    }
+
+    impl FakeIbus {
+        fn engine(&self, name: &str) -> Option<OwnedValue> {
+            self.engines
+                .iter()
+                .find(|(engine_name, _, _)| *engine_name == name)
+                .map(|(name, longname, language)| engine_desc(name, longname, language))
+        }
+    }
+
+    #[zbus::interface(name = "org.freedesktop.IBus")]
+    impl FakeIbus {
+        fn list_active_engines(&self) -> Vec<OwnedValue> {
+            self.engines
+                .iter()
+                .map(|(name, longname, language)| engine_desc(name, longname, language))
+                .collect()
+        }
+
+        fn get_global_engine(&self) -> zbus::fdo::Result<OwnedValue> {
+            self.engine(&self.global_engine)
+                .ok_or_else(|| zbus::fdo::Error::Failed("No global engine".to_string()))
+        }
+
+        async fn set_global_engine(
+            &mut self,
+            #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+            engine_name: String,
+        ) -> zbus::fdo::Result<()> {
+            if self.engine(&engine_name).is_none() {
+                return Err(zbus::fdo::Error::Failed(format!(
+                    "Unknown engine {engine_name}"
+                )));
+            }
+            self.global_engine = engine_name.clone();
+            Self::global_engine_changed(&emitter, &engine_name).await?;
+            Ok(())
+        }
+
+        #[zbus(signal)]
+        async fn global_engine_changed(
+            emitter: &SignalEmitter<'_>,
+            engine_name: &str,
+        ) -> zbus::Result<()>;
+    }
+
+    fn engine_desc(name: &str, longname: &str, language: &str) -> OwnedValue {
+        let attachments: HashMap<String, Value<'static>> = HashMap::new();
+        let mut builder = StructureBuilder::new()
+            .add_field(ENGINE_DESC_TYPE)
+            .add_field(attachments)
+            .add_field(name.to_string())
+            .add_field(longname.to_string())
+            .add_field(String::new())
+            .add_field(language.to_string());
+        // license, author, icon, layout
+        for _ in 0..4 {
+            builder = builder.add_field(String::new());
+        }
+        builder = builder.add_field(0u32);
+        // hotkeys, symbol, setup, layout_variant, layout_option, version, textdomain, icon_prop_key
+        for _ in 0..8 {
+            builder = builder.add_field(String::new());
+        }
+        OwnedValue::try_from(Value::from(builder.build().expect("engine desc")))
+            .expect("owned engine desc")
+    }
+
+    fn start_fake_ibus(bus: &PrivateBus) -> Connection {
+        let service = FakeIbus {
+            engines: vec![
+                ("xkb:us::eng", "English (US)", "en"),
+                ("libpinyin", "Intelligent Pinyin", "zh"),
+                ("anthy", "Anthy", "ja"),
+            ],
+            global_engine: "xkb:us::eng".to_string(),
+        };
+        connection::Builder::address(bus.address())
+            .expect("bus address")
+            .name(IBUS_SERVICE)
+            .expect("well-known name")
+            .serve_at(IBUS_PATH, service)
+            .expect("serve fake IBus")
+            .build()
+            .expect("fake IBus connection")
+    }
+
+    fn connect_backend(bus: &PrivateBus) -> IbusBackend {
+        let connection = connection::Builder::address(bus.address())
+            .expect("bus address")
+            .build()
+            .expect("client connection");
+        IbusBackend::with_connection(connection)
+    }
+
+    #[test]
+    fn test_engine_desc_to_input_source() {
+        let source = engine_desc_to_input_source(&engine_desc("libpinyin", "", "zh")).unwrap();
+        assert_eq!(source.id, "libpinyin");
+        // longname 为空时回退到引擎名
+        assert_eq!(source.name, "libpinyin");
+        assert_eq!(source.category, "zh");
+        assert!(engine_desc_to_input_source(&Value::from("libpinyin")).is_none());
+    }
+
+    #[test]
+    fn test_ibus_backend_against_fake_daemon() {
+        let Some(bus) = PrivateBus::start() else {
+            return;
+        };
+        let _service = start_fake_ibus(&bus);
+        let backend = connect_backend(&bus);
+
+        let sources = backend.list().unwrap();
+        assert_eq!(
+            sources
+                .iter()
+                .map(|source| source.id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["xkb:us::eng", "libpinyin", "anthy"]
+        );
+        assert_eq!(sources[1].name, "Intelligent Pinyin");
+        assert_eq!(sources[1].category, "zh");
+        assert_eq!(backend.current().unwrap().id, "xkb:us::eng");
+
+        let (tx, rx) = mpsc::channel();
+        let tx = Mutex::new(tx);
+        backend
+            .subscribe(Arc::new(move |source: &InputSource| {
+                let _ = tx.lock().unwrap().send(source.clone());
+            }))
+            .unwrap();
+
+        backend.select("libpinyin").unwrap();
+        assert_eq!(backend.current().unwrap().id, "libpinyin");
+        let changed = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(changed.id, "libpinyin");
+        assert_eq!(changed.name, "Intelligent Pinyin");
+
+        assert!(matches!(
+            backend.select("missing-engine"),
+            Err(AppError::InputSource(_))
+        ));
+    }
+}
@@ -1,27 +1,107 @@
 #![allow(deprecated)] // Keep using the existing cocoa/objc bridge until the project migrates to objc2.
 
+use super::{InputSource, InputSourceBackend, InputSourceListener};
 use crate::error::{AppError, Result};
 use cocoa::base::{id, nil};
-use cocoa::foundation::NSString;
+use cocoa::foundation::{NSAutoreleasePool, NSString};
 use core_foundation::array::{CFArrayGetCount, CFArrayGetValueAtIndex, CFArrayRef};
 use core_foundation::base::{CFRelease, CFTypeRef, TCFType};
 use core_foundation::boolean::{CFBoolean, CFBooleanRef};
 use core_foundation::dictionary::CFDictionaryRef;
 use core_foundation::number::{CFNumber, CFNumberRef};
 use core_foundation::string::{CFString, CFStringRef};
+use objc::declare::ClassDecl;
+use objc::runtime::{Object, Sel};
 use objc::{class, msg_send, sel, sel_impl};
-use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use std::ffi::{c_void, CStr};
 use std::io::Cursor;
 use std::process::Command;
 use std::ptr;
+use std::sync::{Mutex, Once};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct InputSource {
-    pub id: String,
-    pub name: String,
-    pub category: String,
+static LISTENERS: Mutex<Vec<InputSourceListener>> = Mutex::new(Vec::new());
+static REGISTER_OBSERVER: Once = Once::new();
+
+/// 基于 Carbon TIS 的输入法后端，所有调用都需要在主线程执行
+pub struct TisBackend;
+
+impl InputSourceBackend for TisBackend {
+    fn name(&self) -> &'static str {
+        "macos-tis"
+    }
+
+    fn list(&self) -> Result<Vec<InputSource>> {
+        get_system_input_sources()
+    }
+
+    fn current(&self) -> Result<InputSource> {
+        get_current_input_source()
+    }
+
+    fn select(&self, source_id: &str) -> Result<()> {
+        select_input_source(source_id)
+    }
+
+    fn subscribe(&self, listener: InputSourceListener) -> Result<()> {
+        LISTENERS
+            .lock()
+            .map_err(|e| AppError::Lock(e.to_string()))?
+            .push(listener);
+        REGISTER_OBSERVER.call_once(register_selection_observer);
+        Ok(())
+    }
+
+    fn requires_main_thread(&self) -> bool {
+        true
+    }
+}
+
+/// 监听系统分发的输入法切换通知
+fn register_selection_observer() {
+    unsafe {
+        let superclass = class!(NSObject);
+        let Some(mut decl) = ClassDecl::new("RustInputSourceObserver", superclass) else {
+            eprintln!("Failed to declare input source observer class");
+            return;
+        };
+        decl.add_method(
+            sel!(inputSourceChanged:),
+            input_source_changed_impl as extern "C" fn(&Object, Sel, id),
+        );
+        decl.register();
+
+        let pool = NSAutoreleasePool::new(nil);
+        let observer: id = msg_send![class!(RustInputSourceObserver), new];
+        // 单例，故意不释放
+        let _ = Box::leak(Box::new(observer));
+
+        let center: id = msg_send![class!(NSDistributedNotificationCenter), defaultCenter];
+        let notification_name = NSString::alloc(nil)
+            .init_str("com.apple.Carbon.TISNotifySelectedKeyboardInputSourceChanged");
+        let _: () = msg_send![center,
+            addObserver: observer
+            selector: sel!(inputSourceChanged:)
+            name: notification_name
+            object: nil
+        ];
+
+        pool.drain();
+    }
+}
+
+extern "C" fn input_source_changed_impl(_this: &Object, _cmd: Sel, _notification: id) {
+    // 通知在主线程派发，可以直接调用 TIS
+    let Ok(current) = get_current_input_source() else {
+        return;
+    };
+    let listeners = match LISTENERS.lock() {
+        Ok(guard) => guard.clone(),
+        Err(_) => return,
+    };
+    for listener in listeners {
+        listener(&current);
+    }
 }
 
 #[repr(C)]
@@ -63,7 +143,7 @@ extern "C" {
 }
 
 /// 获取当前系统所有已启用的键盘输入法
-pub fn get_system_input_sources() -> Result<Vec<InputSource>> {
+fn get_system_input_sources() -> Result<Vec<InputSource>> {
     let mut sources = Vec::new();
     let mut seen_ids = HashSet::new();
     let menu_enabled = load_menu_enabled_sources();
@@ -110,7 +190,7 @@ pub fn get_system_input_sources() -> Result<Vec<InputSource>> {
 }
 
 /// 获取当前系统正在使用的键盘输入法
-pub fn get_current_input_source() -> Result<InputSource> {
+fn get_current_input_source() -> Result<InputSource> {
     let preferred_language = preferred_language_identifier();
 
     unsafe {
@@ -133,7 +213,7 @@ pub fn get_current_input_source() -> Result<InputSource> {
 }
 
 /// 切换到指定的输入法 ID
-pub fn select_input_source(source_id: &str) -> Result<()> {
+fn select_input_source(source_id: &str) -> Result<()> {
     unsafe {
         let source_list = TISCreateInputSourceList(ptr::null(), true);
         if source_list.is_null() {
@@ -0,0 +1,192 @@
+use super::{InputMode, InputSource, InputSourceBackend, InputSourceListener};
+use crate::error::{AppError, Result};
+use std::sync::Mutex;
+
+#[derive(Default)]
+struct MemoryState {
+    sources: Vec<InputSource>,
+    current: Option<String>,
+    mode: Option<InputMode>,
+    listeners: Vec<InputSourceListener>,
+    selections: Vec<String>,
+    failing_ids: Vec<String>,
+}
+
+/// 纯内存的输入法后端，测试中用于脚本化地模拟用户切换与切换失败
+#[derive(Default)]
+pub struct MemoryBackend {
+    state: Mutex<MemoryState>,
+}
+
+impl MemoryBackend {
+    /// 以第一个输入法作为当前输入法
+    pub fn new(sources: Vec<InputSource>) -> Self {
+        let current = sources.first().map(|source| source.id.clone());
+        Self {
+            state: Mutex::new(MemoryState {
+                sources,
+                current,
+                ..MemoryState::default()
+            }),
+        }
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, MemoryState>> {
+        self.state.lock().map_err(|e| AppError::Lock(e.to_string()))
+    }
+
+    fn switch_to(&self, source_id: &str) -> Result<()> {
+        let (source, listeners) = {
+            let mut state = self.lock()?;
+            let source = state
+                .sources
+                .iter()
+                .find(|source| source.id == source_id)
+                .cloned()
+                .ok_or_else(|| {
+                    AppError::InputSource(format!("Input source not found: {source_id}"))
+                })?;
+            if state.current.as_deref() == Some(source_id) {
+                return Ok(());
+            }
+            state.current = Some(source.id.clone());
+            (source, state.listeners.clone())
+        };
+
+        // 在锁外通知，允许回调再次访问后端
+        for listener in listeners {
+            listener(&source);
+        }
+        Ok(())
+    }
+
+    /// 模拟用户手动切换输入法（不计入 `selections`）
+    pub fn simulate_user_switch(&self, source_id: &str) -> Result<()> {
+        self.switch_to(source_id)
+    }
+
+    /// 之后对该 ID 的 `select` 调用都会失败
+    pub fn fail_selection_of(&self, source_id: &str) {
+        if let Ok(mut state) = self.lock() {
+            state.failing_ids.push(source_id.to_string());
+        }
+    }
+
+    /// 通过 `select` 发起的切换记录
+    pub fn selections(&self) -> Vec<String> {
+        self.lock()
+            .map(|state| state.selections.clone())
+            .unwrap_or_default()
+    }
+}
+
+impl InputSourceBackend for MemoryBackend {
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+
+    fn list(&self) -> Result<Vec<InputSource>> {
+        Ok(self.lock()?.sources.clone())
+    }
+
+    fn current(&self) -> Result<InputSource> {
+        let state = self.lock()?;
+        state
+            .current
+            .as_ref()
+            .and_then(|id| state.sources.iter().find(|source| &source.id == id))
+            .cloned()
+            .ok_or_else(|| AppError::InputSource("No current input source".to_string()))
+    }
+
+    fn select(&self, source_id: &str) -> Result<()> {
+        {
+            let mut state = self.lock()?;
+            if state.failing_ids.iter().any(|id| id == source_id) {
+                return Err(AppError::InputSource(format!(
+                    "Simulated selection failure: {source_id}"
+                )));
+            }
+            state.selections.push(source_id.to_string());
+        }
+
+        self.switch_to(source_id)
+    }
+
+    fn subscribe(&self, listener: InputSourceListener) -> Result<()> {
+        self.lock()?.listeners.push(listener);
+        Ok(())
+    }
+
+    fn supports_input_mode(&self) -> bool {
+        true
+    }
+
+    fn current_input_mode(&self) -> Result<Option<InputMode>> {
+        Ok(self.lock()?.mode)
+    }
+
+    fn set_input_mode(&self, mode: InputMode) -> Result<()> {
+        self.lock()?.mode = Some(mode);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn source(id: &str, name: &str) -> InputSource {
+        InputSource {
+            id: id.to_string(),
+            name: name.to_string(),
+            category: "TISCategoryKeyboardInputSource".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_memory_backend_select_and_notify() {
+        let backend = MemoryBackend::new(vec![
+            source("com.apple.keylayout.ABC", "ABC"),
+            source("com.apple.inputmethod.SCIM.ITABC", "简体拼音"),
+        ]);
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        backend
+            .subscribe(Arc::new(move |source: &InputSource| {
+                sink.lock().unwrap().push(source.id.clone());
+            }))
+            .unwrap();
+
+        assert_eq!(backend.current().unwrap().id, "com.apple.keylayout.ABC");
+        backend.select("com.apple.inputmethod.SCIM.ITABC").unwrap();
+        // 已是当前输入法时不重复通知
+        backend.select("com.apple.inputmethod.SCIM.ITABC").unwrap();
+        backend
+            .simulate_user_switch("com.apple.keylayout.ABC")
+            .unwrap();
+
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                "com.apple.inputmethod.SCIM.ITABC".to_string(),
+                "com.apple.keylayout.ABC".to_string()
+            ]
+        );
+        assert_eq!(backend.selections().len(), 2);
+    }
+
+    #[test]
+    fn test_memory_backend_reports_failures() {
+        let backend = MemoryBackend::new(vec![source("com.apple.keylayout.ABC", "ABC")]);
+        assert!(matches!(
+            backend.select("com.example.missing"),
+            Err(AppError::InputSource(_))
+        ));
+
+        backend.fail_selection_of("com.apple.keylayout.ABC");
+        assert!(backend.select("com.apple.keylayout.ABC").is_err());
+        assert!(MemoryBackend::default().current().is_err());
+    }
+}
@@ -0,0 +1,136 @@
+use crate::error::{AppError, Result};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+mod dry_run;
+#[cfg(target_os = "linux")]
+mod fcitx;
+#[cfg(target_os = "linux")]
+mod ibus;
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(test)]
+mod memory;
+mod tracking;
+#[cfg(not(target_os = "macos"))]
+mod unsupported;
+#[cfg(target_os = "linux")]
+mod xkb;
+
+pub use dry_run::DryRunBackend;
+#[cfg(test)]
+pub use memory::MemoryBackend;
+pub use tracking::{ChangeOrigin, FrontmostApp, InputChangeTracker, TrackedBackend};
+#[cfg(not(target_os = "macos"))]
+pub use unsupported::UnsupportedBackend;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct InputSource {
+    pub id: String,
+    pub name: String,
+    pub category: String,
+}
+
+/// 输入法内部的中英文子模式，例如拼音的英文（ASCII）与中文状态
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum InputMode {
+    Ascii,
+    Native,
+}
+
+/// 当前输入法变化时的回调，参数为切换后的输入法
+pub type InputSourceListener = Arc<dyn Fn(&InputSource) + Send + Sync>;
+
+/// 输入法后端：枚举、查询、切换输入法并订阅变化
+///
+/// observer 与 command 只依赖该 trait，具体实现按平台选择。
+pub trait InputSourceBackend: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// 获取当前系统所有已启用的输入法
+    fn list(&self) -> Result<Vec<InputSource>>;
+
+    /// 获取当前正在使用的输入法
+    fn current(&self) -> Result<InputSource>;
+
+    /// 切换到指定的输入法 ID
+    fn select(&self, source_id: &str) -> Result<()>;
+
+    /// 注册输入法变化监听，回调可能在任意线程触发
+    fn subscribe(&self, listener: InputSourceListener) -> Result<()>;
+
+    /// 是否必须在主线程调用（例如 macOS TIS）
+    fn requires_main_thread(&self) -> bool {
+        false
+    }
+
+    /// 是否支持读取与设置 `InputMode`
+    fn supports_input_mode(&self) -> bool {
+        false
+    }
+
+    fn current_input_mode(&self) -> Result<Option<InputMode>> {
+        Err(unsupported_input_mode(self.name()))
+    }
+
+    fn set_input_mode(&self, _mode: InputMode) -> Result<()> {
+        Err(unsupported_input_mode(self.name()))
+    }
+}
+
+fn unsupported_input_mode(backend: &str) -> AppError {
+    AppError::InputSource(format!(
+        "Input mode switching is not supported by the {backend} backend"
+    ))
+}
+
+/// 当前平台的默认输入法后端
+pub fn default_backend() -> Arc<dyn InputSourceBackend> {
+    #[cfg(target_os = "macos")]
+    {
+        Arc::new(macos::TisBackend)
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let mut failures = Vec::new();
+        match fcitx::FcitxBackend::connect() {
+            Ok(backend) => return Arc::new(backend),
+            Err(e) => failures.push(format!("Fcitx5: {e}")),
+        }
+        match ibus::IbusBackend::connect() {
+            Ok(backend) => return Arc::new(backend),
+            Err(e) => failures.push(format!("IBus: {e}")),
+        }
+        // 没有输入法框架的纯 X11 会话直接切换 XKB 布局组
+        if is_plain_x11_session() {
+            match xkb::XkbBackend::connect() {
+                Ok(backend) => return Arc::new(backend),
+                Err(e) => failures.push(format!("XKB: {e}")),
+            }
+        } else {
+            failures.push("XKB: not a plain X11 session".to_string());
+        }
+        let reason = failures.join("; ");
+        eprintln!("No input backend available: {}", reason);
+        Arc::new(UnsupportedBackend::new(reason))
+    }
+
+    #[cfg(not(any(target_os = "macos", target_os = "linux")))]
+    {
+        Arc::new(UnsupportedBackend::new(format!(
+            "{} is not supported",
+            std::env::consts::OS
+        )))
+    }
+}
+
+/// XWayland 下锁定布局组不会影响合成器，只在纯 X11 会话启用 XKB 后端
+#[cfg(target_os = "linux")]
+pub fn is_plain_x11_session() -> bool {
+    let has_env = |key: &str| std::env::var_os(key).is_some_and(|value| !value.is_empty());
+    has_env("DISPLAY")
+        && !has_env("WAYLAND_DISPLAY")
+        && std::env::var("XDG_SESSION_TYPE").map_or(true, |session| session != "wayland")
+}
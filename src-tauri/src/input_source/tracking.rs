@@ -0,0 +1,225 @@
+use super::{InputMode, InputSource, InputSourceBackend, InputSourceListener};
+use crate::error::Result;
+use serde::Serialize;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+// SmartIME 发起切换后，在此时间内收到的同一输入法变化归因于 SmartIME
+const SELECTION_ATTRIBUTION_WINDOW: Duration = Duration::from_secs(2);
+
+/// 输入法变化的来源
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeOrigin {
+    Smartime,
+    User,
+}
+
+/// 变化发生时的前台应用
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct FrontmostApp {
+    pub bundle_id: String,
+    pub app_name: String,
+}
+
+/// `input_source_changed` 事件负载
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct InputSourceChangedEvent {
+    pub source: InputSource,
+    pub app: Option<FrontmostApp>,
+    pub origin: ChangeOrigin,
+}
+
+#[derive(Default)]
+struct TrackerState {
+    frontmost: Option<FrontmostApp>,
+    expected: Vec<(String, Instant)>,
+}
+
+/// 记录 SmartIME 自己发起的切换与前台应用，用于给系统通知归因
+#[derive(Default)]
+pub struct InputChangeTracker {
+    state: Mutex<TrackerState>,
+}
+
+impl InputChangeTracker {
+    fn lock(&self) -> MutexGuard<'_, TrackerState> {
+        // 状态只是归因提示，锁中毒后继续使用即可
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn set_frontmost(&self, bundle_id: &str, app_name: &str) {
+        self.lock().frontmost = Some(FrontmostApp {
+            bundle_id: bundle_id.to_string(),
+            app_name: app_name.to_string(),
+        });
+    }
+
+    pub fn frontmost(&self) -> Option<FrontmostApp> {
+        self.lock().frontmost.clone()
+    }
+
+    fn expect_selection_at(&self, source_id: &str, now: Instant) {
+        self.lock().expected.push((source_id.to_string(), now));
+    }
+
+    fn cancel_selection(&self, source_id: &str) {
+        let mut state = self.lock();
+        if let Some(index) = state.expected.iter().rposition(|(id, _)| id == source_id) {
+            state.expected.remove(index);
+        }
+    }
+
+    fn classify_at(&self, source: &InputSource, now: Instant) -> InputSourceChangedEvent {
+        let mut state = self.lock();
+        state
+            .expected
+            .retain(|(_, at)| now.saturating_duration_since(*at) <= SELECTION_ATTRIBUTION_WINDOW);
+        let origin = match state.expected.iter().position(|(id, _)| *id == source.id) {
+            Some(index) => {
+                // 更早的期望已被这次变化覆盖
+                state.expected.drain(..=index);
+                ChangeOrigin::Smartime
+            }
+            None => ChangeOrigin::User,
+        };
+
+        InputSourceChangedEvent {
+            source: source.clone(),
+            app: state.frontmost.clone(),
+            origin,
+        }
+    }
+
+    /// 为一次输入法变化通知归因
+    pub fn classify(&self, source: &InputSource) -> InputSourceChangedEvent {
+        self.classify_at(source, Instant::now())
+    }
+}
+
+/// 包装平台后端，`select` 前登记期望的变化，便于区分 SmartIME 与用户切换
+pub struct TrackedBackend {
+    inner: Arc<dyn InputSourceBackend>,
+    tracker: Arc<InputChangeTracker>,
+}
+
+impl TrackedBackend {
+    pub fn new(inner: Arc<dyn InputSourceBackend>, tracker: Arc<InputChangeTracker>) -> Self {
+        Self { inner, tracker }
+    }
+}
+
+impl InputSourceBackend for TrackedBackend {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn list(&self) -> Result<Vec<InputSource>> {
+        self.inner.list()
+    }
+
+    fn current(&self) -> Result<InputSource> {
+        self.inner.current()
+    }
+
+    fn select(&self, source_id: &str) -> Result<()> {
+        self.tracker.expect_selection_at(source_id, Instant::now());
+        let result = self.inner.select(source_id);
+        if result.is_err() {
+            self.tracker.cancel_selection(source_id);
+        }
+        result
+    }
+
+    fn subscribe(&self, listener: InputSourceListener) -> Result<()> {
+        self.inner.subscribe(listener)
+    }
+
+    fn requires_main_thread(&self) -> bool {
+        self.inner.requires_main_thread()
+    }
+
+    fn supports_input_mode(&self) -> bool {
+        self.inner.supports_input_mode()
+    }
+
+    fn current_input_mode(&self) -> Result<Option<InputMode>> {
+        self.inner.current_input_mode()
+    }
+
+    fn set_input_mode(&self, mode: InputMode) -> Result<()> {
+        self.inner.set_input_mode(mode)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input_source::MemoryBackend;
+
+    fn source(id: &str) -> InputSource {
+        InputSource {
+            id: id.to_string(),
+            name: id.to_string(),
+            category: "TISCategoryKeyboardInputSource".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_tracked_backend_tags_change_origin() {
+        let inner = Arc::new(MemoryBackend::new(vec![source("abc"), source("pinyin")]));
+        let tracker = Arc::new(InputChangeTracker::default());
+        let backend = TrackedBackend::new(inner.clone(), tracker.clone());
+        tracker.set_frontmost("com.tencent.xinWeChat", "WeChat");
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        let listener_tracker = tracker.clone();
+        backend
+            .subscribe(Arc::new(move |source: &InputSource| {
+                sink.lock().unwrap().push(listener_tracker.classify(source));
+            }))
+            .unwrap();
+
+        backend.select("pinyin").unwrap();
+        inner.simulate_user_switch("abc").unwrap();
+        // 切换失败时不留下期望，之后的同名变化归因于用户
+        inner.fail_selection_of("pinyin");
+        assert!(backend.select("pinyin").is_err());
+        inner.simulate_user_switch("pinyin").unwrap();
+
+        let events = events.lock().unwrap();
+        assert_eq!(
+            events
+                .iter()
+                .map(|event| (event.source.id.as_str(), event.origin))
+                .collect::<Vec<_>>(),
+            vec![
+                ("pinyin", ChangeOrigin::Smartime),
+                ("abc", ChangeOrigin::User),
+                ("pinyin", ChangeOrigin::User),
+            ]
+        );
+        assert_eq!(
+            events[0].app,
+            Some(FrontmostApp {
+                bundle_id: "com.tencent.xinWeChat".to_string(),
+                app_name: "WeChat".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_expected_selection_expires() {
+        let tracker = InputChangeTracker::default();
+        let start = Instant::now();
+        tracker.expect_selection_at("pinyin", start);
+
+        let late = start + SELECTION_ATTRIBUTION_WINDOW + Duration::from_millis(1);
+        assert_eq!(
+            tracker.classify_at(&source("pinyin"), late).origin,
+            ChangeOrigin::User
+        );
+        assert_eq!(tracker.classify_at(&source("pinyin"), late).app, None);
+    }
+}
@@ -0,0 +1,60 @@
+use super::{InputSource, InputSourceBackend, InputSourceListener};
+use crate::error::{AppError, Result};
+
+/// 找不到可用输入法框架时的后端，所有操作都返回包含原因的错误，由界面展示给用户
+pub struct UnsupportedBackend {
+    reason: String,
+}
+
+impl UnsupportedBackend {
+    pub fn new(reason: impl Into<String>) -> Self {
+        Self {
+            reason: reason.into(),
+        }
+    }
+
+    fn error(&self) -> AppError {
+        AppError::InputSource(format!(
+            "No supported input method framework: {}",
+            self.reason
+        ))
+    }
+}
+
+impl InputSourceBackend for UnsupportedBackend {
+    fn name(&self) -> &'static str {
+        "unsupported"
+    }
+
+    fn list(&self) -> Result<Vec<InputSource>> {
+        Err(self.error())
+    }
+
+    fn current(&self) -> Result<InputSource> {
+        Err(self.error())
+    }
+
+    fn select(&self, _source_id: &str) -> Result<()> {
+        Err(self.error())
+    }
+
+    fn subscribe(&self, _listener: InputSourceListener) -> Result<()> {
+        Err(self.error())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unsupported_backend_reports_reason() {
+        let backend = UnsupportedBackend::new("Fcitx5: not running; IBus: not running");
+        let error = backend.list().unwrap_err().to_string();
+        assert!(error.contains("Fcitx5: not running"));
+        assert!(matches!(
+            backend.select("pinyin"),
+            Err(AppError::InputSource(_))
+        ));
+    }
+}
@@ -0,0 +1,367 @@
+use super::{InputSource, InputSourceBackend, InputSourceListener};
+use crate::error::{AppError, Result};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use x11rb::connection::Connection;
+use x11rb::protocol::xkb::{self, ConnectionExt as _};
+use x11rb::protocol::xproto::{self, AtomEnum, ConnectionExt as _};
+use x11rb::protocol::Event;
+use x11rb::rust_connection::RustConnection;
+
+const RULES_NAMES_PROPERTY: &[u8] = b"_XKB_RULES_NAMES";
+const SOURCE_ID_PREFIX: &str = "xkb:";
+const SOURCE_CATEGORY: &str = "xkb";
+// XKB 最多支持 4 个布局组
+const MAX_GROUPS: usize = 4;
+
+/// 纯 X11 会话下通过锁定 XKB 布局组（us、ru、de 等）切换键盘布局
+pub struct XkbBackend {
+    display: Option<String>,
+    connection: RustConnection,
+    root: xproto::Window,
+    listeners: Arc<Mutex<Vec<InputSourceListener>>>,
+    watching: AtomicBool,
+}
+
+impl XkbBackend {
+    /// 连接 `DISPLAY` 指向的 X 服务器
+    pub fn connect() -> Result<Self> {
+        Self::connect_to(None)
+    }
+
+    pub fn connect_to(display: Option<&str>) -> Result<Self> {
+        let (connection, root) = open_display(display)?;
+        Ok(Self {
+            display: display.map(str::to_string),
+            connection,
+            root,
+            listeners: Arc::new(Mutex::new(Vec::new())),
+            watching: AtomicBool::new(false),
+        })
+    }
+
+    fn locked_group(&self) -> Result<usize> {
+        locked_group(&self.connection)
+    }
+}
+
+impl InputSourceBackend for XkbBackend {
+    fn name(&self) -> &'static str {
+        "xkb"
+    }
+
+    fn list(&self) -> Result<Vec<InputSource>> {
+        list_groups(&self.connection, self.root)
+    }
+
+    fn current(&self) -> Result<InputSource> {
+        let group = self.locked_group()?;
+        self.list()?
+            .into_iter()
+            .nth(group)
+            .ok_or_else(|| AppError::InputSource(format!("Unknown XKB layout group {group}")))
+    }
+
+    fn select(&self, source_id: &str) -> Result<()> {
+        let group = self
+            .list()?
+            .iter()
+            .position(|source| source.id == source_id)
+            .ok_or_else(|| {
+                AppError::InputSource(format!("XKB layout not configured: {source_id}"))
+            })?;
+
+        self.connection
+            .xkb_latch_lock_state(
+                xkb::ID::USE_CORE_KBD.into(),
+                xproto::ModMask::from(0u16),
+                xproto::ModMask::from(0u16),
+                true,
+                xkb::Group::from(group as u8),
+                xproto::ModMask::from(0u16),
+                false,
+                0,
+            )
+            .map_err(|e| xkb_error("LatchLockState", e))?
+            .check()
+            .map_err(|e| xkb_error("LatchLockState", e))?;
+        Ok(())
+    }
+
+    fn subscribe(&self, listener: InputSourceListener) -> Result<()> {
+        self.listeners
+            .lock()
+            .map_err(|e| AppError::Lock(e.to_string()))?
+            .push(listener);
+        if self.watching.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        // 事件循环独占一条连接，避免与请求/应答交错
+        let (connection, root) = open_display(self.display.as_deref())?;
+        let details = xkb::SelectEventsAux::new().state_notify(xkb::SelectEventsAuxStateNotify {
+            affect_state: xkb::StatePart::GROUP_LOCK,
+            state_details: xkb::StatePart::GROUP_LOCK,
+        });
+        connection
+            .xkb_select_events(
+                xkb::ID::USE_CORE_KBD.into(),
+                xkb::EventType::from(0u16),
+                xkb::EventType::from(0u16),
+                xkb::MapPart::from(0u16),
+                xkb::MapPart::from(0u16),
+                &details,
+            )
+            .map_err(|e| xkb_error("SelectEvents", e))?
+            .check()
+            .map_err(|e| xkb_error("SelectEvents", e))?;
+        let listeners = self.listeners.clone();
+
+        std::thread::spawn(move || {
+            while let Ok(event) = connection.wait_for_event() {
+                let Event::XkbStateNotify(event) = event else {
+                    continue;
+                };
+                if u16::from(event.changed & xkb::StatePart::GROUP_LOCK) == 0 {
+                    continue;
+                }
+
+                let group = usize::from(u8::from(event.locked_group));
+                let Some(source) = list_groups(&connection, root)
+                    .ok()
+                    .and_then(|sources| sources.into_iter().nth(group))
+                else {
+                    continue;
+                };
+
+                let listeners = match listeners.lock() {
+                    Ok(guard) => guard.clone(),
+                    Err(_) => continue,
+                };
+                for listener in listeners {
+                    listener(&source);
+                }
+            }
+        });
+
+        Ok(())
+    }
+}
+
+fn open_display(display: Option<&str>) -> Result<(RustConnection, xproto::Window)> {
+    let (connection, screen) =
+        x11rb::connect(display).map_err(|e| xkb_error("display connection", e))?;
+    let root = connection.setup().roots[screen].root;
+
+    let version = connection
+        .xkb_use_extension(1, 0)
+        .map_err(|e| xkb_error("UseExtension", e))?
+        .reply()
+        .map_err(|e| xkb_error("UseExtension", e))?;
+    if !version.supported {
+        return Err(AppError::InputSource(
+            "XKB extension is not supported by the X server".to_string(),
+        ));
+    }
+    Ok((connection, root))
+}
+
+fn locked_group(connection: &RustConnection) -> Result<usize> {
+    let state = connection
+        .xkb_get_state(xkb::ID::USE_CORE_KBD.into())
+        .map_err(|e| xkb_error("GetState", e))?
+        .reply()
+        .map_err(|e| xkb_error("GetState", e))?;
+    Ok(usize::from(u8::from(state.locked_group)))
+}
+
+/// 结合 `_XKB_RULES_NAMES` 中的布局代码与 XKB 组名枚举布局组
+fn list_groups(connection: &RustConnection, root: xproto::Window) -> Result<Vec<InputSource>> {
+    let rules_names = read_rules_names(connection, root)?;
+
+    let names = connection
+        .xkb_get_names(xkb::ID::USE_CORE_KBD.into(), xkb::NameDetail::GROUP_NAMES)
+        .map_err(|e| xkb_error("GetNames", e))?
+        .reply()
+        .map_err(|e| xkb_error("GetNames", e))?;
+    let mut group_names = Vec::new();
+    for atom in names.value_list.groups.unwrap_or_default() {
+        group_names.push(atom_name(connection, atom)?);
+    }
+
+    Ok(layout_groups(&rules_names, &group_names))
+}
+
+fn read_rules_names(connection: &RustConnection, root: xproto::Window) -> Result<Vec<u8>> {
+    let atom = connection
+        .intern_atom(true, RULES_NAMES_PROPERTY)
+        .map_err(|e| xkb_error("InternAtom", e))?
+        .reply()
+        .map_err(|e| xkb_error("InternAtom", e))?
+        .atom;
+    if atom == x11rb::NONE {
+        return Ok(Vec::new());
+    }
+
+    Ok(connection
+        .get_property(false, root, atom, AtomEnum::STRING, 0, 1024)
+        .map_err(|e| xkb_error("GetProperty", e))?
+        .reply()
+        .map_err(|e| xkb_error("GetProperty", e))?
+        .value)
+}
+
+fn atom_name(connection: &RustConnection, atom: xproto::Atom) -> Result<String> {
+    if atom == x11rb::NONE {
+        return Ok(String::new());
+    }
+    let reply = connection
+        .get_atom_name(atom)
+        .map_err(|e| xkb_error("GetAtomName", e))?
+        .reply()
+        .map_err(|e| xkb_error("GetAtomName", e))?;
+    Ok(String::from_utf8_lossy(&reply.name).into_owned())
+}
+
+/// 把布局组映射为 `InputSource`
+///
+/// `_XKB_RULES_NAMES` 以 NUL 分隔：rules、model、layout、variant、options。
+/// id 形如 `xkb:us`、`xkb:de(nodeadkeys)`，名称优先使用 XKB 组名（如 “English (US)”）。
+fn layout_groups(rules_names: &[u8], group_names: &[String]) -> Vec<InputSource> {
+    let fields: Vec<String> = rules_names
+        .split(|byte| *byte == 0)
+        .map(|field| String::from_utf8_lossy(field).into_owned())
+        .collect();
+    let split = |index: usize| -> Vec<String> {
+        fields
+            .get(index)
+            .filter(|field| !field.is_empty())
+            .map(|field| field.split(',').map(|s| s.trim().to_string()).collect())
+            .unwrap_or_default()
+    };
+    let layouts = split(2);
+    let variants = split(3);
+
+    let count = layouts.len().max(group_names.len()).min(MAX_GROUPS);
+    (0..count)
+        .map(|index| {
+            let layout = layouts
+                .get(index)
+                .filter(|layout| !layout.is_empty())
+                .map(
+                    |layout| match variants.get(index).filter(|v| !v.is_empty()) {
+                        Some(variant) => format!("{layout}({variant})"),
+                        None => layout.clone(),
+                    },
+                )
+                .unwrap_or_else(|| format!("group{}", index + 1));
+            let name = group_names
+                .get(index)
+                .filter(|name| !name.trim().is_empty())
+                .cloned()
+                .unwrap_or_else(|| layout.clone());
+
+            InputSource {
+                id: format!("{SOURCE_ID_PREFIX}{layout}"),
+                name,
+                category: SOURCE_CATEGORY.to_string(),
+            }
+        })
+        .collect()
+}
+
+fn xkb_error(context: &str, e: impl std::fmt::Display) -> AppError {
+    AppError::InputSource(format!("XKB {context} failed: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::x11_test_support::VirtualDisplay;
+    use std::process::Command;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    /// 用 setxkbmap 配置多个布局组
+    fn set_layouts(display: &VirtualDisplay, layouts: &str, variants: &str) -> bool {
+        Command::new("setxkbmap")
+            .args(["-display", display.display(), "-layout", layouts])
+            .args(["-variant", variants])
+            .status()
+            .map(|status| status.success())
+            .unwrap_or(false)
+    }
+
+    #[test]
+    fn test_layout_groups_from_rules_names() {
+        let sources = layout_groups(
+            b"evdev\0pc105\0us,de,ru\0,nodeadkeys,\0grp:alt_shift_toggle\0",
+            &[
+                "English (US)".to_string(),
+                "German (no dead keys)".to_string(),
+                String::new(),
+            ],
+        );
+
+        assert_eq!(
+            sources
+                .iter()
+                .map(|source| source.id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["xkb:us", "xkb:de(nodeadkeys)", "xkb:ru"]
+        );
+        assert_eq!(sources[1].name, "German (no dead keys)");
+        // 组名缺失时回退到布局代码
+        assert_eq!(sources[2].name, "ru");
+        assert_eq!(sources[2].category, SOURCE_CATEGORY);
+    }
+
+    #[test]
+    fn test_layout_groups_without_rules_names() {
+        let sources = layout_groups(b"", &["English (US)".to_string()]);
+        assert_eq!(sources.len(), 1);
+        assert_eq!(sources[0].id, "xkb:group1");
+        assert_eq!(sources[0].name, "English (US)");
+        assert!(layout_groups(b"", &[]).is_empty());
+    }
+
+    #[test]
+    fn test_xkb_backend_against_xvfb() {
+        let Some(display) = VirtualDisplay::start() else {
+            return;
+        };
+        if !set_layouts(&display, "us,ru", ",") {
+            crate::test_support::skip_integration_test("setxkbmap unavailable");
+            return;
+        }
+        let backend = XkbBackend::connect_to(Some(display.display())).unwrap();
+
+        let sources = backend.list().unwrap();
+        assert_eq!(
+            sources
+                .iter()
+                .map(|source| source.id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["xkb:us", "xkb:ru"]
+        );
+        assert_eq!(backend.current().unwrap().id, "xkb:us");
+
+        let (tx, rx) = mpsc::channel();
+        let tx = Mutex::new(tx);
+        backend
+            .subscribe(Arc::new(move |source: &InputSource| {
+                let _ = tx.lock().unwrap().send(source.clone());
+            }))
+            .unwrap();
+
+        backend.select("xkb:ru").unwrap();
+        assert_eq!(backend.current().unwrap().id, "xkb:ru");
+        let changed = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(changed.id, "xkb:ru");
+
+        assert!(matches!(
+            backend.select("xkb:de"),
+            Err(AppError::InputSource(_))
+        ));
+    }
+}
@@ -1,22 +1,149 @@
-#[tauri::command]
-fn greet(name: &str) -> String {
-    format!("Hello, {}! You've been greeted from Rust!", name)
-}
+mod app_icon;
+mod command;
+pub mod config;
+mod credentials;
+#[cfg(all(test, target_os = "linux"))]
+mod dbus_test_support;
+mod error;
+mod general_settings;
+mod input_source;
+mod llm;
+mod llm_audit;
+mod llm_http;
+mod observer;
+pub mod rule_snapshot;
+mod rule_suggestions;
+mod secret_store;
+mod shortcuts;
+mod single_instance;
+mod switching_pause;
+mod system_apps;
+#[cfg(test)]
+mod test_support;
+mod tray_icon;
+mod tray_menu;
+mod tray_refresh;
+#[cfg(all(test, target_os = "linux"))]
+mod x11_test_support;
+
+use config::AppState;
+use tauri::Manager;
+#[cfg(target_os = "macos")]
+use tauri::WindowEvent;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    tauri::Builder::default()
+    // 命令行回放不启动界面，也不影响正在运行的实例
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(code) = observer::run_replay_from_args(&args) {
+        std::process::exit(code);
+    }
+
+    if !single_instance::prepare_primary_instance() {
+        return;
+    }
+
+    let app = tauri::Builder::default()
+        .plugin(tauri_plugin_store::Builder::new().build())
+        .plugin(tauri_plugin_log::Builder::default().build())
+        .plugin(
+            tauri_plugin_global_shortcut::Builder::new()
+                .with_handler(shortcuts::handle_shortcut)
+                .build(),
+        )
+        .manage(AppState::new()) // 注入全局状态
+        .on_window_event(|window, event| {
+            #[cfg(target_os = "macos")]
+            {
+                if window.label() != "main" {
+                    return;
+                }
+
+                if let WindowEvent::CloseRequested { api, .. } = event {
+                    let should_hide_to_tray = window
+                        .app_handle()
+                        .state::<AppState>()
+                        .config
+                        .lock()
+                        .ok()
+                        .map(|manager| manager.get_config().general.hide_dock_icon)
+                        .unwrap_or(false);
+
+                    if should_hide_to_tray {
+                        api.prevent_close();
+                        let _ = window.app_handle().set_dock_visibility(false);
+                        let _ = window.hide();
+                    }
+                }
+            }
+
+            #[cfg(not(target_os = "macos"))]
+            {
+                let _ = window;
+                let _ = event;
+            }
+        })
         .setup(|app| {
-            if cfg!(debug_assertions) {
-                app.handle().plugin(
-                    tauri_plugin_log::Builder::default()
-                        .level(log::LevelFilter::Info)
-                        .build(),
-                )?;
+            let handle = app.handle().clone();
+            let state = app.state::<AppState>();
+
+            single_instance::start_activation_listener(handle.clone());
+
+            if let Ok(manager) = state.config.lock() {
+                let config = manager.get_config();
+                if let Err(err) = general_settings::apply_general_settings(&handle, &config.general)
+                {
+                    eprintln!("Failed to apply general settings on startup: {}", err);
+                }
             }
+
+            // 启动应用监听
+            observer::setup_observer(handle);
+
             Ok(())
         })
-        .invoke_handler(tauri::generate_handler![greet])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .invoke_handler(tauri::generate_handler![
+            command::cmd_get_system_input_sources,
+            command::cmd_select_input_source,
+            command::cmd_get_switching_metrics,
+            command::cmd_simulate_focus,
+            command::cmd_list_rule_suggestions,
+            command::cmd_accept_suggestion,
+            command::cmd_dismiss_suggestion,
+            command::cmd_pause_switching,
+            command::cmd_resume_switching,
+            command::cmd_get_switching_pause,
+            command::cmd_get_installed_apps,
+            command::cmd_get_app_icons,
+            command::cmd_save_config,
+            command::cmd_save_rules,
+            command::cmd_get_config,
+            command::cmd_has_config,
+            command::cmd_save_llm_config,
+            command::cmd_get_llm_config,
+            command::cmd_check_llm_connection,
+            command::cmd_preview_llm_payloads,
+            command::cmd_get_llm_usage_summary,
+            command::cmd_scan_and_predict,
+            command::cmd_rescan_and_save_rules,
+            command::cmd_is_rescanning,
+            command::cmd_check_permissions,
+            command::cmd_request_permissions,
+            command::cmd_open_system_settings,
+        ])
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application");
+
+    app.run(|app_handle, event| {
+        #[cfg(target_os = "macos")]
+        if let tauri::RunEvent::Reopen { .. } = event {
+            single_instance::focus_main_window(app_handle);
+        }
+
+        #[cfg(not(target_os = "macos"))]
+        {
+            let _ = app_handle;
+            let _ = event;
+        }
+    });
 }
@@ -1,16 +1,82 @@
+use crate::credentials::{clear_resolved_api_keys, resolve_api_key, ApiKeySource};
 use crate::error::{AppError, Result};
 use crate::input_source::InputSource;
+use crate::llm_audit::{
+    estimate_tokens, now_ms, prompt_hash, provider_from_base_url, AuditLog, AuditRecord,
+    LLMUsageLimits, ScanBudget, TokenUsage, STATUS_OK,
+};
+use crate::llm_http::{
+    build_http_client, legacy_network_settings, validate_endpoint, LLMNetworkSettings,
+};
+use crate::secret_store::{default_secret_store, write_private_file, SecretStore};
+use crate::system_apps::SystemApp;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// 持久化文件中代替 API Key 明文的占位值，真实值保存在 SecretStore 中
+const STORED_API_KEY_MARKER: &str = "store:llm_api_key";
+const API_KEY_SECRET_NAME: &str = "llm_api_key";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LLMConfig {
     pub api_key: String,
     pub model: String,
     pub base_url: String,
+    #[serde(default)]
+    pub privacy: LLMPrivacySettings,
+    #[serde(default)]
+    pub limits: LLMUsageLimits,
+    #[serde(default)]
+    pub network: LLMNetworkSettings,
+}
+
+/// 发送给 LLM 的应用身份字段
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AppIdentityMode {
+    #[default]
+    NameAndBundleId,
+    BundleIdOnly,
+    NameOnly,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct LLMPrivacySettings {
+    /// Bundle ID 通配规则（支持 `*` / `?`），命中的应用不会发送给 LLM
+    #[serde(default)]
+    pub excluded_bundle_patterns: Vec<String>,
+    #[serde(default)]
+    pub identity_mode: AppIdentityMode,
+}
+
+impl LLMPrivacySettings {
+    pub fn is_excluded(&self, bundle_id: &str) -> bool {
+        self.excluded_bundle_patterns
+            .iter()
+            .map(|pattern| pattern.trim())
+            .filter(|pattern| !pattern.is_empty())
+            .any(|pattern| bundle_pattern_matches(pattern, bundle_id))
+    }
+}
+
+/// 预览单个将要发送的请求（不含 Authorization 头）
+#[derive(Debug, Clone, Serialize)]
+pub struct LLMRequestPreview {
+    pub bundle_id: String,
+    pub app_name: String,
+    pub url: String,
+    pub body: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LLMPayloadPreview {
+    pub requests: Vec<LLMRequestPreview>,
+    pub excluded_bundle_ids: Vec<String>,
 }
 
 impl Default for LLMConfig {
@@ -19,15 +85,21 @@ impl Default for LLMConfig {
             api_key: "".to_string(),
             model: "gpt-3.5-turbo".to_string(),
             base_url: "https://api.openai.com/v1".to_string(),
+            privacy: LLMPrivacySettings::default(),
+            limits: LLMUsageLimits::default(),
+            network: LLMNetworkSettings::default(),
         }
     }
 }
 
 #[derive(Clone)]
 pub struct LLMClient {
-    client: Client,
+    // 网络设置无效时为 None，实际调用时重新构建以返回具体错误
+    client: Option<Client>,
     config: LLMConfig,
     file_path: PathBuf,
+    audit: AuditLog,
+    secrets: Arc<dyn SecretStore>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -46,6 +118,8 @@ struct ChatCompletionRequest {
 #[derive(Debug, Deserialize)]
 struct ChatCompletionResponse {
     choices: Vec<ChatChoice>,
+    #[serde(default)]
+    usage: Option<TokenUsage>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -61,23 +135,42 @@ impl LLMClient {
         let _ = fs::create_dir_all(&config_dir);
         let file_path = config_dir.join("llm_config.json");
 
+        Self::load(file_path, default_secret_store())
+    }
+
+    fn load(file_path: PathBuf, secrets: Arc<dyn SecretStore>) -> Self {
         // 优先读取持久化配置，随后回退到 .env.llm
-        let config = Self::load_from_file(&file_path)
+        let config = Self::load_from_file(&file_path, secrets.as_ref())
             .or_else(Self::load_from_env)
             .unwrap_or_default();
 
+        let client = build_http_client(&config.network)
+            .map_err(|e| eprintln!("Failed to build LLM HTTP client: {}", e))
+            .ok();
+
         Self {
-            client: Client::new(),
+            client,
             config,
             file_path,
+            audit: AuditLog::new(),
+            secrets,
         }
     }
 
     pub fn update_config(&mut self, config: LLMConfig) -> Result<()> {
+        self.client = Some(build_http_client(&config.network)?);
         self.config = config;
+        clear_resolved_api_keys();
         self.save_to_file()
     }
 
+    fn http_client(&self) -> Result<Client> {
+        match &self.client {
+            Some(client) => Ok(client.clone()),
+            None => build_http_client(&self.config.network),
+        }
+    }
+
     pub fn get_config(&self) -> LLMConfig {
         self.config.clone()
     }
@@ -98,22 +191,69 @@ impl LLMClient {
         Some(LLMConfig {
             api_key,
             model,
+            network: legacy_network_settings(&base_url),
             base_url,
+            privacy: LLMPrivacySettings::default(),
+            limits: LLMUsageLimits::default(),
         })
     }
 
-    fn load_from_file(path: &Path) -> Option<LLMConfig> {
+    fn load_from_file(path: &Path, secrets: &dyn SecretStore) -> Option<LLMConfig> {
         if !path.exists() {
             return None;
         }
         let content = fs::read_to_string(path).ok()?;
-        serde_json::from_str(&content).ok()
+        let value: serde_json::Value = serde_json::from_str(&content).ok()?;
+        let has_network_settings = value.get("network").is_some();
+        let mut config: LLMConfig = serde_json::from_value(value).ok()?;
+        if !has_network_settings {
+            config.network = legacy_network_settings(&config.base_url);
+        }
+
+        if config.api_key == STORED_API_KEY_MARKER {
+            config.api_key = match secrets.get(API_KEY_SECRET_NAME) {
+                Ok(Some(api_key)) => api_key,
+                // 例如关闭 os-keyring 后，之前存入钥匙串的 API Key 无法读取
+                Ok(None) => {
+                    eprintln!(
+                        "No stored API key found in {}, please enter it again",
+                        secrets.name()
+                    );
+                    String::new()
+                }
+                Err(e) => {
+                    eprintln!("Failed to read API key from secret store: {}", e);
+                    String::new()
+                }
+            };
+        } else if !config.api_key.is_empty() && !ApiKeySource::parse(&config.api_key).is_reference()
+        {
+            // 旧版本以明文保存 API Key，首次加载时迁移到 SecretStore
+            match Self::write_config_file(path, &config, secrets) {
+                Ok(()) => eprintln!("Migrated plaintext LLM API key to {}", secrets.name()),
+                Err(e) => eprintln!("Failed to migrate plaintext LLM API key: {}", e),
+            }
+        }
+
+        Some(config)
     }
 
     fn save_to_file(&self) -> Result<()> {
-        let content = serde_json::to_string_pretty(&self.config)?;
-        fs::write(&self.file_path, content)?;
-        Ok(())
+        Self::write_config_file(&self.file_path, &self.config, self.secrets.as_ref())
+    }
+
+    /// 明文 API Key 写入 SecretStore，配置文件中只保留占位值或引用
+    fn write_config_file(path: &Path, config: &LLMConfig, secrets: &dyn SecretStore) -> Result<()> {
+        let mut persisted = config.clone();
+        if persisted.api_key.is_empty() {
+            secrets.delete(API_KEY_SECRET_NAME)?;
+        } else if !ApiKeySource::parse(&persisted.api_key).is_reference() {
+            secrets.set(API_KEY_SECRET_NAME, &persisted.api_key)?;
+            persisted.api_key = STORED_API_KEY_MARKER.to_string();
+        }
+
+        let content = serde_json::to_string_pretty(&persisted)?;
+        write_private_file(path, content.as_bytes())
     }
 
     /// 检查 LLM 连接配置是否有效
@@ -122,8 +262,7 @@ impl LLMClient {
             return Err(AppError::Llm("API Key cannot be empty".to_string()));
         }
 
-        let client = Client::new();
-        let url = format!("{}/chat/completions", config.base_url.trim_end_matches('/'));
+        let client = build_http_client(&config.network)?;
 
         let request = ChatCompletionRequest {
             model: config.model.clone(),
@@ -134,44 +273,52 @@ impl LLMClient {
             temperature: 0.1,
         };
 
-        let resp = client
-            .post(&url)
-            .header("Authorization", format!("Bearer {}", config.api_key))
-            .header("Content-Type", "application/json")
-            .json(&request)
-            .send()
-            .await?;
-
-        if !resp.status().is_success() {
-            let error_text = resp.text().await?;
-            return Err(AppError::Llm(format!("Connection failed: {}", error_text)));
-        }
+        send_chat_completion(
+            &client,
+            config,
+            &AuditLog::new(),
+            &request,
+            "Connection failed",
+        )
+        .await?;
 
         Ok(())
     }
 
-    /// 预测应用最合适的输入法
-    pub async fn predict(
+    /// 按配置的用量上限开启一次扫描预算
+    pub fn begin_scan_budget(&self) -> Result<ScanBudget> {
+        ScanBudget::begin(&self.config.limits, &self.audit)
+    }
+
+    pub fn is_app_excluded(&self, bundle_id: &str) -> bool {
+        self.config.privacy.is_excluded(bundle_id)
+    }
+
+    /// 构造预测请求，应用身份字段遵循隐私设置
+    fn build_prediction_request(
         &self,
         app_name: &str,
         bundle_id: &str,
         input_sources: &[InputSource],
-    ) -> Result<String> {
-        if self.config.api_key.is_empty() {
-            return Err(AppError::Llm("API Key not configured".to_string()));
-        }
-
+    ) -> ChatCompletionRequest {
         let sources_desc = input_sources
             .iter()
             .map(|s| format!("- ID: {}, Name: {}", s.id, s.name))
             .collect::<Vec<_>>()
             .join("\n");
 
+        let app_desc = match self.config.privacy.identity_mode {
+            AppIdentityMode::NameAndBundleId => {
+                format!("- Name: {app_name}\n- Bundle ID: {bundle_id}")
+            }
+            AppIdentityMode::BundleIdOnly => format!("- Bundle ID: {bundle_id}"),
+            AppIdentityMode::NameOnly => format!("- Name: {app_name}"),
+        };
+
         let prompt = format!(
             r#"You are an intelligent assistant for macOS input method switching.
 Target Application:
-- Name: {app_name}
-- Bundle ID: {bundle_id}
+{app_desc}
 
 Available Input Sources:
 {sources_desc}
@@ -185,40 +332,89 @@ Select the most appropriate input source ID for the target application.
 Response Format:
 Just output the ID string of the selected input source. Do not output any other text.
 "#,
-            app_name = app_name,
-            bundle_id = bundle_id,
+            app_desc = app_desc,
             sources_desc = sources_desc
         );
 
-        let request = ChatCompletionRequest {
+        ChatCompletionRequest {
             model: self.config.model.clone(),
             messages: vec![ChatMessage {
                 role: "user".to_string(),
                 content: prompt,
             }],
             temperature: 0.1, // 低温度以获得确定性结果
-        };
+        }
+    }
 
-        let url = format!(
-            "{}/chat/completions",
-            self.config.base_url.trim_end_matches('/')
-        );
+    /// 列出一次扫描将会发送的全部请求内容，不发起任何网络请求
+    pub fn preview_payloads(
+        &self,
+        apps: &[SystemApp],
+        input_sources: &[InputSource],
+    ) -> Result<LLMPayloadPreview> {
+        let url = completions_url(&self.config.base_url);
+        let mut requests = Vec::new();
+        let mut excluded_bundle_ids = Vec::new();
+
+        for app in apps {
+            if self.is_app_excluded(&app.bundle_id) {
+                excluded_bundle_ids.push(app.bundle_id.clone());
+                continue;
+            }
 
-        let resp = self
-            .client
-            .post(&url)
-            .header("Authorization", format!("Bearer {}", self.config.api_key))
-            .header("Content-Type", "application/json")
-            .json(&request)
-            .send()
-            .await?;
+            let request = self.build_prediction_request(&app.name, &app.bundle_id, input_sources);
+            requests.push(LLMRequestPreview {
+                bundle_id: app.bundle_id.clone(),
+                app_name: app.name.clone(),
+                url: url.clone(),
+                body: serde_json::to_value(&request)?,
+            });
+        }
 
-        if !resp.status().is_success() {
-            let error_text = resp.text().await?;
-            return Err(AppError::Llm(format!("API request failed: {}", error_text)));
+        Ok(LLMPayloadPreview {
+            requests,
+            excluded_bundle_ids,
+        })
+    }
+
+    /// 预测应用最合适的输入法
+    pub async fn predict(
+        &self,
+        app_name: &str,
+        bundle_id: &str,
+        input_sources: &[InputSource],
+        budget: &mut ScanBudget,
+    ) -> Result<String> {
+        if self.config.api_key.is_empty() {
+            return Err(AppError::Llm("API Key not configured".to_string()));
         }
 
-        let completion: ChatCompletionResponse = resp.json().await?;
+        if self.is_app_excluded(bundle_id) {
+            return Err(AppError::Llm(format!(
+                "{bundle_id} is excluded by privacy settings"
+            )));
+        }
+
+        let request = self.build_prediction_request(app_name, bundle_id, input_sources);
+        let estimated_tokens = budget.estimate(&request.messages[0].content);
+        budget.check(estimated_tokens)?;
+
+        let completion = send_chat_completion(
+            &self.http_client()?,
+            &self.config,
+            &self.audit,
+            &request,
+            "API request failed",
+        )
+        .await;
+        budget.record(
+            completion
+                .as_ref()
+                .ok()
+                .and_then(|completion| completion.usage),
+            estimated_tokens,
+        );
+        let completion = completion?;
 
         if let Some(choice) = completion.choices.first() {
             let selected_id = choice.message.content.trim().to_string();
@@ -239,9 +435,109 @@ Just output the ID string of the selected input source. Do not output any other
     }
 }
 
+fn completions_url(base_url: &str) -> String {
+    format!("{}/chat/completions", base_url.trim_end_matches('/'))
+}
+
+/// 发送一次 chat/completions 请求，并把结果写入本地审计日志
+async fn send_chat_completion(
+    client: &Client,
+    config: &LLMConfig,
+    audit: &AuditLog,
+    request: &ChatCompletionRequest,
+    error_prefix: &str,
+) -> Result<ChatCompletionResponse> {
+    validate_endpoint(&config.base_url, &config.network)?;
+    // 引用形式的 API Key 可能需要执行外部命令，放到阻塞线程池中解析
+    let raw_api_key = config.api_key.clone();
+    let api_key = tauri::async_runtime::spawn_blocking(move || resolve_api_key(&raw_api_key))
+        .await
+        .map_err(|e| AppError::Llm(format!("Failed to join API key resolution: {e}")))??;
+    let url = completions_url(&config.base_url);
+    let started = Instant::now();
+    let mut status = "network_error".to_string();
+
+    let outcome = async {
+        let resp = client
+            .post(&url)
+            .header("Authorization", format!("Bearer {}", api_key))
+            .header("Content-Type", "application/json")
+            .json(request)
+            .send()
+            .await?;
+
+        if !resp.status().is_success() {
+            status = format!("http_{}", resp.status().as_u16());
+            let error_text = resp.text().await?;
+            return Err(AppError::Llm(format!("{error_prefix}: {error_text}")));
+        }
+
+        status = "invalid_response".to_string();
+        let completion: ChatCompletionResponse = resp.json().await?;
+        status = STATUS_OK.to_string();
+        Ok(completion)
+    }
+    .await;
+
+    let prompt = request
+        .messages
+        .iter()
+        .map(|message| message.content.as_str())
+        .collect::<Vec<_>>()
+        .join("\n");
+    let usage = outcome
+        .as_ref()
+        .ok()
+        .and_then(|completion| completion.usage);
+    let record = AuditRecord {
+        timestamp_ms: now_ms(),
+        provider: provider_from_base_url(&config.base_url),
+        model: request.model.clone(),
+        prompt_sha256: prompt_hash(&prompt),
+        latency_ms: started.elapsed().as_millis() as u64,
+        status,
+        usage,
+        estimated_tokens: usage.is_none().then(|| estimate_tokens(&prompt)),
+    };
+    if let Err(e) = audit.append(&record) {
+        eprintln!("Failed to write LLM audit record: {}", e);
+    }
+
+    outcome
+}
+
+/// 大小写不敏感的通配匹配：`*` 匹配任意长度，`?` 匹配单个字符
+fn bundle_pattern_matches(pattern: &str, bundle_id: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_ascii_lowercase().chars().collect();
+    let text: Vec<char> = bundle_id.to_ascii_lowercase().chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = backtrack {
+            p = star_p + 1;
+            t = star_t + 1;
+            backtrack = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::secret_store::EncryptedFileStore;
+    use crate::test_support::TempDir;
 
     #[test]
     fn test_load_env() {
@@ -255,4 +551,206 @@ mod tests {
         assert_eq!(c.api_key, "test-key");
         assert_eq!(c.model, "test-model");
     }
+
+    fn test_client(dir: &Path, privacy: LLMPrivacySettings) -> LLMClient {
+        LLMClient {
+            client: Some(Client::new()),
+            config: LLMConfig {
+                api_key: "test-key".to_string(),
+                privacy,
+                ..LLMConfig::default()
+            },
+            file_path: dir.join("llm_config.json"),
+            audit: AuditLog::at(dir.join("llm_audit.jsonl")),
+            secrets: Arc::new(EncryptedFileStore::in_dir(dir)),
+        }
+    }
+
+    fn test_sources() -> Vec<InputSource> {
+        vec![InputSource {
+            id: "com.apple.keylayout.ABC".to_string(),
+            name: "ABC".to_string(),
+            category: "TISCategoryKeyboardInputSource".to_string(),
+        }]
+    }
+
+    #[test]
+    fn test_bundle_pattern_matches() {
+        assert!(bundle_pattern_matches(
+            "com.acme.*",
+            "com.acme.internal-tool"
+        ));
+        assert!(bundle_pattern_matches("*.Health", "com.apple.health"));
+        assert!(bundle_pattern_matches(
+            "com.example.app?",
+            "com.example.app2"
+        ));
+        assert!(bundle_pattern_matches("com.example.app", "com.example.app"));
+        assert!(!bundle_pattern_matches("com.acme.*", "com.acmecorp.tool"));
+        assert!(!bundle_pattern_matches(
+            "com.example.app?",
+            "com.example.app"
+        ));
+    }
+
+    #[test]
+    fn test_preview_payloads_skips_excluded_apps() {
+        let dir = TempDir::new("llm-test");
+        let client = test_client(
+            &dir,
+            LLMPrivacySettings {
+                excluded_bundle_patterns: vec!["com.acme.*".to_string(), " ".to_string()],
+                identity_mode: AppIdentityMode::NameAndBundleId,
+            },
+        );
+        let apps = vec![
+            SystemApp {
+                name: "Acme VPN".to_string(),
+                bundle_id: "com.acme.vpn".to_string(),
+                path: PathBuf::from("/Applications/Acme VPN.app"),
+            },
+            SystemApp {
+                name: "Safari".to_string(),
+                bundle_id: "com.apple.Safari".to_string(),
+                path: PathBuf::from("/Applications/Safari.app"),
+            },
+        ];
+
+        let preview = client
+            .preview_payloads(&apps, &test_sources())
+            .expect("build preview");
+
+        assert_eq!(
+            preview.excluded_bundle_ids,
+            vec!["com.acme.vpn".to_string()]
+        );
+        assert_eq!(preview.requests.len(), 1);
+        assert_eq!(preview.requests[0].bundle_id, "com.apple.Safari");
+        assert_eq!(
+            preview.requests[0].url,
+            "https://api.openai.com/v1/chat/completions"
+        );
+        assert!(!preview.requests[0].body.to_string().contains("test-key"));
+    }
+
+    #[test]
+    fn test_build_prediction_request_respects_identity_mode() {
+        let prompt_for = |identity_mode| {
+            let dir = TempDir::new("llm-test");
+            let client = test_client(
+                &dir,
+                LLMPrivacySettings {
+                    excluded_bundle_patterns: Vec::new(),
+                    identity_mode,
+                },
+            );
+            client
+                .build_prediction_request("Health Tracker", "com.example.health", &test_sources())
+                .messages[0]
+                .content
+                .clone()
+        };
+
+        let bundle_only = prompt_for(AppIdentityMode::BundleIdOnly);
+        assert!(bundle_only.contains("com.example.health"));
+        assert!(!bundle_only.contains("Health Tracker"));
+
+        let name_only = prompt_for(AppIdentityMode::NameOnly);
+        assert!(name_only.contains("Health Tracker"));
+        assert!(!name_only.contains("com.example.health"));
+
+        let both = prompt_for(AppIdentityMode::NameAndBundleId);
+        assert!(both.contains("Health Tracker"));
+        assert!(both.contains("com.example.health"));
+    }
+
+    #[test]
+    fn test_chat_completion_response_parses_usage() {
+        let raw = r#"{
+            "choices": [{"message": {"role": "assistant", "content": "com.apple.keylayout.ABC"}}],
+            "usage": {"prompt_tokens": 120, "completion_tokens": 8, "total_tokens": 128}
+        }"#;
+        let parsed: ChatCompletionResponse = serde_json::from_str(raw).expect("parse response");
+        assert_eq!(
+            parsed.usage,
+            Some(TokenUsage {
+                prompt_tokens: 120,
+                completion_tokens: 8,
+                total_tokens: 128,
+            })
+        );
+
+        let without_usage: ChatCompletionResponse =
+            serde_json::from_str(r#"{"choices": []}"#).expect("parse response");
+        assert!(without_usage.usage.is_none());
+    }
+
+    #[test]
+    fn test_load_migrates_plaintext_api_key_to_secret_store() {
+        let dir = TempDir::new("llm-test");
+        let file_path = dir.join("llm_config.json");
+        fs::write(
+            &file_path,
+            r#"{"api_key": "sk-legacy", "model": "gpt-4o-mini", "base_url": "https://api.openai.com/v1"}"#,
+        )
+        .expect("write legacy config");
+        let secrets: Arc<dyn SecretStore> = Arc::new(EncryptedFileStore::in_dir(&dir));
+
+        let client = LLMClient::load(file_path.clone(), secrets.clone());
+        assert_eq!(client.get_config().api_key, "sk-legacy");
+
+        let persisted = fs::read_to_string(&file_path).expect("read migrated config");
+        assert!(!persisted.contains("sk-legacy"));
+        assert!(persisted.contains(STORED_API_KEY_MARKER));
+        assert_eq!(
+            secrets.get(API_KEY_SECRET_NAME).unwrap(),
+            Some("sk-legacy".to_string())
+        );
+
+        // 再次加载从 SecretStore 取回真实值
+        let reloaded = LLMClient::load(file_path, secrets);
+        assert_eq!(reloaded.get_config().api_key, "sk-legacy");
+    }
+
+    #[test]
+    fn test_load_keeps_legacy_local_http_endpoint_working() {
+        let dir = TempDir::new("llm-test");
+        let file_path = dir.join("llm_config.json");
+        fs::write(
+            &file_path,
+            r#"{"api_key": "env:OLLAMA_KEY", "model": "qwen2.5", "base_url": "http://localhost:11434/v1"}"#,
+        )
+        .expect("write legacy config");
+        let secrets: Arc<dyn SecretStore> = Arc::new(EncryptedFileStore::in_dir(&dir));
+
+        let config = LLMClient::load(file_path.clone(), secrets.clone()).get_config();
+        assert!(config.network.allow_http_localhost);
+        assert!(validate_endpoint(&config.base_url, &config.network).is_ok());
+
+        // 保存过网络设置后以用户的选择为准
+        let disabled = LLMConfig {
+            network: LLMNetworkSettings::default(),
+            ..config
+        };
+        LLMClient::write_config_file(&file_path, &disabled, secrets.as_ref()).unwrap();
+        let reloaded = LLMClient::load(file_path, secrets).get_config();
+        assert!(!reloaded.network.allow_http_localhost);
+    }
+
+    #[test]
+    fn test_save_keeps_api_key_references_in_config_file() {
+        let dir = TempDir::new("llm-test");
+        let file_path = dir.join("llm_config.json");
+        let secrets: Arc<dyn SecretStore> = Arc::new(EncryptedFileStore::in_dir(&dir));
+        let config = LLMConfig {
+            api_key: "env:OPENAI_API_KEY".to_string(),
+            ..LLMConfig::default()
+        };
+
+        LLMClient::write_config_file(&file_path, &config, secrets.as_ref()).unwrap();
+
+        let persisted = fs::read_to_string(&file_path).expect("read config");
+        assert!(persisted.contains("env:OPENAI_API_KEY"));
+        assert_eq!(secrets.get(API_KEY_SECRET_NAME).unwrap(), None);
+    }
 }
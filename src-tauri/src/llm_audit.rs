@@ -0,0 +1,408 @@
+use crate::error::{AppError, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// OpenAI 兼容接口返回的 `usage` 字段
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TokenUsage {
+    #[serde(default)]
+    pub prompt_tokens: u64,
+    #[serde(default)]
+    pub completion_tokens: u64,
+    #[serde(default)]
+    pub total_tokens: u64,
+}
+
+/// Token 用量上限，未设置表示不限制
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LLMUsageLimits {
+    #[serde(default)]
+    pub max_tokens_per_scan: Option<u64>,
+    #[serde(default)]
+    pub max_tokens_per_month: Option<u64>,
+}
+
+/// 审计日志中的一行，不记录 prompt 原文与 API Key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub timestamp_ms: u64,
+    pub provider: String,
+    pub model: String,
+    pub prompt_sha256: String,
+    pub latency_ms: u64,
+    pub status: String,
+    #[serde(default)]
+    pub usage: Option<TokenUsage>,
+    /// 服务商未返回 usage（或调用失败）时按提示词估算的用量
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimated_tokens: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, PartialEq, Eq)]
+pub struct UsageTotals {
+    pub calls: u64,
+    pub failed_calls: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+    pub estimated_tokens: u64,
+}
+
+impl UsageTotals {
+    fn add(&mut self, record: &AuditRecord) {
+        self.calls += 1;
+        if record.status != STATUS_OK {
+            self.failed_calls += 1;
+        }
+        match record.usage {
+            Some(usage) => {
+                self.prompt_tokens += usage.prompt_tokens;
+                self.completion_tokens += usage.completion_tokens;
+                self.total_tokens += usage.total_tokens;
+            }
+            None => self.estimated_tokens += record.estimated_tokens.unwrap_or(0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageSummary {
+    pub month: String,
+    pub current_month: UsageTotals,
+    pub all_time: UsageTotals,
+}
+
+pub const STATUS_OK: &str = "ok";
+
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+    path: PathBuf,
+}
+
+impl AuditLog {
+    pub fn new() -> Self {
+        let config_dir = dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("smartime");
+        let _ = fs::create_dir_all(&config_dir);
+        Self::at(config_dir.join("llm_audit.jsonl"))
+    }
+
+    pub fn at(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn append(&self, record: &AuditRecord) -> Result<()> {
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+        let mut options = OpenOptions::new();
+        options.create(true).append(true);
+        // 记录包含提示词与应用列表，只允许当前用户读取
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(&self.path)?;
+        file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    /// 读取全部记录，跳过无法解析的行（例如写入中断留下的半行）
+    pub fn read_records(&self) -> Result<Vec<AuditRecord>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = fs::read_to_string(&self.path)?;
+        Ok(content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+
+    pub fn summarize(&self, now_ms: u64) -> Result<UsageSummary> {
+        let month = month_key(now_ms);
+        let mut summary = UsageSummary {
+            month: month.clone(),
+            current_month: UsageTotals::default(),
+            all_time: UsageTotals::default(),
+        };
+
+        for record in self.read_records()? {
+            summary.all_time.add(&record);
+            if month_key(record.timestamp_ms) == month {
+                summary.current_month.add(&record);
+            }
+        }
+
+        Ok(summary)
+    }
+}
+
+/// 单次扫描的 token 预算，调用前检查、调用后累计
+#[derive(Debug, Clone)]
+pub struct ScanBudget {
+    limits: LLMUsageLimits,
+    scan_used: u64,
+    month_used: u64,
+    last_call_tokens: Option<u64>,
+}
+
+impl ScanBudget {
+    pub fn begin(limits: &LLMUsageLimits, audit: &AuditLog) -> Result<Self> {
+        let month_used = if limits.max_tokens_per_month.is_some() {
+            let current_month = audit.summarize(now_ms())?.current_month;
+            current_month.total_tokens + current_month.estimated_tokens
+        } else {
+            0
+        };
+
+        Ok(Self {
+            limits: limits.clone(),
+            scan_used: 0,
+            month_used,
+            last_call_tokens: None,
+        })
+    }
+
+    /// 预估下一次调用的 token 数：优先使用上一次调用的真实用量，否则按 4 字符 ≈ 1 token 估算
+    pub fn estimate(&self, prompt: &str) -> u64 {
+        self.last_call_tokens
+            .unwrap_or_else(|| estimate_tokens(prompt))
+    }
+
+    pub fn check(&self, estimated_tokens: u64) -> Result<()> {
+        if let Some(limit) = self.limits.max_tokens_per_scan {
+            if self.scan_used + estimated_tokens > limit {
+                return Err(AppError::TokenBudget(format!(
+                    "per-scan token cap of {limit} would be exceeded ({} used)",
+                    self.scan_used
+                )));
+            }
+        }
+
+        if let Some(limit) = self.limits.max_tokens_per_month {
+            if self.month_used + estimated_tokens > limit {
+                return Err(AppError::TokenBudget(format!(
+                    "monthly token cap of {limit} would be exceeded ({} used)",
+                    self.month_used
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 累计一次调用的用量，失败的调用同样计入；没有 usage 时按调用前的预估计算
+    pub fn record(&mut self, usage: Option<TokenUsage>, estimated_tokens: u64) {
+        let used = match usage {
+            Some(usage) => {
+                self.last_call_tokens = Some(usage.total_tokens);
+                usage.total_tokens
+            }
+            None => estimated_tokens,
+        };
+        self.scan_used += used;
+        self.month_used += used;
+    }
+}
+
+/// 按 4 字符 ≈ 1 token 粗略估算提示词用量
+pub fn estimate_tokens(prompt: &str) -> u64 {
+    (prompt.chars().count() as u64).div_ceil(4) + 16
+}
+
+pub fn prompt_hash(prompt: &str) -> String {
+    format!("{:x}", Sha256::digest(prompt.as_bytes()))
+}
+
+/// 从 base_url 中提取服务商标识（主机名）
+pub fn provider_from_base_url(base_url: &str) -> String {
+    reqwest::Url::parse(base_url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// UTC 年月，例如 `2026-10`
+fn month_key(timestamp_ms: u64) -> String {
+    // Howard Hinnant 的 civil_from_days 算法
+    let days = (timestamp_ms / 86_400_000) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    fn temp_audit_log() -> (TempDir, AuditLog) {
+        let dir = TempDir::new("llm-audit-test");
+        let audit = AuditLog::at(dir.join("llm_audit.jsonl"));
+        (dir, audit)
+    }
+
+    fn record(timestamp_ms: u64, status: &str, total_tokens: u64) -> AuditRecord {
+        AuditRecord {
+            timestamp_ms,
+            provider: "api.openai.com".to_string(),
+            model: "gpt-4o-mini".to_string(),
+            prompt_sha256: prompt_hash("prompt"),
+            latency_ms: 120,
+            status: status.to_string(),
+            usage: Some(TokenUsage {
+                prompt_tokens: total_tokens - 1,
+                completion_tokens: 1,
+                total_tokens,
+            }),
+            estimated_tokens: None,
+        }
+    }
+
+    #[test]
+    fn test_month_key() {
+        assert_eq!(month_key(0), "1970-01");
+        // 2026-10-18T17:00:00Z
+        assert_eq!(month_key(1_792_342_800_000), "2026-10");
+        // 2024-02-29T12:00:00Z
+        assert_eq!(month_key(1_709_208_000_000), "2024-02");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_audit_log_is_private_to_the_user() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let (_dir, audit) = temp_audit_log();
+        audit.append(&record(0, "ok", 1)).unwrap();
+        let mode = fs::metadata(&audit.path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn test_audit_log_summarizes_current_month_and_all_time() {
+        let (_dir, audit) = temp_audit_log();
+        let october = 1_792_342_800_000;
+        let september = 1_789_000_000_000;
+
+        audit.append(&record(september, STATUS_OK, 100)).unwrap();
+        audit.append(&record(october, STATUS_OK, 40)).unwrap();
+        audit.append(&record(october, "http_429", 10)).unwrap();
+
+        let summary = audit.summarize(october).unwrap();
+        assert_eq!(summary.month, "2026-10");
+        assert_eq!(summary.current_month.calls, 2);
+        assert_eq!(summary.current_month.failed_calls, 1);
+        assert_eq!(summary.current_month.total_tokens, 50);
+        assert_eq!(summary.all_time.calls, 3);
+        assert_eq!(summary.all_time.total_tokens, 150);
+
+        fs::remove_file(&audit.path).expect("remove audit log");
+    }
+
+    #[test]
+    fn test_scan_budget_aborts_before_cap_is_exceeded() {
+        let (_dir, audit) = temp_audit_log();
+        let limits = LLMUsageLimits {
+            max_tokens_per_scan: Some(250),
+            max_tokens_per_month: None,
+        };
+        let mut budget = ScanBudget::begin(&limits, &audit).unwrap();
+
+        budget
+            .check(budget.estimate("x".repeat(400).as_str()))
+            .unwrap();
+        budget.record(
+            Some(TokenUsage {
+                prompt_tokens: 110,
+                completion_tokens: 10,
+                total_tokens: 120,
+            }),
+            0,
+        );
+        budget.check(budget.estimate("anything")).unwrap();
+        budget.record(
+            Some(TokenUsage {
+                prompt_tokens: 110,
+                completion_tokens: 10,
+                total_tokens: 120,
+            }),
+            0,
+        );
+
+        assert_eq!(budget.scan_used, 240);
+        assert!(matches!(
+            budget.check(budget.estimate("anything")),
+            Err(AppError::TokenBudget(_))
+        ));
+    }
+
+    #[test]
+    fn test_scan_budget_counts_calls_without_usage() {
+        let (_dir, audit) = temp_audit_log();
+        let limits = LLMUsageLimits {
+            max_tokens_per_scan: Some(100),
+            max_tokens_per_month: None,
+        };
+        let mut budget = ScanBudget::begin(&limits, &audit).unwrap();
+
+        // 服务商不返回 usage，或调用失败时，按预估计入
+        let estimated = budget.estimate("x".repeat(160).as_str());
+        assert_eq!(estimated, 56);
+        budget.check(estimated).unwrap();
+        budget.record(None, estimated);
+        assert_eq!(budget.scan_used, 56);
+        assert_eq!(budget.estimate("x".repeat(160).as_str()), 56);
+        assert!(matches!(
+            budget.check(estimated),
+            Err(AppError::TokenBudget(_))
+        ));
+    }
+
+    #[test]
+    fn test_scan_budget_counts_existing_monthly_usage() {
+        let (_dir, audit) = temp_audit_log();
+        audit.append(&record(now_ms(), STATUS_OK, 700)).unwrap();
+        audit
+            .append(&AuditRecord {
+                usage: None,
+                estimated_tokens: Some(200),
+                ..record(now_ms(), "network_error", 1)
+            })
+            .unwrap();
+
+        let limits = LLMUsageLimits {
+            max_tokens_per_scan: None,
+            max_tokens_per_month: Some(1_000),
+        };
+        let budget = ScanBudget::begin(&limits, &audit).unwrap();
+        assert!(budget.check(50).is_ok());
+        assert!(budget.check(101).is_err());
+
+        fs::remove_file(&audit.path).expect("remove audit log");
+    }
+}
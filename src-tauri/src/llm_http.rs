@@ -0,0 +1,210 @@
+use crate::config::expand_home_path;
+use crate::error::{AppError, Result};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION};
+use reqwest::{Certificate, Client, NoProxy, Proxy, Url};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::net::IpAddr;
+
+/// LLM 请求的网络设置（代理、附加请求头、自定义根证书）
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct LLMNetworkSettings {
+    /// 例如 `http://proxy.corp:3128`，留空表示直连
+    #[serde(default)]
+    pub proxy_url: Option<String>,
+    /// 不走代理的主机，语法同 `NO_PROXY`
+    #[serde(default)]
+    pub no_proxy: Vec<String>,
+    #[serde(default)]
+    pub extra_headers: BTreeMap<String, String>,
+    /// PEM 格式的根证书包路径，支持 `~/`
+    #[serde(default)]
+    pub ca_bundle_path: Option<String>,
+    /// 仅允许 localhost 使用明文 `http://`
+    #[serde(default)]
+    pub allow_http_localhost: bool,
+}
+
+/// 所有 LLM 调用共用的 HTTP Client 构建入口
+pub fn build_http_client(settings: &LLMNetworkSettings) -> Result<Client> {
+    let mut builder = Client::builder().https_only(!settings.allow_http_localhost);
+
+    if let Some(proxy_url) = non_empty(settings.proxy_url.as_deref()) {
+        let no_proxy = settings
+            .no_proxy
+            .iter()
+            .map(|host| host.trim())
+            .filter(|host| !host.is_empty())
+            .collect::<Vec<_>>()
+            .join(",");
+        let proxy = Proxy::all(proxy_url)
+            .map_err(|e| AppError::Config(format!("Invalid proxy URL {proxy_url}: {e}")))?
+            .no_proxy(NoProxy::from_string(&no_proxy));
+        builder = builder.proxy(proxy);
+    }
+
+    if !settings.extra_headers.is_empty() {
+        builder = builder.default_headers(extra_header_map(&settings.extra_headers)?);
+    }
+
+    if let Some(ca_path) = non_empty(settings.ca_bundle_path.as_deref()) {
+        let path = expand_home_path(ca_path);
+        let pem = fs::read(&path).map_err(|e| {
+            AppError::Config(format!("Failed to read CA bundle {}: {e}", path.display()))
+        })?;
+        let certificates = Certificate::from_pem_bundle(&pem)
+            .map_err(|e| AppError::Config(format!("Invalid CA bundle {}: {e}", path.display())))?;
+        if certificates.is_empty() {
+            return Err(AppError::Config(format!(
+                "CA bundle {} contains no certificates",
+                path.display()
+            )));
+        }
+        for certificate in certificates {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+
+    builder
+        .build()
+        .map_err(|e| AppError::Config(format!("Failed to build HTTP client: {e}")))
+}
+
+/// 校验 LLM 接口地址：默认只允许 https，明文 http 需显式开启且仅限本机
+pub fn validate_endpoint(base_url: &str, settings: &LLMNetworkSettings) -> Result<()> {
+    let url = Url::parse(base_url.trim())
+        .map_err(|e| AppError::Config(format!("Invalid LLM base URL {base_url}: {e}")))?;
+
+    match url.scheme() {
+        "https" => Ok(()),
+        "http" if settings.allow_http_localhost && is_localhost(&url) => Ok(()),
+        "http" if is_localhost(&url) => Err(AppError::Config(format!(
+            "Plain http:// LLM endpoint {base_url} requires enabling \
+             network.allow_http_localhost in llm_config.json"
+        ))),
+        "http" => Err(AppError::Config(format!(
+            "Plain http:// LLM endpoints are only allowed for localhost: {base_url}"
+        ))),
+        scheme => Err(AppError::Config(format!(
+            "Unsupported LLM base URL scheme: {scheme}"
+        ))),
+    }
+}
+
+/// 旧版本没有明文 http 开关，升级前已指向本机 http 接口（如 Ollama）的配置自动开启
+pub fn legacy_network_settings(base_url: &str) -> LLMNetworkSettings {
+    let allow_http_localhost =
+        Url::parse(base_url.trim()).is_ok_and(|url| url.scheme() == "http" && is_localhost(&url));
+    LLMNetworkSettings {
+        allow_http_localhost,
+        ..LLMNetworkSettings::default()
+    }
+}
+
+fn extra_header_map(headers: &BTreeMap<String, String>) -> Result<HeaderMap> {
+    let mut map = HeaderMap::with_capacity(headers.len());
+    for (name, value) in headers {
+        let header_name = HeaderName::from_bytes(name.trim().as_bytes())
+            .map_err(|e| AppError::Config(format!("Invalid header name {name}: {e}")))?;
+        // API Key 统一由凭据配置注入，避免被附加请求头覆盖
+        if header_name == AUTHORIZATION {
+            return Err(AppError::Config(
+                "Authorization cannot be set through extra headers".to_string(),
+            ));
+        }
+        let header_value = HeaderValue::from_str(value.trim())
+            .map_err(|e| AppError::Config(format!("Invalid value for header {name}: {e}")))?;
+        map.insert(header_name, header_value);
+    }
+    Ok(map)
+}
+
+fn is_localhost(url: &Url) -> bool {
+    match url.host_str() {
+        Some("localhost") => true,
+        Some(host) => host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback()),
+        None => false,
+    }
+}
+
+fn non_empty(value: Option<&str>) -> Option<&str> {
+    value.map(str::trim).filter(|value| !value.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_endpoint_requires_https_by_default() {
+        let settings = LLMNetworkSettings::default();
+        assert!(validate_endpoint("https://api.openai.com/v1", &settings).is_ok());
+        assert!(validate_endpoint("http://localhost:11434/v1", &settings).is_err());
+        assert!(validate_endpoint("ftp://example.com", &settings).is_err());
+    }
+
+    #[test]
+    fn test_validate_endpoint_allows_http_only_for_localhost() {
+        let settings = LLMNetworkSettings {
+            allow_http_localhost: true,
+            ..LLMNetworkSettings::default()
+        };
+        assert!(validate_endpoint("http://localhost:11434/v1", &settings).is_ok());
+        assert!(validate_endpoint("http://127.0.0.1:8080/v1", &settings).is_ok());
+        assert!(validate_endpoint("http://[::1]:8080/v1", &settings).is_ok());
+        assert!(validate_endpoint("http://llm.internal.corp/v1", &settings).is_err());
+    }
+
+    #[test]
+    fn test_validate_endpoint_error_names_http_setting() {
+        let error = validate_endpoint("http://localhost:11434/v1", &LLMNetworkSettings::default())
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("network.allow_http_localhost"));
+    }
+
+    #[test]
+    fn test_legacy_network_settings_allow_only_local_http() {
+        assert!(legacy_network_settings("http://localhost:11434/v1").allow_http_localhost);
+        assert!(legacy_network_settings("http://127.0.0.1:8080/v1").allow_http_localhost);
+        assert!(!legacy_network_settings("https://api.openai.com/v1").allow_http_localhost);
+        assert!(!legacy_network_settings("http://llm.internal.corp/v1").allow_http_localhost);
+    }
+
+    #[test]
+    fn test_build_http_client_with_proxy_and_headers() {
+        let settings = LLMNetworkSettings {
+            proxy_url: Some("http://proxy.corp:3128".to_string()),
+            no_proxy: vec!["localhost".to_string(), ".internal.corp".to_string()],
+            extra_headers: BTreeMap::from([("X-Org-Id".to_string(), "org-42".to_string())]),
+            ..LLMNetworkSettings::default()
+        };
+        assert!(build_http_client(&settings).is_ok());
+    }
+
+    #[test]
+    fn test_build_http_client_rejects_invalid_settings() {
+        let authorization_override = LLMNetworkSettings {
+            extra_headers: BTreeMap::from([("Authorization".to_string(), "x".to_string())]),
+            ..LLMNetworkSettings::default()
+        };
+        assert!(build_http_client(&authorization_override).is_err());
+
+        let invalid_header = LLMNetworkSettings {
+            extra_headers: BTreeMap::from([("X Bad".to_string(), "x".to_string())]),
+            ..LLMNetworkSettings::default()
+        };
+        assert!(build_http_client(&invalid_header).is_err());
+
+        let missing_ca = LLMNetworkSettings {
+            ca_bundle_path: Some("/nonexistent/smartime-ca.pem".to_string()),
+            ..LLMNetworkSettings::default()
+        };
+        assert!(build_http_client(&missing_ca).is_err());
+    }
+}
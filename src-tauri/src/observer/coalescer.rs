@@ -0,0 +1,244 @@
+use super::AppFocusedEvent;
+use serde::Serialize;
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// 焦点事件的处理统计
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct CoalescerMetrics {
+    pub received: u64,
+    /// 等待稳定期间被更新的焦点覆盖而丢弃
+    pub dropped: u64,
+    /// 已交给切换引擎
+    pub dispatched: u64,
+    /// 已派发但执行前被更新的焦点取代
+    pub cancelled: u64,
+}
+
+/// 最新优先的焦点合并器，时间由调用方传入
+///
+/// 每个焦点都有递增的代数，焦点在 `settle_delay` 内没有被新焦点取代才会派发。
+pub struct FocusCoalescer {
+    settle_delay: Duration,
+    pending: Option<(AppFocusedEvent, Instant)>,
+    generation: u64,
+    metrics: CoalescerMetrics,
+}
+
+impl FocusCoalescer {
+    pub fn new(settle_delay: Duration) -> Self {
+        Self {
+            settle_delay,
+            pending: None,
+            generation: 0,
+            metrics: CoalescerMetrics::default(),
+        }
+    }
+
+    pub fn set_settle_delay(&mut self, settle_delay: Duration) {
+        self.settle_delay = settle_delay;
+    }
+
+    /// 记录新焦点并返回其代数，尚未派发的旧焦点被丢弃
+    pub fn push(&mut self, event: AppFocusedEvent, now: Instant) -> u64 {
+        self.metrics.received += 1;
+        if self.pending.is_some() {
+            self.metrics.dropped += 1;
+        }
+        self.generation += 1;
+        self.pending = Some((event, now + self.settle_delay));
+        self.generation
+    }
+
+    /// 下一个待派发焦点的到期时间
+    pub fn deadline(&self) -> Option<Instant> {
+        self.pending.as_ref().map(|(_, due)| *due)
+    }
+
+    /// 取出已稳定的焦点及其代数
+    pub fn poll(&mut self, now: Instant) -> Option<(u64, AppFocusedEvent)> {
+        if self.deadline()? > now {
+            return None;
+        }
+        let (event, _) = self.pending.take()?;
+        self.metrics.dispatched += 1;
+        Some((self.generation, event))
+    }
+
+    /// 已派发的焦点是否仍是最新焦点
+    pub fn is_current(&self, generation: u64) -> bool {
+        self.generation == generation
+    }
+
+    /// 没有待派发的焦点时返回最新代数
+    pub fn settled_generation(&self) -> Option<u64> {
+        self.pending.is_none().then_some(self.generation)
+    }
+
+    pub fn record_cancelled(&mut self) {
+        self.metrics.cancelled += 1;
+    }
+
+    pub fn metrics(&self) -> CoalescerMetrics {
+        self.metrics.clone()
+    }
+}
+
+struct QueueState {
+    coalescer: FocusCoalescer,
+    closed: bool,
+}
+
+/// 跨线程共享的 `FocusCoalescer`：接收线程写入，切换线程等待稳定后的焦点
+pub struct FocusQueue {
+    state: Mutex<QueueState>,
+    changed: Condvar,
+}
+
+impl FocusQueue {
+    pub fn new(settle_delay: Duration) -> Self {
+        Self {
+            state: Mutex::new(QueueState {
+                coalescer: FocusCoalescer::new(settle_delay),
+                closed: false,
+            }),
+            changed: Condvar::new(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, QueueState> {
+        // 状态只包含计数与待派发事件，锁中毒后继续使用不会破坏一致性
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn push(&self, event: AppFocusedEvent, settle_delay: Duration) {
+        let mut state = self.lock();
+        state.coalescer.set_settle_delay(settle_delay);
+        state.coalescer.push(event, Instant::now());
+        self.changed.notify_all();
+    }
+
+    /// 不再有新焦点，`wait_next` 在派发完剩余焦点后返回 `None`
+    pub fn close(&self) {
+        self.lock().closed = true;
+        self.changed.notify_all();
+    }
+
+    /// 阻塞直到有焦点稳定
+    pub fn wait_next(&self) -> Option<(u64, AppFocusedEvent)> {
+        let mut state = self.lock();
+        loop {
+            let now = Instant::now();
+            if let Some(next) = state.coalescer.poll(now) {
+                return Some(next);
+            }
+            state = match state.coalescer.deadline() {
+                Some(due) => {
+                    self.changed
+                        .wait_timeout(state, due.saturating_duration_since(now))
+                        .unwrap_or_else(|e| e.into_inner())
+                        .0
+                }
+                None if state.closed => return None,
+                None => self.changed.wait(state).unwrap_or_else(|e| e.into_inner()),
+            };
+        }
+    }
+
+    pub fn is_current(&self, generation: u64) -> bool {
+        self.lock().coalescer.is_current(generation)
+    }
+
+    pub fn settled_generation(&self) -> Option<u64> {
+        self.lock().coalescer.settled_generation()
+    }
+
+    pub fn record_cancelled(&self) {
+        self.lock().coalescer.record_cancelled();
+    }
+
+    pub fn metrics(&self) -> CoalescerMetrics {
+        self.lock().coalescer.metrics()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn focus(bundle_id: &str) -> AppFocusedEvent {
+        AppFocusedEvent {
+            bundle_id: bundle_id.to_string(),
+            app_name: bundle_id.to_string(),
+            pid: None,
+            window_title: None,
+        }
+    }
+
+    #[test]
+    fn test_coalescer_dispatches_only_settled_focus() {
+        let start = Instant::now();
+        let ms = |value: u64| start + Duration::from_millis(value);
+        let mut coalescer = FocusCoalescer::new(Duration::from_millis(100));
+
+        coalescer.push(focus("com.apple.Safari"), ms(0));
+        coalescer.push(focus("com.tencent.xinWeChat"), ms(30));
+        let latest = coalescer.push(focus("com.microsoft.VSCode"), ms(60));
+
+        assert_eq!(coalescer.poll(ms(100)), None);
+        assert_eq!(coalescer.deadline(), Some(ms(160)));
+        assert_eq!(coalescer.settled_generation(), None);
+        let (generation, event) = coalescer.poll(ms(160)).unwrap();
+        assert_eq!(generation, latest);
+        assert_eq!(coalescer.settled_generation(), Some(latest));
+        assert_eq!(event.bundle_id, "com.microsoft.VSCode");
+        assert_eq!(coalescer.poll(ms(500)), None);
+
+        assert_eq!(
+            coalescer.metrics(),
+            CoalescerMetrics {
+                received: 3,
+                dropped: 2,
+                dispatched: 1,
+                cancelled: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_coalescer_zero_delay_dispatches_immediately() {
+        let now = Instant::now();
+        let mut coalescer = FocusCoalescer::new(Duration::ZERO);
+        coalescer.push(focus("com.apple.Terminal"), now);
+        assert_eq!(
+            coalescer.poll(now).map(|(_, event)| event.bundle_id),
+            Some("com.apple.Terminal".to_string())
+        );
+    }
+
+    #[test]
+    fn test_focus_queue_supersedes_dispatched_focus() {
+        let queue = Arc::new(FocusQueue::new(Duration::ZERO));
+        queue.push(focus("com.tencent.xinWeChat"), Duration::ZERO);
+        let (generation, _) = queue.wait_next().unwrap();
+        assert!(queue.is_current(generation));
+
+        // 派发后、执行前来了新焦点：旧的切换应被取消
+        queue.push(focus("com.microsoft.VSCode"), Duration::ZERO);
+        assert!(!queue.is_current(generation));
+        queue.record_cancelled();
+
+        let worker = {
+            let queue = queue.clone();
+            std::thread::spawn(move || {
+                std::iter::from_fn(|| queue.wait_next())
+                    .map(|(_, event)| event.bundle_id)
+                    .collect::<Vec<_>>()
+            })
+        };
+        queue.close();
+        assert_eq!(worker.join().unwrap(), vec!["com.microsoft.VSCode"]);
+        assert_eq!(queue.metrics().cancelled, 1);
+    }
+}
@@ -0,0 +1,98 @@
+use super::AppFocusedEvent;
+use crate::config::DEFAULT_IGNORED_APPS;
+
+/// 过滤重复焦点与忽略名单中的应用
+///
+/// 忽略名单由内置名单与用户配置合并而成。被忽略的应用对观察者不可见：既不触发切换，也不更新去重用的上一个应用，
+/// 因此从浮层回到原应用时不会重复切换。
+pub struct FocusFilter {
+    own_pid: u32,
+    builtin_ignored: &'static [&'static str],
+    last_bundle_id: Option<String>,
+}
+
+impl FocusFilter {
+    pub fn new(own_pid: u32) -> Self {
+        Self {
+            own_pid,
+            builtin_ignored: DEFAULT_IGNORED_APPS,
+            last_bundle_id: None,
+        }
+    }
+
+    /// `ignored_apps` 是用户添加的条目，与内置名单一起生效
+    pub fn accept(&mut self, event: &AppFocusedEvent, ignored_apps: &[String]) -> bool {
+        let ignored = self
+            .builtin_ignored
+            .iter()
+            .copied()
+            .chain(ignored_apps.iter().map(String::as_str))
+            .any(|ignored| ignored.eq_ignore_ascii_case(&event.bundle_id));
+        if event.pid == Some(self.own_pid) || ignored {
+            return false;
+        }
+        if self.last_bundle_id.as_deref() == Some(event.bundle_id.as_str()) {
+            return false;
+        }
+        self.last_bundle_id = Some(event.bundle_id.clone());
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OWN_PID: u32 = 4242;
+
+    fn focus(bundle_id: &str, pid: u32) -> AppFocusedEvent {
+        AppFocusedEvent {
+            bundle_id: bundle_id.to_string(),
+            app_name: bundle_id.to_string(),
+            pid: Some(pid),
+            window_title: None,
+        }
+    }
+
+    fn accepted(events: &[AppFocusedEvent], ignored_apps: &[String]) -> Vec<String> {
+        let mut filter = FocusFilter::new(OWN_PID);
+        events
+            .iter()
+            .filter(|event| filter.accept(event, ignored_apps))
+            .map(|event| event.bundle_id.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_ignored_overlay_does_not_break_dedup() {
+        let events = [
+            focus("com.tinyspeck.slackmacgap", 100),
+            focus("com.apple.Spotlight", 200),
+            focus("com.tinyspeck.slackmacgap", 100),
+            focus("com.raycast.macos", 300),
+            focus("com.microsoft.VSCode", 400),
+            // SmartIME 窗口按进程识别，无论上报的 ID 是什么
+            focus("SmartIME", OWN_PID),
+            focus("com.microsoft.VSCode", 400),
+        ];
+        assert_eq!(
+            accepted(&events, &[]),
+            vec!["com.tinyspeck.slackmacgap", "com.microsoft.VSCode"]
+        );
+    }
+
+    #[test]
+    fn test_user_ignored_apps_extend_builtin_list() {
+        let events = [
+            focus("com.tinyspeck.slackmacgap", 100),
+            focus("com.apple.Spotlight", 200),
+            focus("com.microsoft.VSCode", 300),
+            focus("com.tinyspeck.slackmacgap", 100),
+        ];
+        // 用户条目不会替换内置名单，匹配不区分大小写
+        assert_eq!(
+            accepted(&events, &["COM.TINYSPECK.SLACKMACGAP".to_string()]),
+            vec!["com.microsoft.VSCode"]
+        );
+    }
+}
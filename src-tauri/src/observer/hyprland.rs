@@ -0,0 +1,133 @@
+use super::AppFocusedEvent;
+use crate::error::{AppError, Result};
+use std::io::{BufRead, BufReader};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+
+const SOCKET_NAME: &str = ".socket2.sock";
+const ACTIVE_WINDOW_EVENT: &str = "activewindow>>";
+
+/// 连接 Hyprland 的事件 socket 并在后台线程中把焦点变化发送到 `tx`
+pub fn start(tx: Sender<AppFocusedEvent>) -> Result<()> {
+    let signature = std::env::var("HYPRLAND_INSTANCE_SIGNATURE")
+        .map_err(|_| AppError::Observer("HYPRLAND_INSTANCE_SIGNATURE is not set".to_string()))?;
+    let socket = socket_path(&signature)
+        .ok_or_else(|| AppError::Observer("Hyprland event socket not found".to_string()))?;
+    let stream = connect(&socket)?;
+    std::thread::spawn(move || run(stream, tx));
+    Ok(())
+}
+
+/// 新版本位于 `$XDG_RUNTIME_DIR/hypr`，0.40 之前位于 `/tmp/hypr`
+fn socket_path(signature: &str) -> Option<PathBuf> {
+    std::env::var_os("XDG_RUNTIME_DIR")
+        .map(|dir| PathBuf::from(dir).join("hypr"))
+        .into_iter()
+        .chain([PathBuf::from("/tmp/hypr")])
+        .map(|dir| dir.join(signature).join(SOCKET_NAME))
+        .find(|path| path.exists())
+}
+
+fn connect(socket: &Path) -> Result<UnixStream> {
+    UnixStream::connect(socket).map_err(|e| {
+        AppError::Observer(format!(
+            "Hyprland connect to {} failed: {e}",
+            socket.display()
+        ))
+    })
+}
+
+/// 逐行读取事件；接收端关闭或连接断开时退出
+fn run(stream: UnixStream, tx: Sender<AppFocusedEvent>) {
+    for line in BufReader::new(stream).lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                eprintln!("Hyprland focus observer stopped: {}", e);
+                return;
+            }
+        };
+        if let Some(event) = parse_event_line(&line) {
+            if tx.send(event).is_err() {
+                return;
+            }
+        }
+    }
+    eprintln!("Hyprland focus observer stopped: event socket closed");
+}
+
+/// `activewindow>>class,title`，标题本身可能包含逗号，class 不会
+fn parse_event_line(line: &str) -> Option<AppFocusedEvent> {
+    let data = line.strip_prefix(ACTIVE_WINDOW_EVENT)?;
+    let (class, title) = data.split_once(',').unwrap_or((data, ""));
+    let class = class.trim();
+    if class.is_empty() {
+        return None;
+    }
+
+    Some(AppFocusedEvent {
+        bundle_id: class.to_string(),
+        app_name: class.to_string(),
+        pid: None,
+        window_title: Some(title.to_string()).filter(|title| !title.is_empty()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::os::unix::net::UnixListener;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    // 从 Hyprland 0.41 抓取的事件流（已精简）
+    const CAPTURED_EVENTS: &str = "workspace>>2\n\
+        activewindow>>kitty,~/code/smartime\n\
+        activewindowv2>>5a8e2c0\n\
+        activewindow>>,\n\
+        activewindow>>org.telegram.desktop,Telegram (3, unread)\n\
+        openwindow>>5a8e3f0,2,firefox,Mozilla Firefox\n";
+
+    #[test]
+    fn test_parse_event_line() {
+        let event =
+            parse_event_line("activewindow>>firefox,Docs, Sheets - Mozilla Firefox").unwrap();
+        assert_eq!(event.bundle_id, "firefox");
+        assert_eq!(
+            event.window_title.as_deref(),
+            Some("Docs, Sheets - Mozilla Firefox")
+        );
+
+        // 焦点落在空工作区时 class 为空
+        assert!(parse_event_line("activewindow>>,").is_none());
+        assert!(parse_event_line("activewindowv2>>5a8e2c0").is_none());
+    }
+
+    #[test]
+    fn test_hyprland_focus_source_replays_fake_server() {
+        let dir = crate::test_support::TempDir::new("hyprland-test");
+        let socket = dir.join(SOCKET_NAME);
+        let listener = UnixListener::bind(&socket).unwrap();
+
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream.write_all(CAPTURED_EVENTS.as_bytes()).unwrap();
+        });
+
+        let stream = connect(&socket).unwrap();
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || run(stream, tx));
+
+        let first = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(first.bundle_id, "kitty");
+        assert_eq!(first.window_title.as_deref(), Some("~/code/smartime"));
+        let second = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(second.bundle_id, "org.telegram.desktop");
+        assert_eq!(second.window_title.as_deref(), Some("Telegram (3, unread)"));
+
+        server.join().unwrap();
+        assert!(rx.recv_timeout(Duration::from_secs(5)).is_err());
+    }
+}
@@ -0,0 +1,105 @@
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::time::{Duration, Instant};
+
+/// 应用失去焦点时记录下的输入法
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LearnedInput {
+    pub bundle_id: String,
+    pub app_name: String,
+    pub input_id: String,
+}
+
+/// 在独立线程上学习输入法
+///
+/// `learn` 立即更新内存中的规则并返回是否有变化；有变化后最多等待 `save_delay`
+/// 调用一次 `persist`，期间的多次学习合并为一次写盘。
+pub fn spawn<L, P>(save_delay: Duration, mut learn: L, mut persist: P) -> Sender<LearnedInput>
+where
+    L: FnMut(&LearnedInput) -> bool + Send + 'static,
+    P: FnMut() + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel::<LearnedInput>();
+    std::thread::spawn(move || {
+        let mut save_at: Option<Instant> = None;
+        loop {
+            let received = match save_at {
+                Some(deadline) => {
+                    rx.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                }
+                None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            match received {
+                Ok(input) => {
+                    if learn(&input) && save_at.is_none() {
+                        save_at = Some(Instant::now() + save_delay);
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    persist();
+                    save_at = None;
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    if save_at.is_some() {
+                        persist();
+                    }
+                    return;
+                }
+            }
+        }
+    });
+    tx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    fn learned(bundle_id: &str, input_id: &str) -> LearnedInput {
+        LearnedInput {
+            bundle_id: bundle_id.to_string(),
+            app_name: bundle_id.to_string(),
+            input_id: input_id.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_learned_inputs_are_saved_once_per_burst() {
+        let learned_inputs = Arc::new(Mutex::new(Vec::new()));
+        let (saved_tx, saved_rx) = mpsc::channel();
+        let learner = spawn(
+            Duration::from_millis(100),
+            {
+                let learned_inputs = learned_inputs.clone();
+                // 与已有规则相同的输入法不算变化
+                move |input: &LearnedInput| {
+                    let mut learned_inputs = learned_inputs.lock().unwrap();
+                    let changed = !learned_inputs.contains(input);
+                    learned_inputs.push(input.clone());
+                    changed
+                }
+            },
+            move || saved_tx.send(()).unwrap(),
+        );
+
+        learner
+            .send(learned("com.tencent.xinWeChat", "pinyin"))
+            .unwrap();
+        learner.send(learned("com.apple.Terminal", "abc")).unwrap();
+        learner
+            .send(learned("com.tencent.xinWeChat", "pinyin"))
+            .unwrap();
+        assert!(saved_rx.recv_timeout(Duration::from_secs(5)).is_ok());
+        assert!(saved_rx.recv_timeout(Duration::from_millis(300)).is_err());
+        assert_eq!(learned_inputs.lock().unwrap().len(), 3);
+
+        // 没有变化的学习不会写盘
+        learner.send(learned("com.apple.Terminal", "abc")).unwrap();
+        assert!(saved_rx.recv_timeout(Duration::from_millis(300)).is_err());
+
+        // 退出前写入尚未保存的修改
+        learner.send(learned("com.apple.Notes", "pinyin")).unwrap();
+        drop(learner);
+        assert!(saved_rx.recv_timeout(Duration::from_secs(5)).is_ok());
+    }
+}
@@ -0,0 +1,186 @@
+#![allow(deprecated)] // Suppress warnings for deprecated cocoa APIs
+
+use super::{AppFocusedEvent, SessionEvent};
+use cocoa::base::{id, nil};
+use cocoa::foundation::{NSAutoreleasePool, NSString};
+use objc::declare::ClassDecl;
+use objc::runtime::{Object, Sel};
+use objc::{class, msg_send, sel, sel_impl};
+use once_cell::sync::OnceCell;
+use std::ffi::CStr;
+use std::sync::mpsc::Sender;
+use std::sync::Once;
+
+// 全局 Channel Sender，用于从 FFI 回调向主线程发送消息
+static APP_EVENT_TX: OnceCell<Sender<AppFocusedEvent>> = OnceCell::new();
+static SESSION_EVENT_TX: OnceCell<Sender<SessionEvent>> = OnceCell::new();
+static REGISTER_OBSERVER_CLASS: Once = Once::new();
+
+/// 监听 NSWorkspace 的应用激活通知，并把事件发送到 `tx`
+pub fn start(tx: Sender<AppFocusedEvent>) {
+    // 保存 Sender 到全局变量
+    if APP_EVENT_TX.set(tx).is_err() {
+        eprintln!("Failed to set global sender for app observer");
+        return;
+    }
+
+    unsafe {
+        register_observer_class();
+        let pool = NSAutoreleasePool::new(nil);
+        let observer = new_observer();
+
+        // 获取 NotificationCenter
+        let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+        let notification_center: id = msg_send![workspace, notificationCenter];
+
+        // 添加观察者
+        // 使用 NSString 创建通知名称，避免链接错误
+        let notification_name =
+            NSString::alloc(nil).init_str("NSWorkspaceDidActivateApplicationNotification");
+
+        let _: () = msg_send![notification_center,
+            addObserver: observer
+            selector: sel!(appActivated:)
+            name: notification_name
+            object: nil
+        ];
+
+        pool.drain();
+    }
+}
+
+/// 监听睡眠唤醒、屏幕唤醒、解锁与切回用户会话，并把事件发送到 `tx`
+pub fn start_session(tx: Sender<SessionEvent>) {
+    if SESSION_EVENT_TX.set(tx).is_err() {
+        eprintln!("Failed to set global sender for session observer");
+        return;
+    }
+
+    unsafe {
+        register_observer_class();
+        let pool = NSAutoreleasePool::new(nil);
+        let observer = new_observer();
+
+        let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+        let workspace_center: id = msg_send![workspace, notificationCenter];
+        for (name, selector) in [
+            ("NSWorkspaceDidWakeNotification", sel!(sessionResumed:)),
+            (
+                "NSWorkspaceScreensDidWakeNotification",
+                sel!(sessionResumed:),
+            ),
+            (
+                "NSWorkspaceSessionDidBecomeActiveNotification",
+                sel!(sessionUnlocked:),
+            ),
+        ] {
+            let notification_name = NSString::alloc(nil).init_str(name);
+            let _: () = msg_send![workspace_center,
+                addObserver: observer
+                selector: selector
+                name: notification_name
+                object: nil
+            ];
+        }
+
+        // 锁屏解除只有分布式通知
+        let distributed_center: id =
+            msg_send![class!(NSDistributedNotificationCenter), defaultCenter];
+        let notification_name = NSString::alloc(nil).init_str("com.apple.screenIsUnlocked");
+        let _: () = msg_send![distributed_center,
+            addObserver: observer
+            selector: sel!(sessionUnlocked:)
+            name: notification_name
+            object: nil
+        ];
+
+        pool.drain();
+    }
+}
+
+/// 注册 Objective-C 类
+unsafe fn register_observer_class() {
+    REGISTER_OBSERVER_CLASS.call_once(|| {
+        let superclass = class!(NSObject);
+        let mut decl =
+            ClassDecl::new("RustAppObserver", superclass).expect("Failed to declare class");
+
+        decl.add_method(
+            sel!(appActivated:),
+            app_activated_impl as extern "C" fn(&Object, Sel, id),
+        );
+        decl.add_method(
+            sel!(sessionResumed:),
+            session_resumed_impl as extern "C" fn(&Object, Sel, id),
+        );
+        decl.add_method(
+            sel!(sessionUnlocked:),
+            session_unlocked_impl as extern "C" fn(&Object, Sel, id),
+        );
+
+        decl.register();
+    });
+}
+
+/// 实例化 Observer，故意泄漏引用防止被回收
+unsafe fn new_observer() -> id {
+    let observer_class = class!(RustAppObserver);
+    let observer: id = msg_send![observer_class, new];
+    let _ = Box::leak(Box::new(observer));
+    observer
+}
+
+extern "C" fn session_resumed_impl(_this: &Object, _cmd: Sel, _notification: id) {
+    send_session_event(SessionEvent::Resumed);
+}
+
+extern "C" fn session_unlocked_impl(_this: &Object, _cmd: Sel, _notification: id) {
+    send_session_event(SessionEvent::Unlocked);
+}
+
+fn send_session_event(event: SessionEvent) {
+    if let Some(tx) = SESSION_EVENT_TX.get() {
+        let _ = tx.send(event);
+    }
+}
+
+// Objective-C 回调函数
+extern "C" fn app_activated_impl(_this: &Object, _cmd: Sel, notification: id) {
+    unsafe {
+        let pool = NSAutoreleasePool::new(nil);
+        let user_info: id = msg_send![notification, userInfo];
+        let key = NSString::alloc(nil).init_str("NSWorkspaceApplicationKey");
+        let app: id = msg_send![user_info, objectForKey: key];
+
+        if app != nil {
+            let bundle_id: id = msg_send![app, bundleIdentifier];
+            let app_name: id = msg_send![app, localizedName];
+            let pid: i32 = msg_send![app, processIdentifier];
+
+            let b_id = nsstring_to_owned(bundle_id).unwrap_or_else(|| "unknown".to_string());
+            let a_name = nsstring_to_owned(app_name).unwrap_or_else(|| "unknown".to_string());
+
+            // 发送到 Channel
+            if let Some(tx) = APP_EVENT_TX.get() {
+                let _ = tx.send(AppFocusedEvent {
+                    bundle_id: b_id,
+                    app_name: a_name,
+                    pid: u32::try_from(pid).ok(),
+                    window_title: None,
+                });
+            }
+        }
+        pool.drain();
+    }
+}
+
+unsafe fn nsstring_to_owned(value: id) -> Option<String> {
+    if value == nil {
+        return None;
+    }
+    let ptr = cocoa::foundation::NSString::UTF8String(value);
+    if ptr.is_null() {
+        return None;
+    }
+    Some(CStr::from_ptr(ptr).to_string_lossy().into_owned())
+}
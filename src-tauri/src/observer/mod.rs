@@ -0,0 +1,795 @@
+use crate::command::run_backend_task;
+use crate::config::{AppState, RuleTarget};
+use crate::error::{AppError, Result};
+use crate::input_source::{ChangeOrigin, DryRunBackend, InputSource, InputSourceBackend};
+use crate::rule_snapshot::RuleSnapshot;
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+mod coalescer;
+mod filter;
+#[cfg(target_os = "linux")]
+mod hyprland;
+mod learning;
+#[cfg(target_os = "macos")]
+mod macos;
+mod replay;
+#[cfg(target_os = "linux")]
+mod session;
+#[cfg(target_os = "linux")]
+mod shell_bridge;
+#[cfg(target_os = "linux")]
+mod sway;
+mod switcher;
+mod timing;
+#[cfg(target_os = "linux")]
+mod x11;
+
+pub use coalescer::{CoalescerMetrics, FocusQueue};
+use filter::FocusFilter;
+use learning::LearnedInput;
+pub use replay::{replay, run_from_args as run_replay_from_args, FocusRecording, ReplayReport};
+use switcher::{FocusSwitcher, SwitchContext};
+use timing::{run_timed_switch, SwitchDriver};
+
+// 每次在后端线程上执行切换步骤的超时
+const SWITCH_TIMEOUT: Duration = Duration::from_millis(500);
+// 唤醒或解锁后系统会恢复自己保存的输入法，等它完成再执行规则
+const SESSION_SETTLE_DELAY: Duration = Duration::from_millis(500);
+// 学习到的输入法合并后写入配置文件的最长等待
+const LEARN_SAVE_DELAY: Duration = Duration::from_secs(2);
+
+// 定义事件数据结构
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct AppFocusedEvent {
+    pub bundle_id: String,
+    pub app_name: String,
+    pub pid: Option<u32>,
+    pub window_title: Option<String>,
+}
+
+/// 前台应用不变、但系统可能改动了输入法的会话事件
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionEvent {
+    /// 从睡眠唤醒
+    Resumed,
+    /// 锁屏解除或切回当前用户会话
+    Unlocked,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SwitchOutcome {
+    Switched,
+    Unchanged,
+    NoRule,
+    /// 执行前已被更新的焦点取代
+    Superseded,
+    /// 自动切换已暂停
+    Paused,
+    /// 校验发现切换未生效，重试后成功
+    Verified {
+        retries: u32,
+    },
+    /// 重试后仍未切换到目标输入法
+    VerifyFailed {
+        retries: u32,
+    },
+    /// 强制窗口内应用改回了输入法，已重新切换
+    Enforced {
+        reapplied: u32,
+    },
+}
+
+/// 初始化监听器
+///
+/// * `app_handle`: Tauri App Handle，用于发送事件到前端
+pub fn setup_observer(app_handle: AppHandle) {
+    // 创建一个 Channel
+    let (tx, rx) = std::sync::mpsc::channel::<AppFocusedEvent>();
+    subscribe_input_source_changes(&app_handle);
+    let queue = app_handle.state::<AppState>().focus_queue.clone();
+
+    // 接收线程：只记录最新焦点，不等待切换完成
+    let receiver_handle = app_handle.clone();
+    let receiver_queue = queue.clone();
+    std::thread::spawn(move || {
+        let mut filter = FocusFilter::new(std::process::id());
+
+        while let Ok(event) = rx.recv() {
+            let rules = receiver_handle.state::<AppState>().rules.load();
+            if !filter.accept(&event, rules.ignored_apps()) {
+                continue;
+            }
+            receiver_queue.push(event, rules.settle_delay());
+        }
+        receiver_queue.close();
+    });
+
+    // 会话线程：唤醒或解锁后没有新的焦点事件，需要主动重新执行规则
+    let (session_tx, session_rx) = std::sync::mpsc::channel::<SessionEvent>();
+    let session_handle = app_handle.clone();
+    std::thread::spawn(move || {
+        while let Ok(event) = session_rx.recv() {
+            std::thread::sleep(SESSION_SETTLE_DELAY);
+            // 唤醒后往往紧跟着解锁，合并为一次
+            while session_rx.try_recv().is_ok() {}
+            // 睡眠期间暂停定时器不计时，先结束已过期的暂停
+            crate::switching_pause::expire_overdue_pause(&session_handle);
+            reapply_frontmost_rule(&session_handle, event);
+        }
+    });
+
+    // 学习线程：记住的输入法立即生效，加锁与写盘不占用切换线程
+    let learn_handle = app_handle.clone();
+    let save_handle = app_handle.clone();
+    let learner = learning::spawn(
+        LEARN_SAVE_DELAY,
+        move |input| learn_input(&learn_handle, input),
+        move || save_learned_inputs(&save_handle),
+    );
+
+    // 切换线程：只处理稳定下来的焦点
+    std::thread::spawn(move || {
+        let mut switcher = FocusSwitcher::default();
+
+        while let Some((generation, event)) = queue.wait_next() {
+            let context = ObserverContext {
+                app_handle: &app_handle,
+                learner: &learner,
+                rules: app_handle.state::<AppState>().rules.load(),
+            };
+            let Some(result) = switcher.handle(&context, generation, &event) else {
+                continue;
+            };
+            match &result {
+                Ok(SwitchOutcome::Superseded) => continue,
+                Ok(SwitchOutcome::VerifyFailed { retries }) => {
+                    eprintln!(
+                        "Input source for {} ({}) did not stick after {} retries",
+                        event.app_name, event.bundle_id, retries
+                    );
+                }
+                Ok(
+                    SwitchOutcome::Switched
+                    | SwitchOutcome::Unchanged
+                    | SwitchOutcome::NoRule
+                    | SwitchOutcome::Paused
+                    | SwitchOutcome::Verified { .. }
+                    | SwitchOutcome::Enforced { .. },
+                ) => {}
+                Err(e) => {
+                    eprintln!(
+                        "Failed to switch input source for {} ({}): {}",
+                        event.app_name, event.bundle_id, e
+                    );
+                }
+            }
+            app_handle
+                .state::<AppState>()
+                .tray_icons
+                .set_switch_failed(matches!(
+                    result,
+                    Err(_) | Ok(SwitchOutcome::VerifyFailed { .. })
+                ));
+            crate::tray_icon::refresh(&app_handle);
+        }
+    });
+
+    start_focus_source(tx);
+    start_session_source(session_tx);
+}
+
+/// 把输入法变化连同前台应用与来源（SmartIME 或用户）转发给前端
+fn subscribe_input_source_changes(app_handle: &AppHandle) {
+    let state = app_handle.state::<AppState>();
+    let backend = state.input_source.clone();
+    let tracker = state.input_changes.clone();
+    let emit_handle = app_handle.clone();
+    let result = backend.subscribe(Arc::new(move |source: &InputSource| {
+        let event = tracker.classify(source);
+        emit_handle
+            .state::<AppState>()
+            .tray_icons
+            .set_current_source(source);
+        crate::tray_icon::refresh(&emit_handle);
+        if let Err(e) = emit_handle.emit("input_source_changed", &event) {
+            eprintln!("Failed to emit input_source_changed event: {}", e);
+        }
+        if let (ChangeOrigin::User, Some(app)) = (event.origin, &event.app) {
+            record_user_change(&emit_handle, &app.bundle_id, &event.source.id);
+        }
+    }));
+
+    if let Err(e) = result {
+        eprintln!(
+            "Failed to subscribe to {} input source changes: {}",
+            backend.name(),
+            e
+        );
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn start_focus_source(tx: Sender<AppFocusedEvent>) {
+    macos::start(tx);
+}
+
+/// Wayland 不允许全局查询活动窗口，优先使用平铺合成器自带的 IPC
+#[cfg(target_os = "linux")]
+fn start_focus_source(tx: Sender<AppFocusedEvent>) {
+    let has_env = |key: &str| std::env::var_os(key).is_some_and(|value| !value.is_empty());
+    let (name, result) = if has_env("SWAYSOCK") {
+        ("sway", sway::start(tx))
+    } else if has_env("HYPRLAND_INSTANCE_SIGNATURE") {
+        ("Hyprland", hyprland::start(tx))
+    } else if crate::input_source::is_plain_x11_session() {
+        ("X11", x11::start(tx))
+    } else if is_gnome_or_kde() {
+        // 需要安装随应用分发的 GNOME Shell 扩展或 KWin 脚本
+        ("GNOME/KDE bridge", shell_bridge::start(tx))
+    } else {
+        eprintln!("No app focus source available for this Linux session");
+        return;
+    };
+
+    if let Err(e) = result {
+        eprintln!("Failed to start {} focus observer: {}", name, e);
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn is_gnome_or_kde() -> bool {
+    std::env::var("XDG_CURRENT_DESKTOP").is_ok_and(|desktops| {
+        desktops
+            .split(':')
+            .any(|desktop| matches!(desktop, "GNOME" | "KDE"))
+    })
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+fn start_focus_source(tx: Sender<AppFocusedEvent>) {
+    drop(tx);
+    eprintln!("No app focus source available on this platform");
+}
+
+#[cfg(target_os = "macos")]
+fn start_session_source(tx: Sender<SessionEvent>) {
+    macos::start_session(tx);
+}
+
+#[cfg(target_os = "linux")]
+fn start_session_source(tx: Sender<SessionEvent>) {
+    if let Err(e) = session::start(tx) {
+        eprintln!("Failed to start session observer: {}", e);
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+fn start_session_source(tx: Sender<SessionEvent>) {
+    drop(tx);
+}
+
+/// 查找应用对应的目标输入法，全局开关关闭时不切换
+pub fn resolve_target_input_source(rules: &RuleSnapshot, bundle_id: &str) -> Option<RuleTarget> {
+    rules.resolve(bundle_id).cloned()
+}
+
+/// 按规则切换输入法与子模式，已是目标状态时不重复切换
+pub fn apply_input_source(
+    backend: &dyn InputSourceBackend,
+    target: Option<&RuleTarget>,
+) -> Result<SwitchOutcome> {
+    let Some(target) = target else {
+        return Ok(SwitchOutcome::NoRule);
+    };
+
+    // 后端不支持子模式时只切换输入法
+    let target_mode = target.input_mode.filter(|_| backend.supports_input_mode());
+    let input_matches = backend
+        .current()
+        .ok()
+        .is_some_and(|current| current.id == target.input_id);
+    let mode_matches = match target_mode {
+        Some(mode) => backend.current_input_mode().ok().flatten() == Some(mode),
+        None => true,
+    };
+
+    if input_matches && mode_matches {
+        return Ok(SwitchOutcome::Unchanged);
+    }
+
+    if !input_matches {
+        backend.select(&target.input_id)?;
+    }
+    if let Some(mode) = target_mode {
+        // 切换输入法后子模式可能被重置，统一重新设置
+        backend.set_input_mode(mode)?;
+    }
+    Ok(SwitchOutcome::Switched)
+}
+
+/// 当前输入法与规则的子模式是否都已生效，供切换后的校验与强制窗口使用
+pub fn is_input_source_applied(
+    backend: &dyn InputSourceBackend,
+    target: &RuleTarget,
+) -> Result<bool> {
+    if backend.current()?.id != target.input_id {
+        return Ok(false);
+    }
+    match target.input_mode.filter(|_| backend.supports_input_mode()) {
+        Some(mode) => Ok(backend.current_input_mode()? == Some(mode)),
+        None => Ok(true),
+    }
+}
+
+/// 用户的手动切换达到阈值时提示前端修改规则
+fn record_user_change(app_handle: &AppHandle, bundle_id: &str, input_id: &str) {
+    let suggestion = match app_handle.state::<AppState>().rule_suggestions.lock() {
+        Ok(mut suggestions) => suggestions.record_user_change(bundle_id, input_id, Instant::now()),
+        Err(_) => None,
+    };
+    if let Some(suggestion) = suggestion {
+        if let Err(e) = app_handle.emit("rule_suggested", &suggestion) {
+            eprintln!("Failed to emit rule_suggested event: {}", e);
+        }
+    }
+}
+
+/// 在输入法后端要求的线程上执行切换的各个步骤
+struct BackendSwitchDriver<'a> {
+    app_handle: &'a AppHandle,
+    queue: Arc<FocusQueue>,
+    generation: u64,
+    target: RuleTarget,
+    dry_run: bool,
+}
+
+impl SwitchDriver for BackendSwitchDriver<'_> {
+    fn is_current(&self) -> bool {
+        self.queue.is_current(self.generation)
+    }
+
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+
+    fn apply(&self) -> Result<SwitchOutcome> {
+        let queue = self.queue.clone();
+        let generation = self.generation;
+        let target = self.target.clone();
+        let dry_run = self.dry_run;
+        run_backend_task(
+            self.app_handle,
+            "input source switch",
+            SWITCH_TIMEOUT,
+            move |backend| {
+                // 在执行线程上再确认一次，避免切换已被取代的焦点
+                if !queue.is_current(generation) {
+                    return Ok(SwitchOutcome::Superseded);
+                }
+                if dry_run {
+                    return apply_input_source(&DryRunBackend::new(backend), Some(&target));
+                }
+                apply_input_source(backend, Some(&target))
+            },
+        )
+    }
+
+    fn is_applied(&self) -> Result<bool> {
+        // 试运行不会改变输入法，校验与强制窗口视为已生效
+        if self.dry_run {
+            return Ok(true);
+        }
+        let target = self.target.clone();
+        run_backend_task(
+            self.app_handle,
+            "input source lookup",
+            SWITCH_TIMEOUT,
+            move |backend| is_input_source_applied(backend, &target),
+        )
+    }
+}
+
+/// 切换线程的运行环境，每个焦点取一次最新的规则快照
+struct ObserverContext<'a> {
+    app_handle: &'a AppHandle,
+    learner: &'a Sender<LearnedInput>,
+    rules: Arc<RuleSnapshot>,
+}
+
+impl SwitchContext for ObserverContext<'_> {
+    fn rules(&self) -> &RuleSnapshot {
+        &self.rules
+    }
+
+    fn is_paused(&self) -> bool {
+        self.app_handle
+            .state::<AppState>()
+            .switching_pause
+            .is_paused()
+    }
+
+    fn is_current(&self, generation: u64) -> bool {
+        self.app_handle
+            .state::<AppState>()
+            .focus_queue
+            .is_current(generation)
+    }
+
+    fn focus_changed(&self, event: &AppFocusedEvent) {
+        self.app_handle
+            .state::<AppState>()
+            .input_changes
+            .set_frontmost(&event.bundle_id, &event.app_name);
+        crate::tray_menu::refresh(self.app_handle);
+
+        // 发送事件到前端
+        if let Err(e) = self.app_handle.emit("app_focused", event) {
+            eprintln!("Failed to emit app_focused event: {}", e);
+        }
+    }
+
+    fn current_input(&self) -> Option<InputSource> {
+        run_backend_task(
+            self.app_handle,
+            "input source lookup",
+            SWITCH_TIMEOUT,
+            |backend| backend.current(),
+        )
+        .ok()
+    }
+
+    fn switch(&self, generation: u64, target: RuleTarget) -> Result<SwitchOutcome> {
+        run_timed_switch(
+            &target.timing.clone(),
+            &BackendSwitchDriver {
+                app_handle: self.app_handle,
+                queue: self.app_handle.state::<AppState>().focus_queue.clone(),
+                generation,
+                target,
+                dry_run: self.rules.dry_run(),
+            },
+        )
+    }
+
+    fn learn(&self, learned: LearnedInput) {
+        let bundle_id = learned.bundle_id.clone();
+        if self.learner.send(learned).is_err() {
+            eprintln!(
+                "Failed to remember input source for {}: learner stopped",
+                bundle_id
+            );
+        }
+    }
+
+    fn record_auto_switch(&self, event: &AppFocusedEvent, input_id: &str) {
+        let state = self.app_handle.state::<AppState>();
+        if let Ok(mut suggestions) = state.rule_suggestions.lock() {
+            suggestions.record_auto_switch(
+                &event.bundle_id,
+                &event.app_name,
+                input_id,
+                Instant::now(),
+            );
+        };
+    }
+
+    fn record_cancelled(&self) {
+        self.app_handle
+            .state::<AppState>()
+            .focus_queue
+            .record_cancelled();
+    }
+}
+
+fn learn_input(app_handle: &AppHandle, input: &LearnedInput) -> bool {
+    match app_handle.state::<AppState>().config.lock() {
+        Ok(mut manager) => manager.learn_input(&input.bundle_id, &input.app_name, &input.input_id),
+        Err(e) => {
+            eprintln!(
+                "Failed to remember input source for {}: {}",
+                input.bundle_id, e
+            );
+            false
+        }
+    }
+}
+
+fn save_learned_inputs(app_handle: &AppHandle) {
+    let result = app_handle
+        .state::<AppState>()
+        .config
+        .lock()
+        .map_err(|e| AppError::Lock(e.to_string()))
+        .and_then(|manager| manager.save());
+    if let Err(e) = result {
+        eprintln!("Failed to save learned input sources: {}", e);
+    }
+}
+
+/// 按规则重新切换当前前台应用的输入法，不学习也不记录建议
+fn reapply_frontmost_rule(app_handle: &AppHandle, event: SessionEvent) {
+    let failed = match reapply_rule(app_handle) {
+        Ok(None | Some(SwitchOutcome::Superseded | SwitchOutcome::Paused)) => return,
+        Ok(Some(SwitchOutcome::VerifyFailed { retries })) => {
+            eprintln!(
+                "Input source did not stick after {:?} and {} retries",
+                event, retries
+            );
+            true
+        }
+        Ok(Some(_)) => false,
+        Err(e) => {
+            eprintln!("Failed to re-apply input source after {:?}: {}", event, e);
+            true
+        }
+    };
+    app_handle
+        .state::<AppState>()
+        .tray_icons
+        .set_switch_failed(failed);
+    crate::tray_icon::refresh(app_handle);
+}
+
+fn reapply_rule(app_handle: &AppHandle) -> Result<Option<SwitchOutcome>> {
+    let state = app_handle.state::<AppState>();
+    if state.switching_pause.is_paused() {
+        return Ok(Some(SwitchOutcome::Paused));
+    }
+    let Some(app) = state.input_changes.frontmost() else {
+        return Ok(None);
+    };
+    // 还有焦点等待派发时由切换线程处理
+    let Some(generation) = state.focus_queue.settled_generation() else {
+        return Ok(None);
+    };
+    let rules = state.rules.load();
+    let Some(target) = resolve_target_input_source(&rules, &app.bundle_id) else {
+        return Ok(Some(SwitchOutcome::NoRule));
+    };
+
+    run_timed_switch(
+        &target.timing.clone(),
+        &BackendSwitchDriver {
+            app_handle,
+            queue: state.focus_queue.clone(),
+            generation,
+            target,
+            dry_run: rules.dry_run(),
+        },
+    )
+    .map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{AppConfig, AppRule, ConfigManager, RuleTiming};
+    use crate::input_source::{InputMode, MemoryBackend};
+    use crate::test_support::TempDir;
+
+    const ABC: &str = "com.apple.keylayout.ABC";
+    const PINYIN: &str = "com.apple.inputmethod.SCIM.ITABC";
+
+    fn backend() -> MemoryBackend {
+        MemoryBackend::new(
+            [(ABC, "ABC"), (PINYIN, "简体拼音")]
+                .into_iter()
+                .map(|(id, name)| InputSource {
+                    id: id.to_string(),
+                    name: name.to_string(),
+                    category: "TISCategoryKeyboardInputSource".to_string(),
+                })
+                .collect(),
+        )
+    }
+
+    fn manager(dir: &TempDir, global_switch: bool) -> ConfigManager {
+        ConfigManager::with_config(
+            AppConfig {
+                global_switch,
+                rules: vec![
+                    AppRule {
+                        bundle_id: "com.tencent.xinWeChat".to_string(),
+                        app_name: "WeChat".to_string(),
+                        preferred_input: PINYIN.to_string(),
+                        is_ai_generated: true,
+                        input_mode: None,
+                        remember_last_input: false,
+                        is_learned: false,
+                        timing: RuleTiming::default(),
+                    },
+                    AppRule {
+                        bundle_id: "com.microsoft.VSCode".to_string(),
+                        app_name: "Visual Studio Code".to_string(),
+                        preferred_input: PINYIN.to_string(),
+                        is_ai_generated: false,
+                        input_mode: Some(InputMode::Ascii),
+                        remember_last_input: false,
+                        is_learned: false,
+                        timing: RuleTiming::default(),
+                    },
+                ],
+                ..AppConfig::default()
+            },
+            dir.join("config.json"),
+        )
+    }
+
+    #[test]
+    fn test_resolve_target_input_source_respects_global_switch() {
+        let dir = TempDir::new("observer-test");
+        assert_eq!(
+            resolve_target_input_source(
+                &manager(&dir, true).snapshots().load(),
+                "com.tencent.xinWeChat"
+            ),
+            Some(RuleTarget {
+                input_id: PINYIN.to_string(),
+                input_mode: None,
+                timing: RuleTiming::default(),
+            })
+        );
+        assert_eq!(
+            resolve_target_input_source(
+                &manager(&dir, true).snapshots().load(),
+                "com.apple.Terminal"
+            ),
+            None
+        );
+        assert_eq!(
+            resolve_target_input_source(
+                &manager(&dir, false).snapshots().load(),
+                "com.tencent.xinWeChat"
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_apply_input_source_outcomes() {
+        let dir = TempDir::new("observer-test");
+        let backend = backend();
+        let target = resolve_target_input_source(
+            &manager(&dir, true).snapshots().load(),
+            "com.tencent.xinWeChat",
+        );
+
+        assert_eq!(
+            apply_input_source(&backend, None).unwrap(),
+            SwitchOutcome::NoRule
+        );
+        assert_eq!(
+            apply_input_source(&backend, target.as_ref()).unwrap(),
+            SwitchOutcome::Switched
+        );
+        assert_eq!(
+            apply_input_source(&backend, target.as_ref()).unwrap(),
+            SwitchOutcome::Unchanged
+        );
+        assert_eq!(backend.current().unwrap().id, PINYIN);
+        assert_eq!(backend.selections(), vec![PINYIN.to_string()]);
+    }
+
+    #[test]
+    fn test_apply_input_source_surfaces_backend_errors() {
+        let backend = backend();
+        backend.fail_selection_of(PINYIN);
+        let target = |input_id: &str| RuleTarget {
+            input_id: input_id.to_string(),
+            input_mode: None,
+            timing: RuleTiming::default(),
+        };
+        assert!(matches!(
+            apply_input_source(&backend, Some(&target(PINYIN))),
+            Err(AppError::InputSource(_))
+        ));
+        assert!(apply_input_source(&backend, Some(&target("com.example.missing"))).is_err());
+        assert_eq!(backend.current().unwrap().id, ABC);
+    }
+
+    #[test]
+    fn test_apply_input_source_sets_input_mode() {
+        let dir = TempDir::new("observer-test");
+        let backend = backend();
+        let target = resolve_target_input_source(
+            &manager(&dir, true).snapshots().load(),
+            "com.microsoft.VSCode",
+        );
+
+        assert_eq!(
+            apply_input_source(&backend, target.as_ref()).unwrap(),
+            SwitchOutcome::Switched
+        );
+        assert_eq!(backend.current().unwrap().id, PINYIN);
+        assert_eq!(
+            backend.current_input_mode().unwrap(),
+            Some(InputMode::Ascii)
+        );
+
+        // 输入法不变、仅子模式被用户改动时也要切回
+        backend.set_input_mode(InputMode::Native).unwrap();
+        let target_ref = target.as_ref().unwrap();
+        assert!(!is_input_source_applied(&backend, target_ref).unwrap());
+        assert_eq!(
+            apply_input_source(&backend, target.as_ref()).unwrap(),
+            SwitchOutcome::Switched
+        );
+        assert_eq!(
+            apply_input_source(&backend, target.as_ref()).unwrap(),
+            SwitchOutcome::Unchanged
+        );
+        assert!(is_input_source_applied(&backend, target_ref).unwrap());
+        assert_eq!(backend.selections(), vec![PINYIN.to_string()]);
+    }
+
+    #[test]
+    fn test_coalesced_focus_changes_switch_only_settled_app() {
+        let dir = TempDir::new("observer-test");
+        let backend = backend();
+        let manager = manager(&dir, true);
+        let mut coalescer = coalescer::FocusCoalescer::new(Duration::from_millis(100));
+        let start = std::time::Instant::now();
+        let focus = |bundle_id: &str| AppFocusedEvent {
+            bundle_id: bundle_id.to_string(),
+            app_name: bundle_id.to_string(),
+            pid: None,
+            window_title: None,
+        };
+
+        // Cmd-Tab 快速经过微信，最终停在终端（无规则）
+        coalescer.push(focus("com.microsoft.VSCode"), start);
+        coalescer.push(
+            focus("com.tencent.xinWeChat"),
+            start + Duration::from_millis(40),
+        );
+        coalescer.push(
+            focus("com.apple.Terminal"),
+            start + Duration::from_millis(80),
+        );
+
+        let mut outcomes = Vec::new();
+        for elapsed in (0..=300).step_by(20) {
+            if let Some((_, event)) = coalescer.poll(start + Duration::from_millis(elapsed)) {
+                let target =
+                    resolve_target_input_source(&manager.snapshots().load(), &event.bundle_id);
+                outcomes.push(apply_input_source(&backend, target.as_ref()).unwrap());
+            }
+        }
+
+        assert_eq!(outcomes, vec![SwitchOutcome::NoRule]);
+        assert!(backend.selections().is_empty());
+        assert_eq!(backend.current().unwrap().id, ABC);
+        assert_eq!(coalescer.metrics().dropped, 2);
+    }
+
+    #[test]
+    fn test_remembered_input_is_restored_on_return() {
+        let dir = TempDir::new("observer-test");
+        let backend = backend();
+        let mut manager = manager(&dir, true);
+        let mut config = manager.get_config();
+        config.switching.remember_last_input = true;
+        manager.set_config(config).unwrap();
+
+        let wechat =
+            resolve_target_input_source(&manager.snapshots().load(), "com.tencent.xinWeChat");
+        apply_input_source(&backend, wechat.as_ref()).unwrap();
+        // 用户在微信里手动切回 ABC，然后切到终端
+        backend.simulate_user_switch(ABC).unwrap();
+        assert!(manager.should_remember("com.tencent.xinWeChat"));
+        let last_input = backend.current().unwrap();
+        assert!(manager.learn_input("com.tencent.xinWeChat", "WeChat", &last_input.id));
+
+        let wechat =
+            resolve_target_input_source(&manager.snapshots().load(), "com.tencent.xinWeChat");
+        assert_eq!(wechat.as_ref().map(|t| t.input_id.as_str()), Some(ABC));
+        assert_eq!(
+            apply_input_source(&backend, wechat.as_ref()).unwrap(),
+            SwitchOutcome::Unchanged
+        );
+    }
+}
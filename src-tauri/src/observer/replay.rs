@@ -0,0 +1,566 @@
+use super::coalescer::FocusCoalescer;
+use super::filter::FocusFilter;
+use super::learning::LearnedInput;
+use super::switcher::{FocusSwitcher, SwitchContext};
+use super::timing::{run_timed_switch, SwitchDriver};
+use super::{
+    apply_input_source, is_input_source_applied, resolve_target_input_source, AppFocusedEvent,
+    CoalescerMetrics, SwitchOutcome,
+};
+use crate::config::{AppConfig, ConfigManager, RuleTarget};
+use crate::error::{AppError, Result};
+use crate::input_source::{InputMode, InputSource, InputSourceBackend, InputSourceListener};
+use crate::rule_snapshot::RuleSnapshot;
+use serde::{Deserialize, Serialize};
+use std::cell::{Cell, RefCell};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// 录制的一次焦点变化，`at_ms` 为相对录制开始的毫秒数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedFocus {
+    pub at_ms: u64,
+    pub bundle_id: String,
+    #[serde(default)]
+    pub app_name: Option<String>,
+    #[serde(default)]
+    pub pid: Option<u32>,
+    #[serde(default)]
+    pub window_title: Option<String>,
+}
+
+/// 回放输入：焦点序列与开始时的输入法
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FocusRecording {
+    #[serde(default)]
+    pub initial_input: Option<String>,
+    pub events: Vec<RecordedFocus>,
+}
+
+/// 切换引擎对一次已稳定焦点的处理结果
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct SimulatedDecision {
+    /// 派发给切换引擎的时间
+    pub at_ms: u64,
+    pub bundle_id: String,
+    pub app_name: String,
+    pub target_input: Option<String>,
+    pub target_mode: Option<InputMode>,
+    /// 切换出错时为 `None`，原因见 `error`
+    pub outcome: Option<SwitchOutcome>,
+    pub error: Option<String>,
+    /// 处理后的模拟输入法
+    pub input_after: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct ReplayReport {
+    pub decisions: Vec<SimulatedDecision>,
+    pub metrics: CoalescerMetrics,
+}
+
+/// 用真实的过滤、合并、规则解析与切换时机回放焦点序列，不接触系统输入法
+///
+/// 时间是虚拟的，回放立即完成。
+pub fn replay(rules: &RuleSnapshot, recording: &FocusRecording) -> ReplayReport {
+    let mut events = recording.events.clone();
+    events.sort_by_key(|event| event.at_ms);
+    let replay = Replay {
+        rules,
+        events,
+        next: Cell::new(0),
+        start: Instant::now(),
+        now_ms: Cell::new(0),
+        // 录制的焦点不会来自本进程
+        filter: RefCell::new(FocusFilter::new(0)),
+        coalescer: RefCell::new(FocusCoalescer::new(rules.settle_delay())),
+        input: SimulatedInput::new(recording.initial_input.clone()),
+    };
+    replay.run()
+}
+
+struct Replay<'a> {
+    rules: &'a RuleSnapshot,
+    events: Vec<RecordedFocus>,
+    next: Cell<usize>,
+    start: Instant,
+    now_ms: Cell<u64>,
+    filter: RefCell<FocusFilter>,
+    coalescer: RefCell<FocusCoalescer>,
+    input: SimulatedInput,
+}
+
+impl Replay<'_> {
+    fn instant(&self, ms: u64) -> Instant {
+        self.start + Duration::from_millis(ms)
+    }
+
+    fn millis(&self, instant: Instant) -> u64 {
+        instant.saturating_duration_since(self.start).as_millis() as u64
+    }
+
+    /// 推进虚拟时间，期间到达的焦点按真实接收线程的方式入队
+    fn advance_to(&self, until_ms: u64) {
+        while let Some(recorded) = self
+            .events
+            .get(self.next.get())
+            .filter(|recorded| recorded.at_ms <= until_ms)
+        {
+            self.next.set(self.next.get() + 1);
+            let event = AppFocusedEvent {
+                bundle_id: recorded.bundle_id.clone(),
+                app_name: recorded
+                    .app_name
+                    .clone()
+                    .unwrap_or_else(|| recorded.bundle_id.clone()),
+                pid: recorded.pid,
+                window_title: recorded.window_title.clone(),
+            };
+            if self
+                .filter
+                .borrow_mut()
+                .accept(&event, self.rules.ignored_apps())
+            {
+                self.coalescer
+                    .borrow_mut()
+                    .push(event, self.instant(recorded.at_ms));
+            }
+        }
+        self.now_ms.set(self.now_ms.get().max(until_ms));
+    }
+
+    fn run(self) -> ReplayReport {
+        let mut decisions = Vec::new();
+        let mut switcher = FocusSwitcher::default();
+
+        loop {
+            let next_event_ms = self.events.get(self.next.get()).map(|event| event.at_ms);
+            let due_ms = self
+                .coalescer
+                .borrow()
+                .deadline()
+                .map(|due| self.millis(due).max(self.now_ms.get()));
+            match (due_ms, next_event_ms) {
+                (Some(due_ms), Some(event_ms)) if event_ms < due_ms => self.advance_to(event_ms),
+                (Some(due_ms), _) => self.advance_to(due_ms),
+                (None, Some(event_ms)) => {
+                    self.advance_to(event_ms);
+                    continue;
+                }
+                (None, None) => break,
+            }
+
+            let polled = self
+                .coalescer
+                .borrow_mut()
+                .poll(self.instant(self.now_ms.get()));
+            let Some((generation, event)) = polled else {
+                continue;
+            };
+
+            let at_ms = self.now_ms.get();
+            let target = resolve_target_input_source(self.rules, &event.bundle_id);
+            let Some(result) = switcher.handle(&self, generation, &event) else {
+                continue;
+            };
+
+            decisions.push(SimulatedDecision {
+                at_ms,
+                bundle_id: event.bundle_id,
+                app_name: event.app_name,
+                target_input: target.as_ref().map(|target| target.input_id.clone()),
+                target_mode: target.and_then(|target| target.input_mode),
+                error: result.as_ref().err().map(|e| e.to_string()),
+                outcome: result.ok(),
+                input_after: self.input.current().ok().map(|source| source.id),
+            });
+        }
+
+        ReplayReport {
+            decisions,
+            metrics: self.coalescer.borrow().metrics(),
+        }
+    }
+}
+
+impl SwitchContext for Replay<'_> {
+    fn rules(&self) -> &RuleSnapshot {
+        self.rules
+    }
+
+    fn is_paused(&self) -> bool {
+        false
+    }
+
+    fn is_current(&self, generation: u64) -> bool {
+        self.coalescer.borrow().is_current(generation)
+    }
+
+    fn focus_changed(&self, _event: &AppFocusedEvent) {}
+
+    fn current_input(&self) -> Option<InputSource> {
+        self.input.current().ok()
+    }
+
+    fn switch(&self, generation: u64, target: RuleTarget) -> Result<SwitchOutcome> {
+        run_timed_switch(
+            &target.timing.clone(),
+            &ReplayDriver {
+                replay: self,
+                generation,
+                target,
+            },
+        )
+    }
+
+    // 回放使用固定的规则快照，学习与建议只在真实切换中生效
+    fn learn(&self, _learned: LearnedInput) {}
+
+    fn record_auto_switch(&self, _event: &AppFocusedEvent, _input_id: &str) {}
+
+    fn record_cancelled(&self) {
+        self.coalescer.borrow_mut().record_cancelled();
+    }
+}
+
+struct ReplayDriver<'a, 'r> {
+    replay: &'a Replay<'r>,
+    generation: u64,
+    target: RuleTarget,
+}
+
+impl SwitchDriver for ReplayDriver<'_, '_> {
+    fn is_current(&self) -> bool {
+        self.replay.coalescer.borrow().is_current(self.generation)
+    }
+
+    fn sleep(&self, duration: Duration) {
+        let until_ms = self
+            .replay
+            .now_ms
+            .get()
+            .saturating_add(duration.as_millis() as u64);
+        self.replay.advance_to(until_ms);
+    }
+
+    fn apply(&self) -> Result<SwitchOutcome> {
+        if !self.is_current() {
+            return Ok(SwitchOutcome::Superseded);
+        }
+        apply_input_source(&self.replay.input, Some(&self.target))
+    }
+
+    fn is_applied(&self) -> Result<bool> {
+        is_input_source_applied(&self.replay.input, &self.target)
+    }
+}
+
+/// 接受任意输入法 ID 的模拟后端
+struct SimulatedInput {
+    state: Mutex<(Option<String>, Option<InputMode>)>,
+}
+
+impl SimulatedInput {
+    fn new(initial_input: Option<String>) -> Self {
+        Self {
+            state: Mutex::new((initial_input, None)),
+        }
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, (Option<String>, Option<InputMode>)>> {
+        self.state.lock().map_err(|e| AppError::Lock(e.to_string()))
+    }
+}
+
+impl InputSourceBackend for SimulatedInput {
+    fn name(&self) -> &'static str {
+        "replay"
+    }
+
+    fn list(&self) -> Result<Vec<InputSource>> {
+        Ok(self.current().into_iter().collect())
+    }
+
+    fn current(&self) -> Result<InputSource> {
+        let id = self
+            .lock()?
+            .0
+            .clone()
+            .ok_or_else(|| AppError::InputSource("No simulated input source".to_string()))?;
+        Ok(InputSource {
+            name: id.clone(),
+            id,
+            category: "TISCategoryKeyboardInputSource".to_string(),
+        })
+    }
+
+    fn select(&self, source_id: &str) -> Result<()> {
+        self.lock()?.0 = Some(source_id.to_string());
+        Ok(())
+    }
+
+    fn subscribe(&self, _listener: InputSourceListener) -> Result<()> {
+        Ok(())
+    }
+
+    fn supports_input_mode(&self) -> bool {
+        true
+    }
+
+    fn current_input_mode(&self) -> Result<Option<InputMode>> {
+        Ok(self.lock()?.1)
+    }
+
+    fn set_input_mode(&self, mode: InputMode) -> Result<()> {
+        self.lock()?.1 = Some(mode);
+        Ok(())
+    }
+}
+
+/// 命令行回放：`smartime --replay <recording.json> [--config <config.json>]`
+///
+/// 不启动界面，把结果以 JSON 打印到标准输出，便于在无桌面的 Linux 上复现问题。
+/// 参数中没有 `--replay` 时返回 `None`。
+pub fn run_from_args(args: &[String]) -> Option<i32> {
+    let recording_path = flag_value(args, "--replay")?;
+    let config_path = flag_value(args, "--config")
+        .map(PathBuf::from)
+        .unwrap_or_else(crate::config::default_config_path);
+
+    match replay_files(&PathBuf::from(recording_path), &config_path) {
+        Ok(report) => match serde_json::to_string_pretty(&report) {
+            Ok(json) => {
+                println!("{}", json);
+                Some(0)
+            }
+            Err(e) => {
+                eprintln!("Failed to serialize replay report: {}", e);
+                Some(1)
+            }
+        },
+        Err(e) => {
+            eprintln!("Replay failed: {}", e);
+            Some(1)
+        }
+    }
+}
+
+fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|index| args.get(index + 1))
+        .map(String::as_str)
+}
+
+fn replay_files(recording_path: &PathBuf, config_path: &PathBuf) -> Result<ReplayReport> {
+    let recording: FocusRecording =
+        serde_json::from_str(&std::fs::read_to_string(recording_path)?)?;
+    let config: AppConfig = ConfigManager::load_from_file(config_path)?;
+    Ok(replay(&RuleSnapshot::compile(&config), &recording))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{AppRule, RuleTiming, SwitchingSettings};
+
+    const ABC: &str = "com.apple.keylayout.ABC";
+    const PINYIN: &str = "com.apple.inputmethod.SCIM.ITABC";
+
+    fn rule(bundle_id: &str, input: &str, timing: RuleTiming) -> AppRule {
+        AppRule {
+            bundle_id: bundle_id.to_string(),
+            app_name: bundle_id.to_string(),
+            preferred_input: input.to_string(),
+            is_ai_generated: false,
+            input_mode: None,
+            remember_last_input: false,
+            is_learned: false,
+            timing,
+        }
+    }
+
+    fn rules() -> RuleSnapshot {
+        RuleSnapshot::compile(&AppConfig {
+            rules: vec![
+                rule("com.tencent.xinWeChat", PINYIN, RuleTiming::default()),
+                rule("com.apple.Terminal", ABC, RuleTiming::default()),
+                rule(
+                    "com.tinyspeck.slackmacgap",
+                    PINYIN,
+                    RuleTiming {
+                        delay_ms: 300,
+                        ..RuleTiming::default()
+                    },
+                ),
+                rule(
+                    "com.microsoft.VSCode",
+                    PINYIN,
+                    RuleTiming {
+                        enforce_ms: 500,
+                        ..RuleTiming::default()
+                    },
+                ),
+            ],
+            switching: SwitchingSettings {
+                settle_delay_ms: 100,
+                ..SwitchingSettings::default()
+            },
+            ..AppConfig::default()
+        })
+    }
+
+    fn focus(at_ms: u64, bundle_id: &str) -> RecordedFocus {
+        RecordedFocus {
+            at_ms,
+            bundle_id: bundle_id.to_string(),
+            app_name: None,
+            pid: None,
+            window_title: None,
+        }
+    }
+
+    fn summary(report: &ReplayReport) -> Vec<(u64, &str, Option<SwitchOutcome>, Option<&str>)> {
+        report
+            .decisions
+            .iter()
+            .map(|decision| {
+                (
+                    decision.at_ms,
+                    decision.bundle_id.as_str(),
+                    decision.outcome.clone(),
+                    decision.input_after.as_deref(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_replay_coalesces_and_filters_focus() {
+        let report = replay(
+            &rules(),
+            &FocusRecording {
+                initial_input: Some(ABC.to_string()),
+                events: vec![
+                    // Cmd-Tab 快速经过终端，停在微信
+                    focus(0, "com.apple.Terminal"),
+                    focus(40, "com.tencent.xinWeChat"),
+                    // Spotlight 浮层不打断微信
+                    focus(500, "com.apple.Spotlight"),
+                    focus(700, "com.tencent.xinWeChat"),
+                    focus(1_000, "com.apple.Terminal"),
+                    focus(1_500, "com.apple.Finder"),
+                ],
+            },
+        );
+
+        assert_eq!(
+            summary(&report),
+            vec![
+                (
+                    140,
+                    "com.tencent.xinWeChat",
+                    Some(SwitchOutcome::Switched),
+                    Some(PINYIN)
+                ),
+                (
+                    1_100,
+                    "com.apple.Terminal",
+                    Some(SwitchOutcome::Switched),
+                    Some(ABC)
+                ),
+                (
+                    1_600,
+                    "com.apple.Finder",
+                    Some(SwitchOutcome::NoRule),
+                    Some(ABC)
+                ),
+            ]
+        );
+        assert_eq!(report.metrics.received, 4);
+        assert_eq!(report.metrics.dropped, 1);
+    }
+
+    #[test]
+    fn test_replay_rule_delay_is_superseded() {
+        let report = replay(
+            &rules(),
+            &FocusRecording {
+                initial_input: Some(ABC.to_string()),
+                events: vec![
+                    focus(0, "com.tinyspeck.slackmacgap"),
+                    // 延迟期间切到终端，Slack 的切换被取消
+                    focus(250, "com.apple.Terminal"),
+                    focus(1_000, "com.tinyspeck.slackmacgap"),
+                ],
+            },
+        );
+
+        assert_eq!(
+            summary(&report),
+            vec![
+                (
+                    100,
+                    "com.tinyspeck.slackmacgap",
+                    Some(SwitchOutcome::Superseded),
+                    Some(ABC)
+                ),
+                (
+                    400,
+                    "com.apple.Terminal",
+                    Some(SwitchOutcome::Unchanged),
+                    Some(ABC)
+                ),
+                (
+                    1_100,
+                    "com.tinyspeck.slackmacgap",
+                    Some(SwitchOutcome::Switched),
+                    Some(PINYIN)
+                ),
+            ]
+        );
+        assert_eq!(report.metrics.cancelled, 1);
+    }
+
+    #[test]
+    fn test_replay_focus_leaving_during_enforce_keeps_switch() {
+        let report = replay(
+            &rules(),
+            &FocusRecording {
+                initial_input: Some(ABC.to_string()),
+                events: vec![
+                    focus(0, "com.microsoft.VSCode"),
+                    // 强制窗口内切走，已完成的切换仍然有效
+                    focus(150, "com.apple.Terminal"),
+                    focus(600, "com.microsoft.VSCode"),
+                ],
+            },
+        );
+
+        assert_eq!(
+            summary(&report),
+            vec![
+                (
+                    100,
+                    "com.microsoft.VSCode",
+                    Some(SwitchOutcome::Switched),
+                    Some(PINYIN)
+                ),
+                (
+                    250,
+                    "com.apple.Terminal",
+                    Some(SwitchOutcome::Switched),
+                    Some(ABC)
+                ),
+                (
+                    700,
+                    "com.microsoft.VSCode",
+                    Some(SwitchOutcome::Switched),
+                    Some(PINYIN)
+                ),
+            ]
+        );
+        assert_eq!(report.metrics.cancelled, 0);
+    }
+}
@@ -0,0 +1,217 @@
+use super::SessionEvent;
+use crate::error::{AppError, Result};
+use std::collections::HashMap;
+use std::sync::mpsc::Sender;
+use zbus::blocking::{connection, Connection, MessageIterator};
+use zbus::message::{Message, Type as MessageType};
+use zbus::zvariant::{OwnedObjectPath, OwnedValue};
+use zbus::MatchRule;
+
+const LOGIND_SERVICE: &str = "org.freedesktop.login1";
+const MANAGER_PATH: &str = "/org/freedesktop/login1";
+const MANAGER_INTERFACE: &str = "org.freedesktop.login1.Manager";
+const SESSION_INTERFACE: &str = "org.freedesktop.login1.Session";
+const PROPERTIES_INTERFACE: &str = "org.freedesktop.DBus.Properties";
+
+/// 在系统总线上监听 logind 的唤醒与解锁信号
+pub fn start(tx: Sender<SessionEvent>) -> Result<()> {
+    let builder = connection::Builder::system().map_err(|e| logind_error("system bus", e))?;
+    start_on(builder, tx)
+}
+
+fn start_on(builder: connection::Builder<'_>, tx: Sender<SessionEvent>) -> Result<()> {
+    let connection = builder.build().map_err(|e| logind_error("connection", e))?;
+
+    // 由桌面 systemd scope 启动时不属于任何会话，"auto" 会解析为用户的图形会话；
+    // 找不到会话时仍可处理睡眠唤醒
+    let session_path = match current_session(&connection) {
+        Ok(path) => Some(path),
+        Err(e) => {
+            eprintln!("Failed to resolve logind session, unlock events are ignored: {e}");
+            None
+        }
+    };
+
+    let rule = MatchRule::builder()
+        .msg_type(MessageType::Signal)
+        .path_namespace(MANAGER_PATH)
+        .map_err(|e| logind_error("match rule", e))?
+        .build();
+    let signals = MessageIterator::for_match_rule(rule, &connection, None)
+        .map_err(|e| logind_error("signal subscription", e))?;
+
+    std::thread::spawn(move || forward_signals(signals, session_path, tx));
+    Ok(())
+}
+
+fn current_session(connection: &Connection) -> zbus::Result<OwnedObjectPath> {
+    connection
+        .call_method(
+            Some(LOGIND_SERVICE),
+            MANAGER_PATH,
+            Some(MANAGER_INTERFACE),
+            "GetSession",
+            &("auto",),
+        )?
+        .body()
+        .deserialize()
+}
+
+fn forward_signals(
+    signals: MessageIterator,
+    session_path: Option<OwnedObjectPath>,
+    tx: Sender<SessionEvent>,
+) {
+    let session_path = session_path.as_ref().map(|path| path.as_str());
+    for message in signals {
+        let Ok(message) = message else {
+            continue;
+        };
+        if let Some(event) = classify(&message, session_path) {
+            if tx.send(event).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// 只关心唤醒与本会话的解锁，其他会话和即将睡眠的信号被忽略
+fn classify(message: &Message, session_path: Option<&str>) -> Option<SessionEvent> {
+    let header = message.header();
+    let interface = header.interface()?.as_str();
+    let member = header.member()?.as_str();
+    let path = header.path()?.as_str();
+
+    match (interface, member) {
+        (MANAGER_INTERFACE, "PrepareForSleep") if path == MANAGER_PATH => {
+            // true 表示即将睡眠，false 表示已唤醒
+            let sleeping: bool = message.body().deserialize().ok()?;
+            (!sleeping).then_some(SessionEvent::Resumed)
+        }
+        (SESSION_INTERFACE, "Unlock") if Some(path) == session_path => Some(SessionEvent::Unlocked),
+        // 部分锁屏程序只更新 LockedHint，不经过 Unlock
+        (PROPERTIES_INTERFACE, "PropertiesChanged") if Some(path) == session_path => {
+            let (changed_interface, changed, _): (
+                String,
+                HashMap<String, OwnedValue>,
+                Vec<String>,
+            ) = message.body().deserialize().ok()?;
+            if changed_interface != SESSION_INTERFACE {
+                return None;
+            }
+            let locked: bool = changed.get("LockedHint")?.downcast_ref().ok()?;
+            (!locked).then_some(SessionEvent::Unlocked)
+        }
+        _ => None,
+    }
+}
+
+fn logind_error(context: &str, e: zbus::Error) -> AppError {
+    AppError::Observer(format!("logind {context} failed: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dbus_test_support::PrivateBus;
+    use std::sync::mpsc;
+    use std::time::Duration;
+    use zbus::zvariant::Value;
+
+    const SESSION_PATH: &str = "/org/freedesktop/login1/session/_32";
+
+    /// 私有总线上的 logind 替身，只实现 `GetSession`
+    struct FakeManager;
+
+    #[zbus::interface(name = "org.freedesktop.login1.Manager")]
+    impl FakeManager {
+        fn get_session(&self, session_id: String) -> zbus::fdo::Result<OwnedObjectPath> {
+            if session_id != "auto" {
+                return Err(zbus::fdo::Error::Failed("No such session".to_string()));
+            }
+            OwnedObjectPath::try_from(SESSION_PATH)
+                .map_err(|e| zbus::fdo::Error::Failed(e.to_string()))
+        }
+    }
+
+    #[test]
+    fn test_logind_resume_and_unlock_signals() {
+        let Some(bus) = PrivateBus::start() else {
+            return;
+        };
+        let logind = connection::Builder::address(bus.address())
+            .expect("bus address")
+            .name(LOGIND_SERVICE)
+            .unwrap()
+            .serve_at(MANAGER_PATH, FakeManager)
+            .unwrap()
+            .build()
+            .expect("fake logind connection");
+
+        let (tx, rx) = mpsc::channel();
+        start_on(
+            connection::Builder::address(bus.address()).expect("bus address"),
+            tx,
+        )
+        .unwrap();
+
+        logind
+            .emit_signal(
+                None::<&str>,
+                MANAGER_PATH,
+                MANAGER_INTERFACE,
+                "PrepareForSleep",
+                &(true,),
+            )
+            .unwrap();
+        logind
+            .emit_signal(
+                None::<&str>,
+                MANAGER_PATH,
+                MANAGER_INTERFACE,
+                "PrepareForSleep",
+                &(false,),
+            )
+            .unwrap();
+        assert_eq!(
+            rx.recv_timeout(Duration::from_secs(5)),
+            Ok(SessionEvent::Resumed)
+        );
+
+        // 其他会话的解锁不影响当前会话
+        logind
+            .emit_signal(
+                None::<&str>,
+                "/org/freedesktop/login1/session/_33",
+                SESSION_INTERFACE,
+                "Unlock",
+                &(),
+            )
+            .unwrap();
+        logind
+            .emit_signal(None::<&str>, SESSION_PATH, SESSION_INTERFACE, "Unlock", &())
+            .unwrap();
+        assert_eq!(
+            rx.recv_timeout(Duration::from_secs(5)),
+            Ok(SessionEvent::Unlocked)
+        );
+
+        for locked in [true, false] {
+            let changed = HashMap::from([("LockedHint", Value::from(locked))]);
+            logind
+                .emit_signal(
+                    None::<&str>,
+                    SESSION_PATH,
+                    PROPERTIES_INTERFACE,
+                    "PropertiesChanged",
+                    &(SESSION_INTERFACE, changed, Vec::<String>::new()),
+                )
+                .unwrap();
+        }
+        assert_eq!(
+            rx.recv_timeout(Duration::from_secs(5)),
+            Ok(SessionEvent::Unlocked)
+        );
+        assert!(rx.recv_timeout(Duration::from_millis(200)).is_err());
+    }
+}
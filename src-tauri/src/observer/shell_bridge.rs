@@ -0,0 +1,160 @@
+use super::AppFocusedEvent;
+use crate::error::{AppError, Result};
+use std::sync::mpsc::Sender;
+use zbus::blocking::{connection, Connection, MessageIterator};
+use zbus::message::Type as MessageType;
+use zbus::MatchRule;
+
+// 随应用分发的 GNOME Shell 扩展与 KWin 脚本使用的 D-Bus 接口
+// （见 resources/linux），两端必须保持一致
+const BRIDGE_SERVICE: &str = "com.smartime.Focus";
+const BRIDGE_PATH: &str = "/com/smartime/Focus";
+const BRIDGE_INTERFACE: &str = "com.smartime.Focus1";
+const FOCUS_CHANGED_SIGNAL: &str = "FocusChanged";
+
+/// KWin 脚本只能调用方法（`callDBus`），因此由 SmartIME 提供 `ReportFocus`
+struct FocusBridge {
+    tx: Sender<AppFocusedEvent>,
+}
+
+#[zbus::interface(name = "com.smartime.Focus1")]
+impl FocusBridge {
+    fn report_focus(&self, app_id: String, title: String) -> zbus::fdo::Result<()> {
+        if let Some(event) = to_event(&app_id, &title, 0) {
+            self.tx
+                .send(event)
+                .map_err(|_| zbus::fdo::Error::Failed("Focus observer stopped".to_string()))?;
+        }
+        Ok(())
+    }
+}
+
+/// 在会话总线上接收 GNOME Shell 扩展与 KWin 脚本上报的焦点变化
+pub fn start(tx: Sender<AppFocusedEvent>) -> Result<()> {
+    let builder = connection::Builder::session().map_err(|e| bridge_error("session bus", e))?;
+    start_on(builder, tx)
+}
+
+fn start_on(builder: connection::Builder<'_>, tx: Sender<AppFocusedEvent>) -> Result<()> {
+    let connection = builder
+        .name(BRIDGE_SERVICE)
+        .map_err(|e| bridge_error("name", e))?
+        .serve_at(BRIDGE_PATH, FocusBridge { tx: tx.clone() })
+        .map_err(|e| bridge_error("serve", e))?
+        .build()
+        .map_err(|e| bridge_error("connection", e))?;
+
+    // GNOME Shell 扩展在自己的连接上导出对象并发出 FocusChanged 信号
+    let rule = MatchRule::builder()
+        .msg_type(MessageType::Signal)
+        .interface(BRIDGE_INTERFACE)
+        .and_then(|rule| rule.path(BRIDGE_PATH))
+        .and_then(|rule| rule.member(FOCUS_CHANGED_SIGNAL))
+        .map_err(|e| bridge_error("match rule", e))?
+        .build();
+    let signals = MessageIterator::for_match_rule(rule, &connection, None)
+        .map_err(|e| bridge_error("FocusChanged subscription", e))?;
+
+    std::thread::spawn(move || forward_signals(connection, signals, tx));
+    Ok(())
+}
+
+/// 持有连接直到接收端关闭，保证 `ReportFocus` 一直可用
+fn forward_signals(_connection: Connection, signals: MessageIterator, tx: Sender<AppFocusedEvent>) {
+    for message in signals {
+        let Ok(message) = message else {
+            continue;
+        };
+        let Ok((app_id, title, pid)) = message.body().deserialize::<(String, String, u32)>() else {
+            continue;
+        };
+        if let Some(event) = to_event(&app_id, &title, pid) {
+            if tx.send(event).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// 扩展上报的 app ID（Wayland app_id 或 WM_CLASS）作为 bundle ID 的等价物
+fn to_event(app_id: &str, title: &str, pid: u32) -> Option<AppFocusedEvent> {
+    let app_id = app_id.trim();
+    if app_id.is_empty() {
+        return None;
+    }
+
+    Some(AppFocusedEvent {
+        bundle_id: app_id.to_string(),
+        app_name: app_id.to_string(),
+        pid: Some(pid).filter(|pid| *pid > 0),
+        window_title: Some(title.to_string()).filter(|title| !title.is_empty()),
+    })
+}
+
+fn bridge_error(context: &str, e: zbus::Error) -> AppError {
+    AppError::Observer(format!("Focus bridge {context} failed: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dbus_test_support::PrivateBus;
+    use std::sync::mpsc;
+    use std::time::Duration;
+    use zbus::blocking::Proxy;
+
+    fn client(bus: &PrivateBus) -> Connection {
+        connection::Builder::address(bus.address())
+            .expect("bus address")
+            .build()
+            .expect("client connection")
+    }
+
+    #[test]
+    fn test_to_event_skips_empty_app_id() {
+        let event = to_event("org.gnome.TextEditor", "", 0).unwrap();
+        assert_eq!(event.bundle_id, "org.gnome.TextEditor");
+        assert_eq!(event.pid, None);
+        assert_eq!(event.window_title, None);
+        assert!(to_event("  ", "Desktop", 42).is_none());
+    }
+
+    #[test]
+    fn test_shell_bridge_receives_signals_and_reports() {
+        let Some(bus) = PrivateBus::start() else {
+            return;
+        };
+        let (tx, rx) = mpsc::channel();
+        start_on(
+            connection::Builder::address(bus.address()).expect("bus address"),
+            tx,
+        )
+        .unwrap();
+
+        // 模拟 GNOME Shell 扩展：在自己的连接上发出信号
+        let extension = client(&bus);
+        extension
+            .emit_signal(
+                None::<&str>,
+                BRIDGE_PATH,
+                BRIDGE_INTERFACE,
+                FOCUS_CHANGED_SIGNAL,
+                &("org.gnome.Ptyxis", "~/code", 3100u32),
+            )
+            .unwrap();
+        let event = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(event.bundle_id, "org.gnome.Ptyxis");
+        assert_eq!(event.pid, Some(3100));
+        assert_eq!(event.window_title.as_deref(), Some("~/code"));
+
+        // 模拟 KWin 脚本：调用 SmartIME 提供的方法
+        let kwin = client(&bus);
+        Proxy::new(&kwin, BRIDGE_SERVICE, BRIDGE_PATH, BRIDGE_INTERFACE)
+            .unwrap()
+            .call::<_, _, ()>("ReportFocus", &("org.kde.dolphin", "Home — Dolphin"))
+            .unwrap();
+        let event = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(event.bundle_id, "org.kde.dolphin");
+        assert_eq!(event.window_title.as_deref(), Some("Home — Dolphin"));
+    }
+}
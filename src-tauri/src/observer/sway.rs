@@ -0,0 +1,257 @@
+use super::AppFocusedEvent;
+use crate::error::{AppError, Result};
+use serde::Deserialize;
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::sync::mpsc::Sender;
+
+// i3-ipc 协议：magic + u32 长度 + u32 类型（本机字节序）+ JSON 负载
+const IPC_MAGIC: &[u8; 6] = b"i3-ipc";
+const IPC_SUBSCRIBE: u32 = 2;
+const IPC_GET_TREE: u32 = 4;
+const IPC_EVENT_WINDOW: u32 = 0x8000_0003;
+
+#[derive(Debug, Deserialize)]
+struct SubscribeReply {
+    success: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct WindowEvent {
+    change: String,
+    container: Node,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Node {
+    #[serde(default)]
+    app_id: Option<String>,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    pid: Option<u32>,
+    #[serde(default)]
+    window_properties: Option<WindowProperties>,
+    #[serde(default)]
+    focused: bool,
+    #[serde(default)]
+    nodes: Vec<Node>,
+    #[serde(default)]
+    floating_nodes: Vec<Node>,
+}
+
+/// XWayland 窗口没有 app_id，只有 X11 的 class/instance
+#[derive(Debug, Default, Deserialize)]
+struct WindowProperties {
+    #[serde(default)]
+    class: Option<String>,
+    #[serde(default)]
+    instance: Option<String>,
+}
+
+/// 连接 `$SWAYSOCK` 并在后台线程中把焦点变化发送到 `tx`
+pub fn start(tx: Sender<AppFocusedEvent>) -> Result<()> {
+    let socket = std::env::var("SWAYSOCK")
+        .map_err(|_| AppError::Observer("SWAYSOCK is not set".to_string()))?;
+    let mut stream = connect(Path::new(&socket))?;
+    std::thread::spawn(move || run(&mut stream, tx));
+    Ok(())
+}
+
+/// 订阅 window 事件，订阅成功后才返回
+fn connect(socket: &Path) -> Result<UnixStream> {
+    let mut stream = UnixStream::connect(socket)
+        .map_err(|e| sway_error(&format!("connect to {}", socket.display()), e))?;
+    send_message(&mut stream, IPC_SUBSCRIBE, br#"["window"]"#)?;
+    let (_, payload) = read_message(&mut stream)?;
+    let reply: SubscribeReply =
+        serde_json::from_slice(&payload).map_err(|e| sway_error("subscribe reply", e))?;
+    if !reply.success {
+        return Err(AppError::Observer(
+            "sway rejected the window event subscription".to_string(),
+        ));
+    }
+    Ok(stream)
+}
+
+/// 先上报当前聚焦窗口，之后逐个处理 focus 事件；接收端关闭或连接断开时退出
+fn run(stream: &mut UnixStream, tx: Sender<AppFocusedEvent>) {
+    if let Err(e) = send_message(stream, IPC_GET_TREE, b"") {
+        eprintln!("Failed to query sway tree: {}", e);
+    }
+
+    loop {
+        let (message_type, payload) = match read_message(stream) {
+            Ok(message) => message,
+            Err(e) => {
+                eprintln!("sway focus observer stopped: {}", e);
+                return;
+            }
+        };
+
+        let event = match message_type {
+            IPC_GET_TREE => serde_json::from_slice::<Node>(&payload)
+                .ok()
+                .and_then(|tree| find_focused(&tree).and_then(node_to_event)),
+            IPC_EVENT_WINDOW => parse_window_event(&payload),
+            _ => None,
+        };
+        if let Some(event) = event {
+            if tx.send(event).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+fn send_message(stream: &mut UnixStream, message_type: u32, payload: &[u8]) -> Result<()> {
+    let mut message = Vec::with_capacity(IPC_MAGIC.len() + 8 + payload.len());
+    message.extend_from_slice(IPC_MAGIC);
+    message.extend_from_slice(&(payload.len() as u32).to_ne_bytes());
+    message.extend_from_slice(&message_type.to_ne_bytes());
+    message.extend_from_slice(payload);
+    stream
+        .write_all(&message)
+        .map_err(|e| sway_error("write", e))
+}
+
+fn read_message(stream: &mut impl Read) -> Result<(u32, Vec<u8>)> {
+    let mut header = [0u8; 14];
+    stream
+        .read_exact(&mut header)
+        .map_err(|e| sway_error("read", e))?;
+    if &header[..6] != IPC_MAGIC {
+        return Err(AppError::Observer("Invalid sway IPC header".to_string()));
+    }
+    let length = u32::from_ne_bytes([header[6], header[7], header[8], header[9]]);
+    let message_type = u32::from_ne_bytes([header[10], header[11], header[12], header[13]]);
+
+    let mut payload = vec![0u8; length as usize];
+    stream
+        .read_exact(&mut payload)
+        .map_err(|e| sway_error("read", e))?;
+    Ok((message_type, payload))
+}
+
+/// 只关心 focus 变化，标题变化等其他事件忽略
+fn parse_window_event(payload: &[u8]) -> Option<AppFocusedEvent> {
+    let event: WindowEvent = serde_json::from_slice(payload).ok()?;
+    if event.change != "focus" {
+        return None;
+    }
+    node_to_event(&event.container)
+}
+
+fn find_focused(node: &Node) -> Option<&Node> {
+    if node.focused {
+        return Some(node);
+    }
+    node.nodes
+        .iter()
+        .chain(&node.floating_nodes)
+        .find_map(find_focused)
+}
+
+/// app_id（原生 Wayland）或 class（XWayland）作为 bundle ID 的等价物
+fn node_to_event(node: &Node) -> Option<AppFocusedEvent> {
+    let properties = node.window_properties.as_ref();
+    let app_id = [
+        node.app_id.as_ref(),
+        properties.and_then(|p| p.class.as_ref()),
+        properties.and_then(|p| p.instance.as_ref()),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|id| id.trim())
+    .find(|id| !id.is_empty())?
+    .to_string();
+
+    Some(AppFocusedEvent {
+        bundle_id: app_id.clone(),
+        app_name: app_id,
+        pid: node.pid.filter(|pid| *pid > 0),
+        window_title: node.name.clone().filter(|name| !name.is_empty()),
+    })
+}
+
+fn sway_error(context: &str, e: impl std::fmt::Display) -> AppError {
+    AppError::Observer(format!("sway IPC {context} failed: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixListener;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    // 从 sway 1.9 抓取的事件（已精简）
+    const FOCUS_NATIVE: &str = r#"{"change":"focus","container":{"id":12,"type":"con","focused":true,"name":"Mozilla Firefox","app_id":"firefox","pid":2001,"window_properties":null}}"#;
+    const TITLE_CHANGE: &str = r#"{"change":"title","container":{"id":12,"type":"con","focused":true,"name":"New Tab - Mozilla Firefox","app_id":"firefox","pid":2001}}"#;
+    const FOCUS_XWAYLAND: &str = r#"{"change":"focus","container":{"id":15,"type":"con","focused":true,"name":"main.rs - SmartIME","app_id":null,"pid":2002,"window_properties":{"class":"Code","instance":"code","title":"main.rs - SmartIME"}}}"#;
+    const TREE: &str = r#"{"id":1,"type":"root","focused":false,"nodes":[{"id":2,"type":"output","focused":false,"nodes":[{"id":3,"type":"workspace","focused":false,"nodes":[],"floating_nodes":[{"id":9,"type":"floating_con","focused":true,"name":"Terminal","app_id":"foot","pid":2000}]}]}]}"#;
+
+    fn frame(message_type: u32, payload: &str) -> Vec<u8> {
+        let mut message = IPC_MAGIC.to_vec();
+        message.extend_from_slice(&(payload.len() as u32).to_ne_bytes());
+        message.extend_from_slice(&message_type.to_ne_bytes());
+        message.extend_from_slice(payload.as_bytes());
+        message
+    }
+
+    #[test]
+    fn test_parse_window_events() {
+        let native = parse_window_event(FOCUS_NATIVE.as_bytes()).unwrap();
+        assert_eq!(native.bundle_id, "firefox");
+        assert_eq!(native.pid, Some(2001));
+        assert_eq!(native.window_title.as_deref(), Some("Mozilla Firefox"));
+
+        let xwayland = parse_window_event(FOCUS_XWAYLAND.as_bytes()).unwrap();
+        assert_eq!(xwayland.bundle_id, "Code");
+
+        assert!(parse_window_event(TITLE_CHANGE.as_bytes()).is_none());
+        assert!(parse_window_event(b"not json").is_none());
+    }
+
+    #[test]
+    fn test_sway_focus_source_replays_fake_server() {
+        let dir = crate::test_support::TempDir::new("sway-test");
+        let socket = dir.join("sway-ipc.sock");
+        let listener = UnixListener::bind(&socket).unwrap();
+
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let (message_type, payload) = read_message(&mut stream).unwrap();
+            assert_eq!(message_type, IPC_SUBSCRIBE);
+            assert_eq!(payload, br#"["window"]"#);
+            stream
+                .write_all(&frame(IPC_SUBSCRIBE, r#"{"success":true}"#))
+                .unwrap();
+
+            let (message_type, _) = read_message(&mut stream).unwrap();
+            assert_eq!(message_type, IPC_GET_TREE);
+            for (message_type, payload) in [
+                (IPC_GET_TREE, TREE),
+                (IPC_EVENT_WINDOW, FOCUS_NATIVE),
+                (IPC_EVENT_WINDOW, TITLE_CHANGE),
+                (IPC_EVENT_WINDOW, FOCUS_XWAYLAND),
+            ] {
+                stream.write_all(&frame(message_type, payload)).unwrap();
+            }
+        });
+
+        let mut stream = connect(&socket).unwrap();
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || run(&mut stream, tx));
+
+        let received: Vec<String> = (0..3)
+            .map(|_| rx.recv_timeout(Duration::from_secs(5)).unwrap().bundle_id)
+            .collect();
+        assert_eq!(received, vec!["foot", "firefox", "Code"]);
+
+        server.join().unwrap();
+        // 服务端断开后观察线程退出，channel 随之关闭
+        assert!(rx.recv_timeout(Duration::from_secs(5)).is_err());
+    }
+}
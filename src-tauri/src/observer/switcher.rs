@@ -0,0 +1,117 @@
+use super::learning::LearnedInput;
+use super::{resolve_target_input_source, AppFocusedEvent, SwitchOutcome};
+use crate::config::RuleTarget;
+use crate::error::Result;
+use crate::input_source::InputSource;
+use crate::rule_snapshot::RuleSnapshot;
+
+/// 切换线程与回放各自提供的运行环境
+pub trait SwitchContext {
+    fn rules(&self) -> &RuleSnapshot;
+    fn is_paused(&self) -> bool;
+    fn is_current(&self, generation: u64) -> bool;
+    /// 焦点确实变化后通知前端与托盘
+    fn focus_changed(&self, event: &AppFocusedEvent);
+    /// 切换前的输入法，用于记住上一个应用离开时的选择
+    fn current_input(&self) -> Option<InputSource>;
+    /// 按规则的切换时机执行切换
+    fn switch(&self, generation: u64, target: RuleTarget) -> Result<SwitchOutcome>;
+    fn learn(&self, learned: LearnedInput);
+    fn record_auto_switch(&self, event: &AppFocusedEvent, input_id: &str);
+    fn record_cancelled(&self);
+}
+
+/// 处理已稳定的焦点：去重、执行规则，并记录上一个应用
+#[derive(Debug, Default)]
+pub struct FocusSwitcher {
+    previous: Option<AppFocusedEvent>,
+}
+
+impl FocusSwitcher {
+    /// 与上一个应用相同（快速切走又切回）时返回 `None`
+    pub fn handle(
+        &mut self,
+        context: &impl SwitchContext,
+        generation: u64,
+        event: &AppFocusedEvent,
+    ) -> Option<Result<SwitchOutcome>> {
+        if self
+            .previous
+            .as_ref()
+            .is_some_and(|previous| previous.bundle_id == event.bundle_id)
+        {
+            return None;
+        }
+
+        context.focus_changed(event);
+        let result = switch_for_bundle(context, event, self.previous.as_ref(), generation);
+        // 只有尚未切换时才会被取代，上一个应用保持不变
+        if let Ok(SwitchOutcome::Superseded) = result {
+            context.record_cancelled();
+        } else {
+            self.previous = Some(event.clone());
+        }
+        Some(result)
+    }
+}
+
+fn switch_for_bundle(
+    context: &impl SwitchContext,
+    event: &AppFocusedEvent,
+    previous: Option<&AppFocusedEvent>,
+    generation: u64,
+) -> Result<SwitchOutcome> {
+    // 暂停期间既不切换也不学习用户的选择
+    if context.is_paused() {
+        return Ok(SwitchOutcome::Paused);
+    }
+    let rules = context.rules();
+    let target = resolve_target_input_source(rules, &event.bundle_id);
+    // 试运行只观察规则，不学习用户的选择
+    let remember_previous = !rules.dry_run()
+        && previous.is_some_and(|previous| rules.should_remember(&previous.bundle_id));
+
+    // 排队期间用户已切到别的应用时放弃本次切换
+    if !context.is_current(generation) {
+        return Ok(SwitchOutcome::Superseded);
+    }
+    // 切换前的输入法就是上一个应用离开时使用的输入法
+    let last_input = if remember_previous {
+        context.current_input()
+    } else {
+        None
+    };
+
+    let target_input = target.as_ref().map(|target| target.input_id.clone());
+    let outcome = match target {
+        Some(target) => context.switch(generation, target),
+        None => Ok(SwitchOutcome::NoRule),
+    };
+
+    if let (Some(previous), Some(last_input)) = (previous, last_input) {
+        context.learn(LearnedInput {
+            bundle_id: previous.bundle_id.clone(),
+            app_name: previous.app_name.clone(),
+            input_id: last_input.id,
+        });
+    }
+
+    // 记录自动切换，随后的手动切换视为对规则的纠正；试运行没有真正切换，不记录
+    if rules.dry_run() {
+        return outcome;
+    }
+    if let (
+        Ok(
+            SwitchOutcome::Switched
+            | SwitchOutcome::Unchanged
+            | SwitchOutcome::Verified { .. }
+            | SwitchOutcome::Enforced { .. },
+        ),
+        Some(input_id),
+    ) = (&outcome, target_input)
+    {
+        context.record_auto_switch(event, &input_id);
+    }
+
+    outcome
+}
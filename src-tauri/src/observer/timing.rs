@@ -0,0 +1,251 @@
+use super::SwitchOutcome;
+use crate::config::RuleTiming;
+use crate::error::Result;
+use std::time::Duration;
+
+// 切换后多久校验一次当前输入法
+const VERIFY_INTERVAL: Duration = Duration::from_millis(50);
+// 强制窗口内的检查间隔
+const ENFORCE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 按时机设置执行切换所需的操作，便于在测试中替换时间与后端
+pub trait SwitchDriver {
+    /// 焦点是否仍是本次切换的应用
+    fn is_current(&self) -> bool;
+    fn sleep(&self, duration: Duration);
+    /// 切换一次，已是目标状态时返回 `Unchanged`
+    fn apply(&self) -> Result<SwitchOutcome>;
+    /// 当前输入法是否为目标输入法
+    fn is_applied(&self) -> Result<bool>;
+}
+
+/// 依次执行延迟、切换、校验重试与强制窗口
+pub fn run_timed_switch(timing: &RuleTiming, driver: &dyn SwitchDriver) -> Result<SwitchOutcome> {
+    if timing.delay_ms > 0 {
+        driver.sleep(Duration::from_millis(timing.delay_ms));
+        if !driver.is_current() {
+            return Ok(SwitchOutcome::Superseded);
+        }
+    }
+
+    let first = driver.apply()?;
+    if first == SwitchOutcome::Superseded {
+        return Ok(first);
+    }
+
+    let mut retries = 0;
+    if timing.verify_retries > 0 {
+        loop {
+            driver.sleep(VERIFY_INTERVAL);
+            // 已经切换过，焦点离开时返回实际结果，切换线程据此更新上一个应用
+            if !driver.is_current() {
+                return Ok(settled_outcome(first, retries, 0));
+            }
+            if driver.is_applied()? {
+                break;
+            }
+            if retries == timing.verify_retries {
+                return Ok(SwitchOutcome::VerifyFailed { retries });
+            }
+            driver.apply()?;
+            retries += 1;
+        }
+    }
+
+    let window = Duration::from_millis(timing.enforce_ms);
+    let mut waited = Duration::ZERO;
+    let mut reapplied = 0;
+    while waited < window {
+        let step = ENFORCE_POLL_INTERVAL.min(window - waited);
+        driver.sleep(step);
+        waited += step;
+        // 用户已切到别的应用，保留已完成的切换
+        if !driver.is_current() {
+            break;
+        }
+        if !driver.is_applied()? {
+            driver.apply()?;
+            reapplied += 1;
+        }
+    }
+
+    Ok(settled_outcome(first, retries, reapplied))
+}
+
+fn settled_outcome(first: SwitchOutcome, retries: u32, reapplied: u32) -> SwitchOutcome {
+    if reapplied > 0 {
+        SwitchOutcome::Enforced { reapplied }
+    } else if retries > 0 {
+        SwitchOutcome::Verified { retries }
+    } else {
+        first
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::{Cell, RefCell};
+
+    /// 虚拟时间的驱动：应用在指定时刻把输入法改回去
+    struct FakeDriver {
+        now: Cell<Duration>,
+        applied: Cell<bool>,
+        // 应用改回输入法的时刻
+        resets_at: RefCell<Vec<Duration>>,
+        // 焦点离开的时刻
+        leaves_at: Option<Duration>,
+        // 前几次切换不生效
+        ineffective_applies: Cell<u32>,
+        applies: Cell<u32>,
+    }
+
+    impl FakeDriver {
+        fn new() -> Self {
+            Self {
+                now: Cell::new(Duration::ZERO),
+                applied: Cell::new(false),
+                resets_at: RefCell::new(Vec::new()),
+                leaves_at: None,
+                ineffective_applies: Cell::new(0),
+                applies: Cell::new(0),
+            }
+        }
+    }
+
+    impl SwitchDriver for FakeDriver {
+        fn is_current(&self) -> bool {
+            self.leaves_at.map_or(true, |at| self.now.get() < at)
+        }
+
+        fn sleep(&self, duration: Duration) {
+            self.now.set(self.now.get() + duration);
+            let now = self.now.get();
+            let mut resets = self.resets_at.borrow_mut();
+            if resets.iter().any(|at| *at <= now) {
+                resets.retain(|at| *at > now);
+                self.applied.set(false);
+            }
+        }
+
+        fn apply(&self) -> Result<SwitchOutcome> {
+            self.applies.set(self.applies.get() + 1);
+            if self.applied.get() {
+                return Ok(SwitchOutcome::Unchanged);
+            }
+            match self.ineffective_applies.get() {
+                0 => self.applied.set(true),
+                remaining => self.ineffective_applies.set(remaining - 1),
+            }
+            Ok(SwitchOutcome::Switched)
+        }
+
+        fn is_applied(&self) -> Result<bool> {
+            Ok(self.applied.get())
+        }
+    }
+
+    fn ms(value: u64) -> Duration {
+        Duration::from_millis(value)
+    }
+
+    #[test]
+    fn test_default_timing_switches_once() {
+        let driver = FakeDriver::new();
+        assert_eq!(
+            run_timed_switch(&RuleTiming::default(), &driver).unwrap(),
+            SwitchOutcome::Switched
+        );
+        assert_eq!(driver.applies.get(), 1);
+        assert_eq!(driver.now.get(), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_delay_is_cancelled_when_focus_moves_on() {
+        let timing = RuleTiming {
+            delay_ms: 200,
+            ..RuleTiming::default()
+        };
+        let driver = FakeDriver {
+            leaves_at: Some(ms(100)),
+            ..FakeDriver::new()
+        };
+        assert_eq!(
+            run_timed_switch(&timing, &driver).unwrap(),
+            SwitchOutcome::Superseded
+        );
+        assert_eq!(driver.applies.get(), 0);
+    }
+
+    #[test]
+    fn test_verify_retries_until_switch_sticks() {
+        let timing = RuleTiming {
+            verify_retries: 3,
+            ..RuleTiming::default()
+        };
+        let driver = FakeDriver::new();
+        driver.ineffective_applies.set(2);
+        assert_eq!(
+            run_timed_switch(&timing, &driver).unwrap(),
+            SwitchOutcome::Verified { retries: 2 }
+        );
+
+        let driver = FakeDriver::new();
+        driver.ineffective_applies.set(5);
+        assert_eq!(
+            run_timed_switch(&timing, &driver).unwrap(),
+            SwitchOutcome::VerifyFailed { retries: 3 }
+        );
+        assert_eq!(driver.applies.get(), 4);
+    }
+
+    #[test]
+    fn test_focus_leaving_during_verify_keeps_applied_outcome() {
+        let timing = RuleTiming {
+            verify_retries: 3,
+            enforce_ms: 500,
+            ..RuleTiming::default()
+        };
+        let driver = FakeDriver {
+            leaves_at: Some(ms(120)),
+            ..FakeDriver::new()
+        };
+        driver.ineffective_applies.set(5);
+        // 50ms、100ms 两次校验失败后重试，150ms 时焦点已离开
+        assert_eq!(
+            run_timed_switch(&timing, &driver).unwrap(),
+            SwitchOutcome::Verified { retries: 2 }
+        );
+        assert_eq!(driver.applies.get(), 3);
+        assert_eq!(driver.now.get(), ms(150));
+
+        let driver = FakeDriver {
+            leaves_at: Some(ms(30)),
+            ..FakeDriver::new()
+        };
+        assert_eq!(
+            run_timed_switch(&timing, &driver).unwrap(),
+            SwitchOutcome::Switched
+        );
+    }
+
+    #[test]
+    fn test_enforce_window_reapplies_after_app_resets_input() {
+        let timing = RuleTiming {
+            delay_ms: 50,
+            enforce_ms: 500,
+            ..RuleTiming::default()
+        };
+        let driver = FakeDriver::new();
+        driver
+            .resets_at
+            .borrow_mut()
+            .extend([ms(120), ms(400), ms(900)]);
+        assert_eq!(
+            run_timed_switch(&timing, &driver).unwrap(),
+            SwitchOutcome::Enforced { reapplied: 2 }
+        );
+        assert_eq!(driver.now.get(), ms(550));
+        assert!(driver.applied.get());
+    }
+}
@@ -0,0 +1,305 @@
+use super::AppFocusedEvent;
+use crate::error::{AppError, Result};
+use std::sync::mpsc::Sender;
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::{
+    self, AtomEnum, ChangeWindowAttributesAux, ConnectionExt as _, EventMask,
+};
+use x11rb::protocol::Event;
+use x11rb::rust_connection::RustConnection;
+
+x11rb::atom_manager! {
+    Atoms: AtomsCookie {
+        _NET_ACTIVE_WINDOW,
+        _NET_WM_NAME,
+        _NET_WM_PID,
+        UTF8_STRING,
+    }
+}
+
+/// 监听根窗口的 `_NET_ACTIVE_WINDOW`，把活动窗口转换为 `AppFocusedEvent`
+struct ActiveWindowWatcher {
+    connection: RustConnection,
+    root: xproto::Window,
+    atoms: Atoms,
+}
+
+/// 连接 `DISPLAY` 并在后台线程中把焦点变化发送到 `tx`
+pub fn start(tx: Sender<AppFocusedEvent>) -> Result<()> {
+    let watcher = ActiveWindowWatcher::connect(None)?;
+    std::thread::spawn(move || watcher.run(tx));
+    Ok(())
+}
+
+impl ActiveWindowWatcher {
+    fn connect(display: Option<&str>) -> Result<Self> {
+        let (connection, screen) =
+            x11rb::connect(display).map_err(|e| x11_error("display connection", e))?;
+        let root = connection.setup().roots[screen].root;
+        let atoms = Atoms::new(&connection)
+            .map_err(|e| x11_error("InternAtom", e))?
+            .reply()
+            .map_err(|e| x11_error("InternAtom", e))?;
+
+        // 先订阅再返回，保证之后的焦点变化不会漏掉
+        connection
+            .change_window_attributes(
+                root,
+                &ChangeWindowAttributesAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+            )
+            .map_err(|e| x11_error("ChangeWindowAttributes", e))?
+            .check()
+            .map_err(|e| x11_error("ChangeWindowAttributes", e))?;
+
+        Ok(Self {
+            connection,
+            root,
+            atoms,
+        })
+    }
+
+    /// 先上报当前活动窗口，之后每次变化上报一次；接收端关闭或连接断开时退出
+    fn run(self, tx: Sender<AppFocusedEvent>) {
+        let mut last_window = None;
+
+        loop {
+            let window = self.active_window();
+            if window.is_some() && window != last_window {
+                last_window = window;
+                if let Some(event) = window.and_then(|window| self.resolve(window)) {
+                    if tx.send(event).is_err() {
+                        return;
+                    }
+                }
+            }
+
+            if !self.wait_for_active_window_change() {
+                return;
+            }
+        }
+    }
+
+    fn wait_for_active_window_change(&self) -> bool {
+        loop {
+            match self.connection.wait_for_event() {
+                Ok(Event::PropertyNotify(event))
+                    if event.window == self.root && event.atom == self.atoms._NET_ACTIVE_WINDOW =>
+                {
+                    return true;
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    eprintln!("X11 focus observer stopped: {}", e);
+                    return false;
+                }
+            }
+        }
+    }
+
+    fn active_window(&self) -> Option<xproto::Window> {
+        self.property(
+            self.root,
+            self.atoms._NET_ACTIVE_WINDOW,
+            AtomEnum::WINDOW.into(),
+        )?
+        .value32()?
+        .next()
+        .filter(|window| *window != x11rb::NONE)
+    }
+
+    /// WM_CLASS 的 class 部分作为 bundle ID 的等价物
+    fn resolve(&self, window: xproto::Window) -> Option<AppFocusedEvent> {
+        let wm_class = self.property(window, AtomEnum::WM_CLASS.into(), AtomEnum::STRING.into())?;
+        let (instance, class) = parse_wm_class(&wm_class.value)?;
+
+        let window_title = self
+            .property(window, self.atoms._NET_WM_NAME, self.atoms.UTF8_STRING)
+            .or_else(|| self.property(window, AtomEnum::WM_NAME.into(), AtomEnum::STRING.into()))
+            .map(|reply| String::from_utf8_lossy(&reply.value).into_owned())
+            .filter(|title| !title.is_empty());
+        let pid = self
+            .property(window, self.atoms._NET_WM_PID, AtomEnum::CARDINAL.into())
+            .and_then(|reply| reply.value32()?.next());
+
+        Some(AppFocusedEvent {
+            bundle_id: if class.is_empty() {
+                instance.clone()
+            } else {
+                class.clone()
+            },
+            app_name: if class.is_empty() { instance } else { class },
+            pid,
+            window_title,
+        })
+    }
+
+    /// 读取窗口属性，属性缺失或窗口已销毁时返回 `None`
+    fn property(
+        &self,
+        window: xproto::Window,
+        property: xproto::Atom,
+        type_: xproto::Atom,
+    ) -> Option<xproto::GetPropertyReply> {
+        self.connection
+            .get_property(false, window, property, type_, 0, 1024)
+            .ok()?
+            .reply()
+            .ok()
+            .filter(|reply| !reply.value.is_empty())
+    }
+}
+
+/// WM_CLASS 由两个以 NUL 结尾的字符串组成：instance 与 class
+fn parse_wm_class(value: &[u8]) -> Option<(String, String)> {
+    let mut parts = value
+        .split(|byte| *byte == 0)
+        .map(|part| String::from_utf8_lossy(part).trim().to_string());
+    let instance = parts.next().unwrap_or_default();
+    let class = parts.next().unwrap_or_default();
+    if instance.is_empty() && class.is_empty() {
+        return None;
+    }
+    Some((instance, class))
+}
+
+fn x11_error(context: &str, e: impl std::fmt::Display) -> AppError {
+    AppError::Observer(format!("X11 {context} failed: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::x11_test_support::VirtualDisplay;
+    use std::sync::mpsc;
+    use std::time::Duration;
+    use x11rb::protocol::xproto::{CreateWindowAux, PropMode, WindowClass};
+    use x11rb::wrapper::ConnectionExt as _;
+
+    /// 窗口管理器的最小替身：创建客户端窗口并维护 `_NET_ACTIVE_WINDOW`
+    struct FakeWindowManager {
+        connection: RustConnection,
+        root: xproto::Window,
+        atoms: Atoms,
+    }
+
+    impl FakeWindowManager {
+        fn connect(display: &VirtualDisplay) -> Self {
+            let (connection, screen) = x11rb::connect(Some(display.display())).unwrap();
+            let root = connection.setup().roots[screen].root;
+            let atoms = Atoms::new(&connection).unwrap().reply().unwrap();
+            Self {
+                connection,
+                root,
+                atoms,
+            }
+        }
+
+        fn create_window(&self, wm_class: &[u8], title: &str, pid: u32) -> xproto::Window {
+            let window = self.connection.generate_id().unwrap();
+            self.connection
+                .create_window(
+                    x11rb::COPY_DEPTH_FROM_PARENT,
+                    window,
+                    self.root,
+                    0,
+                    0,
+                    100,
+                    100,
+                    0,
+                    WindowClass::INPUT_OUTPUT,
+                    x11rb::COPY_FROM_PARENT,
+                    &CreateWindowAux::new(),
+                )
+                .unwrap();
+            self.connection
+                .change_property8(
+                    PropMode::REPLACE,
+                    window,
+                    AtomEnum::WM_CLASS,
+                    AtomEnum::STRING,
+                    wm_class,
+                )
+                .unwrap();
+            self.connection
+                .change_property8(
+                    PropMode::REPLACE,
+                    window,
+                    self.atoms._NET_WM_NAME,
+                    self.atoms.UTF8_STRING,
+                    title.as_bytes(),
+                )
+                .unwrap();
+            self.connection
+                .change_property32(
+                    PropMode::REPLACE,
+                    window,
+                    self.atoms._NET_WM_PID,
+                    AtomEnum::CARDINAL,
+                    &[pid],
+                )
+                .unwrap();
+            self.connection.flush().unwrap();
+            window
+        }
+
+        fn activate(&self, window: xproto::Window) {
+            self.connection
+                .change_property32(
+                    PropMode::REPLACE,
+                    self.root,
+                    self.atoms._NET_ACTIVE_WINDOW,
+                    AtomEnum::WINDOW,
+                    &[window],
+                )
+                .unwrap();
+            self.connection.flush().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_parse_wm_class() {
+        assert_eq!(
+            parse_wm_class(b"code\0Code\0"),
+            Some(("code".to_string(), "Code".to_string()))
+        );
+        assert_eq!(
+            parse_wm_class(b"xterm\0"),
+            Some(("xterm".to_string(), String::new()))
+        );
+        assert_eq!(parse_wm_class(b"\0\0"), None);
+    }
+
+    #[test]
+    fn test_x11_focus_observer_against_xvfb() {
+        let Some(display) = VirtualDisplay::start() else {
+            return;
+        };
+        let wm = FakeWindowManager::connect(&display);
+        let editor = wm.create_window(b"code\0Code\0", "main.rs - SmartIME", 4242);
+        let terminal = wm.create_window(b"xterm\0XTerm\0", "bash", 4343);
+        wm.activate(editor);
+
+        let watcher = ActiveWindowWatcher::connect(Some(display.display())).unwrap();
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || watcher.run(tx));
+
+        let initial = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(initial.bundle_id, "Code");
+        assert_eq!(initial.app_name, "Code");
+        assert_eq!(initial.pid, Some(4242));
+        assert_eq!(initial.window_title.as_deref(), Some("main.rs - SmartIME"));
+
+        wm.activate(terminal);
+        let switched = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(switched.bundle_id, "XTerm");
+        assert_eq!(switched.pid, Some(4343));
+
+        // 活动窗口未变化时不重复上报
+        wm.activate(terminal);
+        wm.activate(editor);
+        assert_eq!(
+            rx.recv_timeout(Duration::from_secs(5)).unwrap().bundle_id,
+            "Code"
+        );
+    }
+}
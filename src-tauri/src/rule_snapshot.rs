@@ -0,0 +1,146 @@
+use crate::config::{AppConfig, RuleTarget};
+use arc_swap::ArcSwap;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 由配置编译出的只读快照，焦点切换热路径不加锁、不克隆整份配置
+#[derive(Debug, Default)]
+pub struct RuleSnapshot {
+    global_switch: bool,
+    remember_all: bool,
+    rules: HashMap<String, RuleTarget>,
+    // 单独开启“记住上次输入法”的应用
+    remembered: HashSet<String>,
+    settle_delay: Duration,
+    ignored_apps: Vec<String>,
+    dry_run: bool,
+}
+
+impl RuleSnapshot {
+    pub fn compile(config: &AppConfig) -> Self {
+        let mut rules = HashMap::with_capacity(config.rules.len());
+        let mut remembered = HashSet::new();
+        for rule in &config.rules {
+            rules.insert(
+                rule.bundle_id.clone(),
+                RuleTarget {
+                    input_id: rule.preferred_input.clone(),
+                    input_mode: rule.input_mode,
+                    timing: rule.timing,
+                },
+            );
+            if rule.remember_last_input {
+                remembered.insert(rule.bundle_id.clone());
+            }
+        }
+
+        Self {
+            global_switch: config.global_switch,
+            remember_all: config.switching.remember_last_input,
+            rules,
+            remembered,
+            settle_delay: Duration::from_millis(config.switching.settle_delay_ms),
+            ignored_apps: config.switching.ignored_apps.clone(),
+            dry_run: config.switching.dry_run,
+        }
+    }
+
+    pub fn global_switch(&self) -> bool {
+        self.global_switch
+    }
+
+    /// 应用的目标输入法，全局开关关闭时不切换
+    pub fn resolve(&self, bundle_id: &str) -> Option<&RuleTarget> {
+        if !self.global_switch {
+            return None;
+        }
+        self.rules.get(bundle_id)
+    }
+
+    /// 应用失去焦点时是否需要记录当前输入法
+    pub fn should_remember(&self, bundle_id: &str) -> bool {
+        self.global_switch && (self.remember_all || self.remembered.contains(bundle_id))
+    }
+
+    pub fn settle_delay(&self) -> Duration {
+        self.settle_delay
+    }
+
+    pub fn ignored_apps(&self) -> &[String] {
+        &self.ignored_apps
+    }
+
+    pub fn dry_run(&self) -> bool {
+        self.dry_run
+    }
+}
+
+/// 跨线程共享的最新快照，`ConfigManager` 每次修改配置后整体替换
+#[derive(Clone, Default)]
+pub struct RuleSnapshots(Arc<ArcSwap<RuleSnapshot>>);
+
+impl RuleSnapshots {
+    pub fn load(&self) -> Arc<RuleSnapshot> {
+        self.0.load_full()
+    }
+
+    pub fn publish(&self, snapshot: RuleSnapshot) {
+        self.0.store(Arc::new(snapshot));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{AppRule, ConfigManager, RuleTiming};
+    use crate::test_support::TempDir;
+
+    fn rule(index: usize) -> AppRule {
+        AppRule {
+            bundle_id: format!("com.example.app{index}"),
+            app_name: format!("App {index}"),
+            preferred_input: if index % 2 == 0 { "pinyin" } else { "abc" }.to_string(),
+            is_ai_generated: true,
+            input_mode: None,
+            remember_last_input: index % 7 == 0,
+            is_learned: false,
+            timing: RuleTiming::default(),
+        }
+    }
+
+    fn config(rule_count: usize) -> AppConfig {
+        AppConfig {
+            rules: (0..rule_count).map(rule).collect(),
+            ..AppConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_snapshot_is_republished_on_set_config() {
+        let dir = TempDir::new("snapshot-test");
+        let file_path = dir.join("config.json");
+        let mut manager = ConfigManager::with_config(config(10), file_path.clone());
+        let snapshots = manager.snapshots();
+        let before = snapshots.load();
+        assert_eq!(
+            before
+                .resolve("com.example.app4")
+                .map(|target| target.input_id.as_str()),
+            Some("pinyin")
+        );
+        assert!(before.should_remember("com.example.app7"));
+        assert!(!before.should_remember("com.example.app8"));
+
+        let mut disabled = manager.get_config();
+        disabled.global_switch = false;
+        manager.set_config(disabled).unwrap();
+
+        // 已取出的快照保持不变，新读取看到新配置
+        assert!(before.resolve("com.example.app4").is_some());
+        let after = snapshots.load();
+        assert!(!after.global_switch());
+        assert_eq!(after.resolve("com.example.app4"), None);
+        assert!(!after.should_remember("com.example.app7"));
+    }
+}
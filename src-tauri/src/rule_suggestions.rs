@@ -0,0 +1,213 @@
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+// 自动切换后多久内的手动切换算作纠正
+const CORRECTION_WINDOW: Duration = Duration::from_secs(5);
+// 同一应用被纠正到同一输入法多少次后生成建议
+const SUGGESTION_THRESHOLD: u32 = 3;
+
+/// 根据反复的手动纠正生成的规则修改建议，例如 “把 Slack 改为拼音？”
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct RuleSuggestion {
+    pub bundle_id: String,
+    pub app_name: String,
+    /// 当前规则切换到的输入法
+    pub current_input: String,
+    /// 用户每次手动改成的输入法
+    pub suggested_input: String,
+    pub corrections: u32,
+}
+
+struct AutoSwitch {
+    bundle_id: String,
+    app_name: String,
+    input_id: String,
+    at: Instant,
+}
+
+/// 关联 observer 的自动切换与随后的手动切换，按应用统计纠正次数
+pub struct SuggestionEngine {
+    window: Duration,
+    threshold: u32,
+    last_auto: Option<AutoSwitch>,
+    // (bundle_id, 纠正后的输入法) -> 次数
+    corrections: HashMap<(String, String), u32>,
+    dismissed: HashSet<(String, String)>,
+    suggestions: Vec<RuleSuggestion>,
+}
+
+impl Default for SuggestionEngine {
+    fn default() -> Self {
+        Self::new(CORRECTION_WINDOW, SUGGESTION_THRESHOLD)
+    }
+}
+
+impl SuggestionEngine {
+    pub fn new(window: Duration, threshold: u32) -> Self {
+        Self {
+            window,
+            threshold: threshold.max(1),
+            last_auto: None,
+            corrections: HashMap::new(),
+            dismissed: HashSet::new(),
+            suggestions: Vec::new(),
+        }
+    }
+
+    /// observer 按规则把应用切到（或确认已是）`input_id`
+    pub fn record_auto_switch(
+        &mut self,
+        bundle_id: &str,
+        app_name: &str,
+        input_id: &str,
+        now: Instant,
+    ) {
+        self.last_auto = Some(AutoSwitch {
+            bundle_id: bundle_id.to_string(),
+            app_name: app_name.to_string(),
+            input_id: input_id.to_string(),
+            at: now,
+        });
+    }
+
+    /// 用户在 `bundle_id` 中手动切到 `input_id`，达到阈值时返回新生成的建议
+    pub fn record_user_change(
+        &mut self,
+        bundle_id: &str,
+        input_id: &str,
+        now: Instant,
+    ) -> Option<RuleSuggestion> {
+        // 每次自动切换只关联第一次手动切换
+        let auto = self.last_auto.take()?;
+        if auto.bundle_id != bundle_id
+            || auto.input_id == input_id
+            || now.saturating_duration_since(auto.at) > self.window
+        {
+            return None;
+        }
+
+        let key = (bundle_id.to_string(), input_id.to_string());
+        let count = self.corrections.entry(key.clone()).or_insert(0);
+        *count += 1;
+        let corrections = *count;
+        if corrections < self.threshold || self.dismissed.contains(&key) {
+            return None;
+        }
+
+        let suggestion = RuleSuggestion {
+            bundle_id: auto.bundle_id,
+            app_name: auto.app_name,
+            current_input: auto.input_id,
+            suggested_input: input_id.to_string(),
+            corrections,
+        };
+        // 每个应用只保留最新的一条建议
+        self.suggestions
+            .retain(|existing| existing.bundle_id != suggestion.bundle_id);
+        self.suggestions.push(suggestion.clone());
+        Some(suggestion)
+    }
+
+    pub fn list(&self) -> Vec<RuleSuggestion> {
+        self.suggestions.clone()
+    }
+
+    /// 取出建议用于生成手动规则，并清空该应用的计数
+    pub fn accept(&mut self, bundle_id: &str) -> Option<RuleSuggestion> {
+        let index = self
+            .suggestions
+            .iter()
+            .position(|suggestion| suggestion.bundle_id == bundle_id)?;
+        let suggestion = self.suggestions.remove(index);
+        self.corrections
+            .retain(|(bundle, _), _| bundle != bundle_id);
+        Some(suggestion)
+    }
+
+    /// 忽略建议，之后不再对同一应用与输入法重复建议
+    pub fn dismiss(&mut self, bundle_id: &str) -> bool {
+        match self.accept(bundle_id) {
+            Some(suggestion) => {
+                self.dismissed
+                    .insert((suggestion.bundle_id, suggestion.suggested_input));
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SLACK: &str = "com.tinyspeck.slackmacgap";
+    const ABC: &str = "com.apple.keylayout.ABC";
+    const PINYIN: &str = "com.apple.inputmethod.SCIM.ITABC";
+
+    fn correct_slack(engine: &mut SuggestionEngine, at: Instant) -> Option<RuleSuggestion> {
+        engine.record_auto_switch(SLACK, "Slack", ABC, at);
+        engine.record_user_change(SLACK, PINYIN, at + Duration::from_secs(1))
+    }
+
+    #[test]
+    fn test_repeated_corrections_queue_a_suggestion() {
+        let mut engine = SuggestionEngine::default();
+        let start = Instant::now();
+
+        assert_eq!(correct_slack(&mut engine, start), None);
+        assert_eq!(correct_slack(&mut engine, start), None);
+        let suggestion = correct_slack(&mut engine, start).unwrap();
+        assert_eq!(
+            suggestion,
+            RuleSuggestion {
+                bundle_id: SLACK.to_string(),
+                app_name: "Slack".to_string(),
+                current_input: ABC.to_string(),
+                suggested_input: PINYIN.to_string(),
+                corrections: 3,
+            }
+        );
+        assert_eq!(engine.list(), vec![suggestion.clone()]);
+
+        assert_eq!(engine.accept(SLACK), Some(suggestion));
+        assert!(engine.list().is_empty());
+        // 接受后重新计数
+        assert_eq!(correct_slack(&mut engine, start), None);
+    }
+
+    #[test]
+    fn test_only_prompt_corrections_in_same_app_count() {
+        let mut engine = SuggestionEngine::new(Duration::from_secs(5), 1);
+        let start = Instant::now();
+
+        // 超出时间窗口
+        engine.record_auto_switch(SLACK, "Slack", ABC, start);
+        assert_eq!(
+            engine.record_user_change(SLACK, PINYIN, start + Duration::from_secs(6)),
+            None
+        );
+        // 切换发生在别的应用
+        engine.record_auto_switch(SLACK, "Slack", ABC, start);
+        assert_eq!(
+            engine.record_user_change("com.apple.Terminal", PINYIN, start),
+            None
+        );
+        // 一次自动切换只关联一次手动切换
+        assert_eq!(engine.record_user_change(SLACK, PINYIN, start), None);
+        assert!(engine.list().is_empty());
+    }
+
+    #[test]
+    fn test_dismissed_suggestion_is_not_repeated() {
+        let mut engine = SuggestionEngine::new(Duration::from_secs(5), 1);
+        let start = Instant::now();
+
+        assert!(correct_slack(&mut engine, start).is_some());
+        assert!(engine.dismiss(SLACK));
+        assert!(!engine.dismiss(SLACK));
+        assert_eq!(correct_slack(&mut engine, start), None);
+        assert!(engine.list().is_empty());
+    }
+}
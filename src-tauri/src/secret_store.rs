@@ -0,0 +1,402 @@
+use crate::error::{AppError, Result};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+#[cfg(all(feature = "os-keyring", target_os = "macos"))]
+const KEYRING_SERVICE: &str = "com.smartime.app";
+
+/// 敏感配置（例如 LLM API Key）的存储后端
+pub trait SecretStore: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn get(&self, key: &str) -> Result<Option<String>>;
+    fn set(&self, key: &str, value: &str) -> Result<()>;
+    fn delete(&self, key: &str) -> Result<()>;
+}
+
+/// 优先使用系统钥匙串（需启用 `os-keyring` feature），否则回退到本地加密文件
+///
+/// 启用钥匙串后，之前保存在加密文件中的条目会在首次读取时迁移过去。
+/// 反过来关闭该 feature 后无法再读取钥匙串，需要重新输入 API Key。
+pub fn default_secret_store() -> Arc<dyn SecretStore> {
+    let config_dir = dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("smartime");
+    let file_store = EncryptedFileStore::in_dir(&config_dir);
+
+    #[cfg(all(feature = "os-keyring", target_os = "macos"))]
+    {
+        let keyring = KeyringSecretStore::new(KEYRING_SERVICE);
+        if keyring.is_available() {
+            return Arc::new(MigratingSecretStore::new(keyring, file_store));
+        }
+    }
+
+    Arc::new(file_store)
+}
+
+/// 读取时从旧存储迁移：当前存储没有条目时读取旧存储，写入当前存储后删除旧条目
+#[cfg(any(test, all(feature = "os-keyring", target_os = "macos")))]
+pub struct MigratingSecretStore<P, L> {
+    primary: P,
+    legacy: L,
+}
+
+#[cfg(any(test, all(feature = "os-keyring", target_os = "macos")))]
+impl<P: SecretStore, L: SecretStore> MigratingSecretStore<P, L> {
+    pub fn new(primary: P, legacy: L) -> Self {
+        Self { primary, legacy }
+    }
+}
+
+#[cfg(any(test, all(feature = "os-keyring", target_os = "macos")))]
+impl<P: SecretStore, L: SecretStore> SecretStore for MigratingSecretStore<P, L> {
+    fn name(&self) -> &'static str {
+        self.primary.name()
+    }
+
+    fn get(&self, key: &str) -> Result<Option<String>> {
+        if let Some(value) = self.primary.get(key)? {
+            return Ok(Some(value));
+        }
+        let Some(value) = self.legacy.get(key)? else {
+            return Ok(None);
+        };
+
+        // 迁移失败时仍返回旧值，下次读取再试
+        match self.primary.set(key, &value) {
+            Ok(()) => {
+                if let Err(e) = self.legacy.delete(key) {
+                    eprintln!(
+                        "Failed to remove migrated secret from {}: {}",
+                        self.legacy.name(),
+                        e
+                    );
+                }
+            }
+            Err(e) => eprintln!("Failed to migrate secret to {}: {}", self.primary.name(), e),
+        }
+        Ok(Some(value))
+    }
+
+    fn set(&self, key: &str, value: &str) -> Result<()> {
+        self.primary.set(key, value)?;
+        // 避免旧存储中残留过期的副本
+        self.legacy.delete(key)
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        self.primary.delete(key)?;
+        self.legacy.delete(key)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct EncryptedPayload {
+    version: u32,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// 使用本机随机密钥加密的 JSON 文件，密钥与密文均以 0600 权限写入
+///
+/// 不依赖任何桌面钥匙串服务，Linux 与测试环境下同样可用。
+/// `secrets.key` 与 `secrets.enc` 放在同一目录，这只是混淆：可以避免配置目录被
+/// 备份、同步或误提交时直接泄露明文，但能读取该目录的进程同样能解密。
+/// 需要真正的保护时请启用 `os-keyring`。
+pub struct EncryptedFileStore {
+    data_path: PathBuf,
+    key_path: PathBuf,
+}
+
+impl EncryptedFileStore {
+    pub fn in_dir(dir: &Path) -> Self {
+        Self {
+            data_path: dir.join("secrets.enc"),
+            key_path: dir.join("secrets.key"),
+        }
+    }
+
+    fn cipher(&self, create_key: bool) -> Result<Option<ChaCha20Poly1305>> {
+        let key = match fs::read(&self.key_path) {
+            Ok(bytes) if bytes.len() == KEY_LEN => bytes,
+            Ok(_) => {
+                return Err(AppError::SecretStore(format!(
+                    "Invalid key file: {}",
+                    self.key_path.display()
+                )))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                if !create_key {
+                    return Ok(None);
+                }
+                let bytes = random_bytes::<KEY_LEN>()?.to_vec();
+                write_private_file(&self.key_path, &bytes)?;
+                bytes
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Some(ChaCha20Poly1305::new(Key::from_slice(&key))))
+    }
+
+    fn read_all(&self) -> Result<BTreeMap<String, String>> {
+        if !self.data_path.exists() {
+            return Ok(BTreeMap::new());
+        }
+        let Some(cipher) = self.cipher(false)? else {
+            return Err(AppError::SecretStore(
+                "Encrypted secrets exist but the key file is missing".to_string(),
+            ));
+        };
+
+        let payload: EncryptedPayload = serde_json::from_slice(&fs::read(&self.data_path)?)?;
+        let nonce = decode_base64(&payload.nonce)?;
+        let ciphertext = decode_base64(&payload.ciphertext)?;
+        if nonce.len() != NONCE_LEN {
+            return Err(AppError::SecretStore("Invalid nonce length".to_string()));
+        }
+
+        let plaintext = cipher
+            .decrypt(Nonce::from_slice(&nonce), ciphertext.as_ref())
+            .map_err(|_| AppError::SecretStore("Failed to decrypt secrets".to_string()))?;
+        Ok(serde_json::from_slice(&plaintext)?)
+    }
+
+    fn write_all(&self, secrets: &BTreeMap<String, String>) -> Result<()> {
+        let Some(cipher) = self.cipher(true)? else {
+            return Err(AppError::SecretStore(
+                "Failed to create encryption key".to_string(),
+            ));
+        };
+
+        let nonce = random_bytes::<NONCE_LEN>()?;
+        let plaintext = serde_json::to_vec(secrets)?;
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce), plaintext.as_ref())
+            .map_err(|_| AppError::SecretStore("Failed to encrypt secrets".to_string()))?;
+
+        let payload = EncryptedPayload {
+            version: 1,
+            nonce: STANDARD.encode(nonce),
+            ciphertext: STANDARD.encode(ciphertext),
+        };
+        write_private_file(&self.data_path, &serde_json::to_vec_pretty(&payload)?)
+    }
+}
+
+impl SecretStore for EncryptedFileStore {
+    fn name(&self) -> &'static str {
+        "encrypted-file"
+    }
+
+    fn get(&self, key: &str) -> Result<Option<String>> {
+        Ok(self.read_all()?.remove(key))
+    }
+
+    fn set(&self, key: &str, value: &str) -> Result<()> {
+        let mut secrets = self.read_all()?;
+        secrets.insert(key.to_string(), value.to_string());
+        self.write_all(&secrets)
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        let mut secrets = self.read_all()?;
+        if secrets.remove(key).is_some() {
+            self.write_all(&secrets)?;
+        }
+        Ok(())
+    }
+}
+
+/// macOS 钥匙串后端
+#[cfg(all(feature = "os-keyring", target_os = "macos"))]
+pub struct KeyringSecretStore {
+    service: String,
+}
+
+#[cfg(all(feature = "os-keyring", target_os = "macos"))]
+impl KeyringSecretStore {
+    pub fn new(service: &str) -> Self {
+        Self {
+            service: service.to_string(),
+        }
+    }
+
+    fn is_available(&self) -> bool {
+        let Ok(entry) = keyring::Entry::new(&self.service, "availability-probe") else {
+            return false;
+        };
+        matches!(entry.get_password(), Ok(_) | Err(keyring::Error::NoEntry))
+    }
+
+    fn entry(&self, key: &str) -> Result<keyring::Entry> {
+        keyring::Entry::new(&self.service, key)
+            .map_err(|e| AppError::SecretStore(format!("Keyring entry error: {e}")))
+    }
+}
+
+#[cfg(all(feature = "os-keyring", target_os = "macos"))]
+impl SecretStore for KeyringSecretStore {
+    fn name(&self) -> &'static str {
+        "os-keyring"
+    }
+
+    fn get(&self, key: &str) -> Result<Option<String>> {
+        match self.entry(key)?.get_password() {
+            Ok(value) => Ok(Some(value)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(AppError::SecretStore(format!("Keyring read error: {e}"))),
+        }
+    }
+
+    fn set(&self, key: &str, value: &str) -> Result<()> {
+        self.entry(key)?
+            .set_password(value)
+            .map_err(|e| AppError::SecretStore(format!("Keyring write error: {e}")))
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        match self.entry(key)?.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(AppError::SecretStore(format!("Keyring delete error: {e}"))),
+        }
+    }
+}
+
+/// 以 0600 权限原子写入文件（先写临时文件再重命名）
+pub fn write_private_file(path: &Path, content: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_path);
+    {
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(&tmp_path)?;
+        std::io::Write::write_all(&mut file, content)?;
+        file.sync_all()?;
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&tmp_path, fs::Permissions::from_mode(0o600))?;
+    }
+
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+fn random_bytes<const N: usize>() -> Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    getrandom::getrandom(&mut bytes)
+        .map_err(|e| AppError::SecretStore(format!("Failed to generate random bytes: {e}")))?;
+    Ok(bytes)
+}
+
+fn decode_base64(value: &str) -> Result<Vec<u8>> {
+    STANDARD
+        .decode(value)
+        .map_err(|e| AppError::SecretStore(format!("Invalid base64 payload: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn test_encrypted_file_store_roundtrip() {
+        let dir = TempDir::new("secret-store-test");
+        let store = EncryptedFileStore::in_dir(&dir);
+
+        assert_eq!(store.get("llm_api_key").unwrap(), None);
+        store.set("llm_api_key", "sk-secret-value").unwrap();
+        assert_eq!(
+            store.get("llm_api_key").unwrap(),
+            Some("sk-secret-value".to_string())
+        );
+
+        let on_disk = fs::read_to_string(dir.join("secrets.enc")).unwrap();
+        assert!(!on_disk.contains("sk-secret-value"));
+
+        store.delete("llm_api_key").unwrap();
+        assert_eq!(store.get("llm_api_key").unwrap(), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_encrypted_file_store_writes_private_files() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new("secret-store-test");
+        let store = EncryptedFileStore::in_dir(&dir);
+        store.set("llm_api_key", "sk-secret-value").unwrap();
+
+        for name in ["secrets.enc", "secrets.key"] {
+            let mode = fs::metadata(dir.join(name)).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600, "{name} should be 0600");
+        }
+    }
+
+    #[test]
+    fn test_migrating_store_reads_and_moves_legacy_entries() {
+        let primary_dir = TempDir::new("secret-store-test");
+        let legacy_dir = TempDir::new("secret-store-test");
+        let legacy = EncryptedFileStore::in_dir(&legacy_dir);
+        legacy.set("llm_api_key", "sk-legacy").unwrap();
+
+        let store = MigratingSecretStore::new(
+            EncryptedFileStore::in_dir(&primary_dir),
+            EncryptedFileStore::in_dir(&legacy_dir),
+        );
+        assert_eq!(
+            store.get("llm_api_key").unwrap(),
+            Some("sk-legacy".to_string())
+        );
+        assert_eq!(
+            EncryptedFileStore::in_dir(&primary_dir)
+                .get("llm_api_key")
+                .unwrap(),
+            Some("sk-legacy".to_string())
+        );
+        assert_eq!(legacy.get("llm_api_key").unwrap(), None);
+
+        store.set("llm_api_key", "sk-new").unwrap();
+        assert_eq!(
+            store.get("llm_api_key").unwrap(),
+            Some("sk-new".to_string())
+        );
+        store.delete("llm_api_key").unwrap();
+        assert_eq!(store.get("llm_api_key").unwrap(), None);
+    }
+
+    #[test]
+    fn test_encrypted_file_store_rejects_foreign_key() {
+        let dir = TempDir::new("secret-store-test");
+        let store = EncryptedFileStore::in_dir(&dir);
+        store.set("llm_api_key", "sk-secret-value").unwrap();
+
+        write_private_file(&dir.join("secrets.key"), &[7u8; KEY_LEN]).unwrap();
+        assert!(matches!(
+            store.get("llm_api_key"),
+            Err(AppError::SecretStore(_))
+        ));
+    }
+}
@@ -0,0 +1,288 @@
+use crate::command::{run_backend_task, run_switch_task};
+use crate::config::{AppState, ShortcutSettings};
+use crate::error::{AppError, Result};
+use crate::input_source::InputSource;
+use crate::switching_pause;
+use crate::tray_menu;
+use std::str::FromStr;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutEvent, ShortcutState};
+
+/// 可绑定全局快捷键的操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShortcutAction {
+    ToggleGlobalSwitch,
+    PauseSwitching,
+    CycleInput,
+    PinCurrentInput,
+}
+
+impl ShortcutAction {
+    /// 对应 `ShortcutSettings` 中的字段名，用于错误提示
+    fn setting_name(self) -> &'static str {
+        match self {
+            Self::ToggleGlobalSwitch => "toggle_global_switch",
+            Self::PauseSwitching => "pause_switching",
+            Self::CycleInput => "cycle_input",
+            Self::PinCurrentInput => "pin_current_input",
+        }
+    }
+}
+
+/// 解析已配置的快捷键，返回可注册的绑定以及无效或重复的绑定说明
+fn plan_bindings(settings: &ShortcutSettings) -> (Vec<(Shortcut, ShortcutAction)>, Vec<String>) {
+    let configured = [
+        (
+            ShortcutAction::ToggleGlobalSwitch,
+            &settings.toggle_global_switch,
+        ),
+        (ShortcutAction::PauseSwitching, &settings.pause_switching),
+        (ShortcutAction::CycleInput, &settings.cycle_input),
+        (ShortcutAction::PinCurrentInput, &settings.pin_current_input),
+    ];
+
+    let mut bindings: Vec<(Shortcut, ShortcutAction)> = Vec::new();
+    let mut problems = Vec::new();
+    for (action, accelerator) in configured {
+        let Some(accelerator) = accelerator
+            .as_deref()
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+        else {
+            continue;
+        };
+
+        let shortcut = match Shortcut::from_str(accelerator) {
+            Ok(shortcut) => shortcut,
+            Err(e) => {
+                problems.push(format!(
+                    "{}: invalid shortcut \"{}\" ({})",
+                    action.setting_name(),
+                    accelerator,
+                    e
+                ));
+                continue;
+            }
+        };
+
+        match bindings.iter().find(|(bound, _)| *bound == shortcut) {
+            Some((_, existing)) => problems.push(format!(
+                "{}: \"{}\" is already bound to {}",
+                action.setting_name(),
+                accelerator,
+                existing.setting_name()
+            )),
+            None => bindings.push((shortcut, action)),
+        }
+    }
+
+    (bindings, problems)
+}
+
+/// 按设置重新注册全部快捷键，冲突或注册失败时返回汇总错误
+///
+/// 没有问题的绑定仍会注册。
+pub fn register_shortcuts(app: &AppHandle, settings: &ShortcutSettings) -> Result<()> {
+    let manager = app.global_shortcut();
+    manager
+        .unregister_all()
+        .map_err(|e| AppError::Config(format!("Failed to unregister shortcuts: {}", e)))?;
+
+    let (bindings, mut problems) = plan_bindings(settings);
+    for (shortcut, action) in bindings {
+        // 被其他应用占用的组合键在这里失败
+        if let Err(e) = manager.register(shortcut) {
+            problems.push(format!("{}: {}", action.setting_name(), e));
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::Config(format!(
+            "Failed to register shortcuts: {}",
+            problems.join("; ")
+        )))
+    }
+}
+
+/// 全局快捷键插件的回调
+pub fn handle_shortcut(app: &AppHandle, shortcut: &Shortcut, event: ShortcutEvent) {
+    if event.state != ShortcutState::Pressed {
+        return;
+    }
+
+    let Ok(settings) = app
+        .state::<AppState>()
+        .config
+        .lock()
+        .map(|manager| manager.get_config().general.shortcuts)
+    else {
+        return;
+    };
+    let Some(action) = plan_bindings(&settings)
+        .0
+        .into_iter()
+        .find(|(bound, _)| bound == shortcut)
+        .map(|(_, action)| action)
+    else {
+        return;
+    };
+
+    spawn_action(app, action);
+}
+
+/// 在后台线程执行操作，快捷键与托盘菜单共用
+///
+/// 输入法操作可能要等待主线程执行，不能阻塞事件回调。
+pub fn spawn_action(app: &AppHandle, action: ShortcutAction) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        if let Err(e) = run_action(&app, action) {
+            eprintln!("Failed to run {}: {}", action.setting_name(), e);
+        }
+        tray_menu::refresh(&app);
+    });
+}
+
+fn run_action(app: &AppHandle, action: ShortcutAction) -> Result<()> {
+    let state = app.state::<AppState>();
+    match action {
+        ShortcutAction::ToggleGlobalSwitch => {
+            let enabled = {
+                let mut manager = state
+                    .config
+                    .lock()
+                    .map_err(|e| AppError::Lock(e.to_string()))?;
+                let mut config = manager.get_config();
+                config.global_switch = !config.global_switch;
+                let enabled = config.global_switch;
+                manager.set_config(config)?;
+                enabled
+            };
+            emit(app, "global_switch_changed", enabled);
+        }
+        ShortcutAction::PauseSwitching => {
+            // 再次按下时提前恢复
+            if state.switching_pause.is_paused() {
+                switching_pause::resume_switching(app);
+            } else {
+                let pause_minutes = state
+                    .config
+                    .lock()
+                    .map_err(|e| AppError::Lock(e.to_string()))?
+                    .get_config()
+                    .general
+                    .shortcuts
+                    .pause_minutes;
+                let duration = Duration::from_secs(pause_minutes.saturating_mul(60));
+                switching_pause::pause_switching(app, Some(duration));
+            }
+        }
+        ShortcutAction::CycleInput => {
+            run_switch_task(
+                app,
+                "input source cycle",
+                Duration::from_secs(2),
+                |backend| {
+                    let sources = backend.list()?;
+                    let current = backend.current()?;
+                    match next_input_source(&sources, &current.id) {
+                        Some(next) => backend.select(&next.id),
+                        None => Ok(()),
+                    }
+                },
+            )?;
+        }
+        ShortcutAction::PinCurrentInput => {
+            let app_info = state.input_changes.frontmost().ok_or_else(|| {
+                AppError::InputSource("No frontmost app to pin the input source to".to_string())
+            })?;
+            let current = run_backend_task(
+                app,
+                "current input source",
+                Duration::from_millis(500),
+                |backend| backend.current(),
+            )?;
+            let rule = state
+                .config
+                .lock()
+                .map_err(|e| AppError::Lock(e.to_string()))?
+                .upsert_manual_rule(&app_info.bundle_id, &app_info.app_name, &current.id)?;
+            emit(app, "rule_pinned", rule);
+        }
+    }
+    Ok(())
+}
+
+/// 循环切换的下一个输入法，当前输入法不在列表中时从第一个开始
+fn next_input_source<'a>(sources: &'a [InputSource], current_id: &str) -> Option<&'a InputSource> {
+    let next = sources
+        .iter()
+        .position(|source| source.id == current_id)
+        .map_or(0, |index| (index + 1) % sources.len());
+    sources.get(next).filter(|source| source.id != current_id)
+}
+
+fn emit<S: serde::Serialize + Clone>(app: &AppHandle, event: &str, payload: S) {
+    if let Err(e) = app.emit(event, payload) {
+        eprintln!("Failed to emit {} event: {}", event, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(id: &str) -> InputSource {
+        InputSource {
+            id: id.to_string(),
+            name: id.to_string(),
+            category: "TISCategoryKeyboardInputSource".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_plan_bindings_reports_invalid_and_conflicting_shortcuts() {
+        let settings = ShortcutSettings {
+            toggle_global_switch: Some("CmdOrCtrl+Shift+I".to_string()),
+            pause_switching: Some("Shift+CmdOrCtrl+I".to_string()),
+            cycle_input: Some("Ctrl+Nope".to_string()),
+            pin_current_input: Some("  ".to_string()),
+            ..ShortcutSettings::default()
+        };
+
+        let (bindings, problems) = plan_bindings(&settings);
+        assert_eq!(
+            bindings
+                .iter()
+                .map(|(_, action)| *action)
+                .collect::<Vec<_>>(),
+            vec![ShortcutAction::ToggleGlobalSwitch]
+        );
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0].starts_with("pause_switching:"));
+        assert!(problems[0].contains("toggle_global_switch"));
+        assert!(problems[1].starts_with("cycle_input:"));
+    }
+
+    #[test]
+    fn test_next_input_source_wraps_around() {
+        let sources = vec![source("abc"), source("pinyin"), source("kana")];
+        assert_eq!(
+            next_input_source(&sources, "pinyin").map(|s| s.id.as_str()),
+            Some("kana")
+        );
+        assert_eq!(
+            next_input_source(&sources, "kana").map(|s| s.id.as_str()),
+            Some("abc")
+        );
+        assert_eq!(
+            next_input_source(&sources, "unknown").map(|s| s.id.as_str()),
+            Some("abc")
+        );
+        assert_eq!(next_input_source(&sources[..1], "abc"), None);
+        assert_eq!(next_input_source(&[], "abc"), None);
+    }
+}
@@ -0,0 +1,199 @@
+use crate::config::AppState;
+use crate::llm_audit::now_ms;
+use serde::Serialize;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// 定时器按墙上时间分段检查，系统睡眠期间 `sleep` 不计时
+const EXPIRY_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 暂停状态，也是 `switching_paused_changed` 事件负载
+#[derive(Debug, Clone, Copy, Default, Serialize, PartialEq, Eq)]
+pub struct PauseStatus {
+    pub paused: bool,
+    /// 自动恢复的时间（Unix 毫秒），`None` 表示直到手动恢复或重启
+    pub resume_at_ms: Option<u64>,
+}
+
+#[derive(Default)]
+struct PauseState {
+    status: PauseStatus,
+    // 每次暂停递增，过期的定时器不会恢复新的暂停
+    token: u64,
+}
+
+/// 临时暂停自动切换，不修改 `global_switch`，重启后自然恢复
+#[derive(Default)]
+pub struct SwitchingPause {
+    state: Mutex<PauseState>,
+}
+
+impl SwitchingPause {
+    fn lock(&self) -> MutexGuard<'_, PauseState> {
+        // 状态只有标志与时间，锁中毒后继续使用即可
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 开始暂停并返回状态与本次暂停的标识
+    pub fn pause_at(&self, duration: Option<Duration>, now_ms: u64) -> (PauseStatus, u64) {
+        let mut state = self.lock();
+        state.token += 1;
+        state.status = PauseStatus {
+            paused: true,
+            resume_at_ms: duration
+                .map(|duration| now_ms.saturating_add(duration.as_millis() as u64)),
+        };
+        (state.status, state.token)
+    }
+
+    pub fn resume(&self) -> PauseStatus {
+        let mut state = self.lock();
+        state.status = PauseStatus::default();
+        state.status
+    }
+
+    /// 定时器到期：仍是同一次暂停且已到恢复时间时恢复
+    pub fn expire_at(&self, token: u64, now_ms: u64) -> Option<PauseStatus> {
+        let mut state = self.lock();
+        if state.token != token {
+            return None;
+        }
+        Self::expire_due(&mut state, now_ms)
+    }
+
+    /// 唤醒后检查：已过恢复时间的暂停立即恢复，不等待定时器
+    pub fn expire_overdue_at(&self, now_ms: u64) -> Option<PauseStatus> {
+        Self::expire_due(&mut self.lock(), now_ms)
+    }
+
+    fn expire_due(state: &mut PauseState, now_ms: u64) -> Option<PauseStatus> {
+        let due = state.status.resume_at_ms.is_some_and(|at| at <= now_ms);
+        if !state.status.paused || !due {
+            return None;
+        }
+        state.status = PauseStatus::default();
+        Some(state.status)
+    }
+
+    /// 已过恢复时间但定时器尚未触发（例如系统睡眠）时视为未暂停
+    pub fn status_at(&self, now_ms: u64) -> PauseStatus {
+        let status = self.lock().status;
+        match status.resume_at_ms {
+            Some(at) if at <= now_ms => PauseStatus::default(),
+            _ => status,
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.status_at(now_ms()).paused
+    }
+}
+
+/// 暂停自动切换，`duration` 为 `None` 时直到手动恢复或重启
+pub fn pause_switching(app: &AppHandle, duration: Option<Duration>) -> PauseStatus {
+    let (status, token) = app
+        .state::<AppState>()
+        .switching_pause
+        .pause_at(duration, now_ms());
+    emit_status(app, status);
+
+    if let Some(resume_at_ms) = status.resume_at_ms {
+        let app = app.clone();
+        std::thread::spawn(move || {
+            loop {
+                let remaining = resume_at_ms.saturating_sub(now_ms());
+                if remaining == 0 {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(remaining).min(EXPIRY_CHECK_INTERVAL));
+            }
+            if let Some(status) = app
+                .state::<AppState>()
+                .switching_pause
+                .expire_at(token, now_ms())
+            {
+                emit_status(&app, status);
+            }
+        });
+    }
+    status
+}
+
+/// 系统唤醒后通知已过期的暂停，避免界面仍显示暂停
+pub fn expire_overdue_pause(app: &AppHandle) {
+    if let Some(status) = app
+        .state::<AppState>()
+        .switching_pause
+        .expire_overdue_at(now_ms())
+    {
+        emit_status(app, status);
+    }
+}
+
+pub fn resume_switching(app: &AppHandle) -> PauseStatus {
+    let status = app.state::<AppState>().switching_pause.resume();
+    emit_status(app, status);
+    status
+}
+
+fn emit_status(app: &AppHandle, status: PauseStatus) {
+    if let Err(e) = app.emit("switching_paused_changed", status) {
+        eprintln!("Failed to emit switching_paused_changed event: {}", e);
+    }
+    crate::tray_menu::refresh(app);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timed_pause_expires() {
+        let pause = SwitchingPause::default();
+        let (status, token) = pause.pause_at(Some(Duration::from_secs(60)), 1_000);
+        assert_eq!(
+            status,
+            PauseStatus {
+                paused: true,
+                resume_at_ms: Some(61_000),
+            }
+        );
+        assert!(pause.status_at(60_999).paused);
+        assert!(!pause.status_at(61_000).paused);
+
+        assert_eq!(pause.expire_at(token, 60_000), None);
+        assert_eq!(pause.expire_at(token, 61_000), Some(PauseStatus::default()));
+        assert_eq!(pause.status_at(0), PauseStatus::default());
+    }
+
+    #[test]
+    fn test_stale_timer_does_not_resume_new_pause() {
+        let pause = SwitchingPause::default();
+        let (_, first) = pause.pause_at(Some(Duration::from_secs(60)), 0);
+        // 改为暂停直到重启，旧定时器到期不应恢复
+        let (status, _) = pause.pause_at(None, 30_000);
+        assert_eq!(status.resume_at_ms, None);
+        assert_eq!(pause.expire_at(first, 60_000), None);
+        assert!(pause.status_at(u64::MAX).paused);
+
+        assert_eq!(pause.resume(), PauseStatus::default());
+        assert!(!pause.status_at(30_000).paused);
+    }
+
+    #[test]
+    fn test_overdue_pause_expires_after_wake() {
+        let pause = SwitchingPause::default();
+        pause.pause_at(Some(Duration::from_secs(60)), 0);
+        assert_eq!(pause.expire_overdue_at(59_999), None);
+        assert_eq!(
+            pause.expire_overdue_at(120_000),
+            Some(PauseStatus::default())
+        );
+        // 已恢复后不再重复通知
+        assert_eq!(pause.expire_overdue_at(120_000), None);
+
+        pause.pause_at(None, 0);
+        assert_eq!(pause.expire_overdue_at(u64::MAX), None);
+    }
+}
@@ -0,0 +1,65 @@
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// 只属于调用方的临时目录，并行运行的测试互不干扰，离开作用域时删除
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(prefix: &str) -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "smartime-{prefix}-{}-{}",
+            std::process::id(),
+            NEXT_ID.fetch_add(1, Ordering::Relaxed)
+        ));
+        // 进程号可能被复用，清掉上次运行留下的同名目录
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).expect("create temp dir");
+        Self(path)
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// 设为 `1` 时缺少外部依赖的集成测试直接失败，CI 用它确保测试真正运行
+pub const REQUIRE_INTEGRATION_ENV: &str = "SMARTIME_REQUIRE_INTEGRATION";
+
+/// 集成测试缺少 `dbus-daemon`、`Xvfb` 等外部依赖时跳过，要求运行时改为失败
+pub fn skip_integration_test(reason: &str) {
+    if std::env::var(REQUIRE_INTEGRATION_ENV).is_ok_and(|value| value == "1") {
+        panic!("Integration test could not run: {reason} ({REQUIRE_INTEGRATION_ENV}=1)");
+    }
+    eprintln!("Skipping integration test: {reason}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_temp_dirs_are_unique_and_removed_on_drop() {
+        let first = TempDir::new("temp-dir-test");
+        let second = TempDir::new("temp-dir-test");
+        assert_ne!(first.to_path_buf(), second.to_path_buf());
+        fs::write(first.join("file"), "content").unwrap();
+
+        let path = first.to_path_buf();
+        drop(first);
+        assert!(!path.exists());
+        assert!(second.is_dir());
+    }
+}
@@ -0,0 +1,454 @@
+use crate::command::run_backend_task;
+use crate::config::AppState;
+use crate::general_settings::TRAY_ICON_ID;
+use crate::input_source::InputSource;
+use crate::tray_refresh::{self, TrayRefresh};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tauri::image::Image;
+use tauri::{AppHandle, Manager};
+
+pub const ICON_SIZE: u32 = 32;
+
+// 5x7 点阵拉丁字母，每行低 5 位从左到右
+const LATIN_GLYPHS: [[u8; 7]; 26] = [
+    [0x0E, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11], // A
+    [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E], // B
+    [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E], // C
+    [0x1E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x1E], // D
+    [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F], // E
+    [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10], // F
+    [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F], // G
+    [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11], // H
+    [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E], // I
+    [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C], // J
+    [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11], // K
+    [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F], // L
+    [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11], // M
+    [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11], // N
+    [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E], // O
+    [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10], // P
+    [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D], // Q
+    [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11], // R
+    [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E], // S
+    [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04], // T
+    [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E], // U
+    [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04], // V
+    [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A], // W
+    [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11], // X
+    [0x11, 0x11, 0x0A, 0x04, 0x04, 0x04, 0x04], // Y
+    [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F], // Z
+];
+const UNKNOWN_GLYPH: [u8; 7] = [0x0E, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04];
+
+// 11x11 点阵 CJK 字符，每行低 11 位从左到右
+const HAN_ZHONG: [u16; 11] = [
+    0x020, 0x020, 0x3FE, 0x222, 0x222, 0x222, 0x3FE, 0x020, 0x020, 0x020, 0x020,
+];
+const HIRAGANA_A: [u16; 11] = [
+    0x080, 0x7FC, 0x080, 0x0B8, 0x1C6, 0x283, 0x481, 0x501, 0x502, 0x20C, 0x1F0,
+];
+const HANGUL_HAN: [u16; 11] = [
+    0x104, 0x7C4, 0x004, 0x387, 0x444, 0x384, 0x000, 0x200, 0x200, 0x200, 0x3FF,
+];
+
+/// 托盘图标的状态，优先级从高到低：关闭、暂停、切换失败、正常
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IconState {
+    Active,
+    Paused,
+    Disabled,
+    Failed,
+}
+
+impl IconState {
+    pub fn resolve(global_switch: bool, paused: bool, last_switch_failed: bool) -> Self {
+        if !global_switch {
+            Self::Disabled
+        } else if paused {
+            Self::Paused
+        } else if last_switch_failed {
+            Self::Failed
+        } else {
+            Self::Active
+        }
+    }
+}
+
+/// 输入法在图标上显示的文字：“EN”“中”“あ”“한”或布局代号
+pub fn icon_label(source: &InputSource) -> String {
+    let id = source.id.to_ascii_lowercase();
+    let has_char =
+        |range: std::ops::RangeInclusive<char>| source.name.chars().any(|c| range.contains(&c));
+    let id_has = |needles: &[&str]| needles.iter().any(|needle| id.contains(needle));
+
+    if has_char('\u{3040}'..='\u{30FF}')
+        || id_has(&["japanese", "kotoeri", "mozc", "anthy", "kkc", "skk"])
+    {
+        return "あ".to_string();
+    }
+    if has_char('\u{AC00}'..='\u{D7AF}') || id_has(&["korean", "hangul"]) {
+        return "한".to_string();
+    }
+    if has_char('\u{4E00}'..='\u{9FFF}')
+        || id_has(&[
+            "scim",
+            "tcim",
+            "pinyin",
+            "rime",
+            "wubi",
+            "chewing",
+            "zhuyin",
+            "cangjie",
+            "shuangpin",
+        ])
+    {
+        return "中".to_string();
+    }
+
+    // 键盘布局取 ID 最后一段，例如 com.apple.keylayout.ABC、xkb:de、keyboard-us
+    let layout = source
+        .id
+        .rsplit(['.', ':', '-'])
+        .next()
+        .unwrap_or_default()
+        .split('(')
+        .next()
+        .unwrap_or_default()
+        .to_ascii_uppercase();
+    match layout.as_str() {
+        "ABC" | "US" | "USEXTENDED" | "BRITISH" | "GB" | "AUSTRALIAN" | "DVORAK" | "COLEMAK" => {
+            "EN".to_string()
+        }
+        _ => {
+            let letters = layout
+                .chars()
+                .filter(|c| c.is_ascii_alphabetic())
+                .take(2)
+                .collect::<String>();
+            if letters.is_empty() {
+                "?".to_string()
+            } else {
+                letters
+            }
+        }
+    }
+}
+
+/// 托盘提示文字，例如 “SmartIME — Slack → Pinyin”
+pub fn tooltip(app_name: Option<&str>, source_name: Option<&str>, state: IconState) -> String {
+    let mut text = match (app_name, source_name) {
+        (Some(app), Some(source)) => format!("SmartIME — {app} → {source}"),
+        (None, Some(source)) => format!("SmartIME — {source}"),
+        _ => "SmartIME".to_string(),
+    };
+    match state {
+        IconState::Active => {}
+        IconState::Paused => text.push_str("（已暂停）"),
+        IconState::Disabled => text.push_str("（自动切换已关闭）"),
+        IconState::Failed => text.push_str("（上次切换失败）"),
+    }
+    text
+}
+
+struct Canvas {
+    rgba: Vec<u8>,
+}
+
+impl Canvas {
+    fn new() -> Self {
+        Self {
+            rgba: vec![0; (ICON_SIZE * ICON_SIZE * 4) as usize],
+        }
+    }
+
+    fn set(&mut self, x: u32, y: u32, on: bool) {
+        if x >= ICON_SIZE || y >= ICON_SIZE {
+            return;
+        }
+        let offset = ((y * ICON_SIZE + x) * 4) as usize;
+        // 黑色像素配合模板图标，由系统按菜单栏主题着色
+        let pixel = if on { [0, 0, 0, 255] } else { [0, 0, 0, 0] };
+        self.rgba[offset..offset + 4].copy_from_slice(&pixel);
+    }
+
+    fn fill(&mut self, x: u32, y: u32, width: u32, height: u32, on: bool) {
+        for dy in 0..height {
+            for dx in 0..width {
+                self.set(x + dx, y + dy, on);
+            }
+        }
+    }
+
+    /// 按 `scale` 倍绘制点阵，`rows` 每行低 `width` 位从左到右
+    fn draw_bitmap(&mut self, rows: &[u16], width: u32, x: u32, y: u32, scale: u32) {
+        for (row_index, row) in rows.iter().enumerate() {
+            for column in 0..width {
+                if (row >> (width - 1 - column)) & 1 == 1 {
+                    self.fill(
+                        x + column * scale,
+                        y + row_index as u32 * scale,
+                        scale,
+                        scale,
+                        true,
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn latin_glyph(c: char) -> [u16; 7] {
+    let rows = match c {
+        'A'..='Z' => LATIN_GLYPHS[(c as u8 - b'A') as usize],
+        _ => UNKNOWN_GLYPH,
+    };
+    rows.map(u16::from)
+}
+
+/// 绘制 32x32 RGBA 图标
+pub fn render_icon(label: &str, state: IconState) -> Vec<u8> {
+    let mut canvas = Canvas::new();
+    let cjk = match label {
+        "中" => Some(HAN_ZHONG),
+        "あ" => Some(HIRAGANA_A),
+        "한" => Some(HANGUL_HAN),
+        _ => None,
+    };
+
+    match cjk {
+        // 11x11 放大两倍后居中
+        Some(rows) => canvas.draw_bitmap(&rows, 11, 5, 5, 2),
+        None => {
+            let letters = label.chars().take(2).collect::<Vec<_>>();
+            let width = (letters.len() as u32 * 12).saturating_sub(2);
+            let x = (ICON_SIZE - width.min(ICON_SIZE)) / 2;
+            for (index, letter) in letters.into_iter().enumerate() {
+                canvas.draw_bitmap(
+                    &latin_glyph(letter.to_ascii_uppercase()),
+                    5,
+                    x + index as u32 * 12,
+                    9,
+                    2,
+                );
+            }
+        }
+    }
+
+    match state {
+        IconState::Active => {}
+        IconState::Paused => {
+            // 右下角暂停符号
+            canvas.fill(19, 19, 13, 13, false);
+            canvas.fill(22, 21, 3, 10, true);
+            canvas.fill(27, 21, 3, 10, true);
+        }
+        IconState::Failed => {
+            // 右下角感叹号
+            canvas.fill(21, 18, 11, 14, false);
+            canvas.fill(25, 19, 3, 8, true);
+            canvas.fill(25, 29, 3, 3, true);
+        }
+        IconState::Disabled => {
+            // 左下到右上的斜线
+            for step in 0..28 {
+                canvas.fill(2 + step, 28 - step, 2, 2, true);
+            }
+        }
+    }
+    canvas.rgba
+}
+
+// 图标文字与状态
+type IconKey = (String, IconState);
+
+#[derive(Default)]
+struct IconStatus {
+    source: Option<InputSource>,
+    last_switch_failed: bool,
+}
+
+/// 当前输入法、上次切换结果与按状态缓存的图标
+#[derive(Default)]
+pub struct TrayIcons {
+    status: Mutex<IconStatus>,
+    cache: Mutex<HashMap<IconKey, Arc<Vec<u8>>>>,
+}
+
+impl TrayIcons {
+    fn status(&self) -> MutexGuard<'_, IconStatus> {
+        // 只是展示用的状态，锁中毒后继续使用即可
+        self.status.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn set_current_source(&self, source: &InputSource) {
+        self.status().source = Some(source.clone());
+    }
+
+    pub fn set_switch_failed(&self, failed: bool) {
+        self.status().last_switch_failed = failed;
+    }
+
+    /// 同一文字与状态只绘制一次
+    pub fn icon(&self, label: &str, state: IconState) -> Arc<Vec<u8>> {
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        cache
+            .entry((label.to_string(), state))
+            .or_insert_with(|| Arc::new(render_icon(label, state)))
+            .clone()
+    }
+}
+
+/// 按当前输入法与开关状态更新托盘图标和提示，在托盘工作线程执行
+pub fn refresh(app: &AppHandle) {
+    if app.tray_by_id(TRAY_ICON_ID).is_none() {
+        return;
+    }
+    tray_refresh::request(app, TrayRefresh::ICON);
+}
+
+/// 只在托盘工作线程上调用
+pub fn update(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    let known_source = state.tray_icons.status().source.clone();
+    let source = match known_source {
+        Some(source) => Some(source),
+        None => run_backend_task(
+            app,
+            "tray input source lookup",
+            Duration::from_millis(500),
+            |backend| backend.current(),
+        )
+        .ok()
+        .inspect(|source| state.tray_icons.set_current_source(source)),
+    };
+    let last_switch_failed = state.tray_icons.status().last_switch_failed;
+    let global_switch = state
+        .config
+        .lock()
+        .map(|manager| manager.get_config().global_switch)
+        .unwrap_or(true);
+    let icon_state = IconState::resolve(
+        global_switch,
+        state.switching_pause.is_paused(),
+        last_switch_failed,
+    );
+    let frontmost = state.input_changes.frontmost();
+
+    let Some(tray) = app.tray_by_id(TRAY_ICON_ID) else {
+        return;
+    };
+    let text = tooltip(
+        frontmost.as_ref().map(|app| app.app_name.as_str()),
+        source.as_ref().map(|source| source.name.as_str()),
+        icon_state,
+    );
+    if let Err(e) = tray.set_tooltip(Some(text)) {
+        eprintln!("Failed to update tray tooltip: {}", e);
+    }
+
+    // 无法读取当前输入法时保留静态图标
+    let Some(source) = source else {
+        return;
+    };
+    let rgba = state.tray_icons.icon(&icon_label(&source), icon_state);
+    let result = tray
+        .set_icon(Some(Image::new(&rgba, ICON_SIZE, ICON_SIZE)))
+        // macOS 更换图标后需要重新声明模板图标
+        .and_then(|_| tray.set_icon_as_template(true));
+    if let Err(e) = result {
+        eprintln!("Failed to update tray icon: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(id: &str, name: &str) -> InputSource {
+        InputSource {
+            id: id.to_string(),
+            name: name.to_string(),
+            category: "TISCategoryKeyboardInputSource".to_string(),
+        }
+    }
+
+    fn opaque_pixels(rgba: &[u8]) -> usize {
+        rgba.chunks(4).filter(|pixel| pixel[3] == 255).count()
+    }
+
+    #[test]
+    fn test_icon_label() {
+        let cases = [
+            ("com.apple.keylayout.ABC", "ABC", "EN"),
+            (
+                "com.apple.inputmethod.SCIM.ITABC",
+                "Pinyin - Simplified",
+                "中",
+            ),
+            (
+                "com.apple.inputmethod.Kotoeri.RomajiTyping.Japanese",
+                "Hiragana",
+                "あ",
+            ),
+            (
+                "com.apple.inputmethod.Korean.2SetKorean",
+                "2-Set Korean",
+                "한",
+            ),
+            ("xkb:de(nodeadkeys)", "German", "DE"),
+            ("keyboard-us", "English (US)", "EN"),
+            ("rime", "中州韻", "中"),
+            ("xkb:group3", "Group 3", "GR"),
+        ];
+        for (id, name, expected) in cases {
+            assert_eq!(icon_label(&source(id, name)), expected, "{id}");
+        }
+    }
+
+    #[test]
+    fn test_icon_state_priority_and_tooltip() {
+        assert_eq!(IconState::resolve(false, true, true), IconState::Disabled);
+        assert_eq!(IconState::resolve(true, true, true), IconState::Paused);
+        assert_eq!(IconState::resolve(true, false, true), IconState::Failed);
+        assert_eq!(IconState::resolve(true, false, false), IconState::Active);
+
+        assert_eq!(
+            tooltip(Some("Slack"), Some("Pinyin"), IconState::Active),
+            "SmartIME — Slack → Pinyin"
+        );
+        assert_eq!(
+            tooltip(None, Some("ABC"), IconState::Paused),
+            "SmartIME — ABC（已暂停）"
+        );
+        assert_eq!(tooltip(None, None, IconState::Active), "SmartIME");
+    }
+
+    #[test]
+    fn test_rendered_states_are_distinct_and_cached() {
+        let icons = TrayIcons::default();
+        let states = [
+            IconState::Active,
+            IconState::Paused,
+            IconState::Disabled,
+            IconState::Failed,
+        ];
+        let rendered = states.map(|state| icons.icon("EN", state));
+        for (index, icon) in rendered.iter().enumerate() {
+            assert_eq!(icon.len(), (ICON_SIZE * ICON_SIZE * 4) as usize);
+            assert!(opaque_pixels(icon) > 0);
+            for other in &rendered[index + 1..] {
+                assert_ne!(icon, other);
+            }
+        }
+        assert_ne!(
+            render_icon("中", IconState::Active),
+            render_icon("あ", IconState::Active)
+        );
+        assert!(Arc::ptr_eq(
+            &rendered[0],
+            &icons.icon("EN", IconState::Active)
+        ));
+    }
+}
@@ -0,0 +1,426 @@
+use crate::command::{run_backend_task, run_switch_task};
+use crate::config::AppState;
+use crate::error::{AppError, Result};
+use crate::general_settings::TRAY_ICON_ID;
+use crate::input_source::{FrontmostApp, InputSource};
+use crate::shortcuts::{self, ShortcutAction};
+use crate::switching_pause::PauseStatus;
+use crate::tray_refresh::{self, TrayRefresh};
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::menu::{CheckMenuItem, IsMenuItem, Menu, MenuItem, PredefinedMenuItem, Submenu};
+use tauri::{AppHandle, Emitter, Manager, Wry};
+
+const SET_INPUT_PREFIX: &str = "set_input:";
+const TOGGLE_GLOBAL_SWITCH_ID: &str = "toggle_global_switch";
+const TOGGLE_PAUSE_ID: &str = "toggle_pause";
+const RESCAN_ID: &str = "rescan";
+const OPEN_SETTINGS_ID: &str = "open_settings";
+const QUIT_ID: &str = "quit";
+
+/// 构建菜单所需的状态快照
+#[derive(Debug, Clone, Default)]
+pub struct TrayStatus {
+    pub frontmost: Option<FrontmostApp>,
+    /// 前台应用规则指定的输入法 ID
+    pub rule_input: Option<String>,
+    pub input_sources: Vec<InputSource>,
+    pub global_switch: bool,
+    pub pause: PauseStatus,
+    pub is_rescanning: bool,
+}
+
+/// 与托盘运行时无关的菜单描述
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrayMenuItem {
+    /// 仅展示状态，不可点击
+    Label(String),
+    Action {
+        id: String,
+        text: String,
+        enabled: bool,
+    },
+    Check {
+        id: String,
+        text: String,
+        checked: bool,
+    },
+    Submenu {
+        text: String,
+        enabled: bool,
+        items: Vec<TrayMenuItem>,
+    },
+    Separator,
+}
+
+/// 菜单点击对应的操作
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrayCommand {
+    SetInput(String),
+    ToggleGlobalSwitch,
+    TogglePause,
+    Rescan,
+    OpenSettings,
+    Quit,
+}
+
+pub fn parse_menu_id(id: &str) -> Option<TrayCommand> {
+    if let Some(input_id) = id.strip_prefix(SET_INPUT_PREFIX) {
+        return Some(TrayCommand::SetInput(input_id.to_string()));
+    }
+    match id {
+        TOGGLE_GLOBAL_SWITCH_ID => Some(TrayCommand::ToggleGlobalSwitch),
+        TOGGLE_PAUSE_ID => Some(TrayCommand::TogglePause),
+        RESCAN_ID => Some(TrayCommand::Rescan),
+        OPEN_SETTINGS_ID => Some(TrayCommand::OpenSettings),
+        QUIT_ID => Some(TrayCommand::Quit),
+        _ => None,
+    }
+}
+
+fn action(id: &str, text: &str, enabled: bool) -> TrayMenuItem {
+    TrayMenuItem::Action {
+        id: id.to_string(),
+        text: text.to_string(),
+        enabled,
+    }
+}
+
+pub fn build_menu_model(status: &TrayStatus) -> Vec<TrayMenuItem> {
+    let source_name = |id: &str| {
+        status
+            .input_sources
+            .iter()
+            .find(|source| source.id == id)
+            .map_or_else(|| id.to_string(), |source| source.name.clone())
+    };
+
+    let app_label = match &status.frontmost {
+        Some(app) => format!("当前应用：{}", app.app_name),
+        None => "当前应用：未知".to_string(),
+    };
+    let rule_label = match &status.rule_input {
+        Some(input_id) => format!("规则：{}", source_name(input_id)),
+        None => "规则：未设置".to_string(),
+    };
+    let input_items = status
+        .input_sources
+        .iter()
+        .map(|source| TrayMenuItem::Check {
+            id: format!("{SET_INPUT_PREFIX}{}", source.id),
+            text: source.name.clone(),
+            checked: status.rule_input.as_deref() == Some(source.id.as_str()),
+        })
+        .collect::<Vec<_>>();
+
+    vec![
+        TrayMenuItem::Label(app_label),
+        TrayMenuItem::Label(rule_label),
+        TrayMenuItem::Submenu {
+            text: "为此应用设置输入法".to_string(),
+            enabled: status.frontmost.is_some() && !input_items.is_empty(),
+            items: input_items,
+        },
+        TrayMenuItem::Separator,
+        TrayMenuItem::Check {
+            id: TOGGLE_GLOBAL_SWITCH_ID.to_string(),
+            text: "自动切换".to_string(),
+            checked: status.global_switch,
+        },
+        TrayMenuItem::Check {
+            id: TOGGLE_PAUSE_ID.to_string(),
+            text: "暂停自动切换".to_string(),
+            checked: status.pause.paused,
+        },
+        TrayMenuItem::Separator,
+        action(
+            RESCAN_ID,
+            if status.is_rescanning {
+                "正在扫描应用…"
+            } else {
+                "重新扫描应用"
+            },
+            !status.is_rescanning,
+        ),
+        action(OPEN_SETTINGS_ID, "打开设置", true),
+        TrayMenuItem::Separator,
+        action(QUIT_ID, "退出 SmartIME", true),
+    ]
+}
+
+/// 菜单中列出的输入法，首次构建菜单时读取，重新扫描后刷新
+#[derive(Default)]
+pub struct TrayMenuCache {
+    input_sources: Mutex<Option<Vec<InputSource>>>,
+}
+
+impl TrayMenuCache {
+    fn invalidate(&self) {
+        if let Ok(mut sources) = self.input_sources.lock() {
+            *sources = None;
+        }
+    }
+}
+
+fn collect_status(app: &AppHandle) -> Result<TrayStatus> {
+    let state = app.state::<AppState>();
+    let cached = state
+        .tray_menu
+        .input_sources
+        .lock()
+        .map_err(|e| AppError::Lock(e.to_string()))?
+        .clone();
+    let input_sources = match cached {
+        Some(sources) => sources,
+        None => {
+            let sources = run_backend_task(
+                app,
+                "tray input source scan",
+                Duration::from_secs(5),
+                |backend| backend.list(),
+            )?;
+            if let Ok(mut cache) = state.tray_menu.input_sources.lock() {
+                *cache = Some(sources.clone());
+            }
+            sources
+        }
+    };
+
+    let frontmost = state.input_changes.frontmost();
+    let rules = state.rules.load();
+    let rule_input = frontmost
+        .as_ref()
+        .and_then(|app| rules.resolve(&app.bundle_id))
+        .map(|target| target.input_id.clone());
+
+    Ok(TrayStatus {
+        frontmost,
+        rule_input,
+        input_sources,
+        global_switch: rules.global_switch(),
+        pause: state.switching_pause.status_at(crate::llm_audit::now_ms()),
+        is_rescanning: state.is_rescanning.load(Ordering::SeqCst),
+    })
+}
+
+fn to_native_item(app: &AppHandle, item: &TrayMenuItem) -> Result<Box<dyn IsMenuItem<Wry>>> {
+    let map_err = |e: tauri::Error| AppError::Config(format!("Failed to build tray menu: {}", e));
+    let native: Box<dyn IsMenuItem<Wry>> = match item {
+        TrayMenuItem::Label(text) => {
+            Box::new(MenuItem::new(app, text, false, None::<&str>).map_err(map_err)?)
+        }
+        TrayMenuItem::Action { id, text, enabled } => {
+            Box::new(MenuItem::with_id(app, id, text, *enabled, None::<&str>).map_err(map_err)?)
+        }
+        TrayMenuItem::Check { id, text, checked } => Box::new(
+            CheckMenuItem::with_id(app, id, text, true, *checked, None::<&str>).map_err(map_err)?,
+        ),
+        TrayMenuItem::Submenu {
+            text,
+            enabled,
+            items,
+        } => {
+            let submenu = Submenu::new(app, text, *enabled).map_err(map_err)?;
+            for child in items {
+                submenu
+                    .append(to_native_item(app, child)?.as_ref())
+                    .map_err(map_err)?;
+            }
+            Box::new(submenu)
+        }
+        TrayMenuItem::Separator => Box::new(PredefinedMenuItem::separator(app).map_err(map_err)?),
+    };
+    Ok(native)
+}
+
+pub fn build_menu(app: &AppHandle) -> Result<Menu<Wry>> {
+    let model = build_menu_model(&collect_status(app)?);
+    let menu = Menu::new(app)
+        .map_err(|e| AppError::Config(format!("Failed to build tray menu: {}", e)))?;
+    for item in &model {
+        menu.append(to_native_item(app, item)?.as_ref())
+            .map_err(|e| AppError::Config(format!("Failed to build tray menu: {}", e)))?;
+    }
+    Ok(menu)
+}
+
+/// 焦点、规则或开关变化后重建托盘菜单
+pub fn refresh(app: &AppHandle) {
+    if app.tray_by_id(TRAY_ICON_ID).is_none() {
+        return;
+    }
+    // 图标与提示依赖同样的状态
+    tray_refresh::request(app, TrayRefresh::ALL);
+}
+
+/// 按最新状态重建菜单，只在托盘工作线程上调用
+pub fn update(app: &AppHandle) {
+    let result = build_menu(app).and_then(|menu| {
+        let tray = app
+            .tray_by_id(TRAY_ICON_ID)
+            .ok_or_else(|| AppError::Config("Tray icon was removed".to_string()))?;
+        tray.set_menu(Some(menu))
+            .map_err(|e| AppError::Config(format!("Failed to update tray menu: {}", e)))
+    });
+    if let Err(e) = result {
+        eprintln!("Failed to refresh tray menu: {}", e);
+    }
+}
+
+/// 托盘菜单点击回调
+pub fn handle_menu_event(app: &AppHandle, id: &str) {
+    let Some(command) = parse_menu_id(id) else {
+        return;
+    };
+    match command {
+        TrayCommand::OpenSettings => crate::single_instance::focus_main_window(app),
+        TrayCommand::Quit => app.exit(0),
+        TrayCommand::Rescan => {
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                let state = app.state::<AppState>();
+                match crate::command::cmd_rescan_and_save_rules(state, app.clone()).await {
+                    // 重新扫描后输入法列表可能变化
+                    Ok(_) => {
+                        app.state::<AppState>().tray_menu.invalidate();
+                        refresh(&app);
+                    }
+                    Err(e) => eprintln!("Failed to rescan apps from tray: {}", e),
+                }
+            });
+        }
+        TrayCommand::SetInput(input_id) => {
+            let app = app.clone();
+            std::thread::spawn(move || {
+                if let Err(e) = set_frontmost_input(&app, &input_id) {
+                    eprintln!("Failed to set input source from tray: {}", e);
+                }
+                refresh(&app);
+            });
+        }
+        TrayCommand::ToggleGlobalSwitch => {
+            shortcuts::spawn_action(app, ShortcutAction::ToggleGlobalSwitch)
+        }
+        TrayCommand::TogglePause => shortcuts::spawn_action(app, ShortcutAction::PauseSwitching),
+    }
+}
+
+/// 把输入法写入前台应用的手动规则并立即切换
+fn set_frontmost_input(app: &AppHandle, input_id: &str) -> Result<()> {
+    let state = app.state::<AppState>();
+    let frontmost = state.input_changes.frontmost().ok_or_else(|| {
+        AppError::InputSource("No frontmost app to set the input source for".to_string())
+    })?;
+    let rule = state
+        .config
+        .lock()
+        .map_err(|e| AppError::Lock(e.to_string()))?
+        .upsert_manual_rule(&frontmost.bundle_id, &frontmost.app_name, input_id)?;
+    if let Err(e) = app.emit("rule_pinned", &rule) {
+        eprintln!("Failed to emit rule_pinned event: {}", e);
+    }
+
+    let input_id = input_id.to_string();
+    run_switch_task(
+        app,
+        "input source selection",
+        Duration::from_millis(500),
+        move |backend| backend.select(&input_id),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(id: &str, name: &str) -> InputSource {
+        InputSource {
+            id: id.to_string(),
+            name: name.to_string(),
+            category: "TISCategoryKeyboardInputSource".to_string(),
+        }
+    }
+
+    fn status() -> TrayStatus {
+        TrayStatus {
+            frontmost: Some(FrontmostApp {
+                bundle_id: "com.tencent.xinWeChat".to_string(),
+                app_name: "WeChat".to_string(),
+            }),
+            rule_input: Some("pinyin".to_string()),
+            input_sources: vec![source("abc", "ABC"), source("pinyin", "拼音")],
+            global_switch: true,
+            pause: PauseStatus::default(),
+            is_rescanning: false,
+        }
+    }
+
+    #[test]
+    fn test_menu_model_shows_frontmost_rule() {
+        let model = build_menu_model(&status());
+        assert_eq!(
+            model[0],
+            TrayMenuItem::Label("当前应用：WeChat".to_string())
+        );
+        assert_eq!(model[1], TrayMenuItem::Label("规则：拼音".to_string()));
+
+        let TrayMenuItem::Submenu { enabled, items, .. } = &model[2] else {
+            panic!("expected input submenu");
+        };
+        assert!(*enabled);
+        assert_eq!(
+            items
+                .iter()
+                .map(|item| match item {
+                    TrayMenuItem::Check { id, checked, .. } => (id.as_str(), *checked),
+                    other => panic!("unexpected item {other:?}"),
+                })
+                .collect::<Vec<_>>(),
+            vec![("set_input:abc", false), ("set_input:pinyin", true)]
+        );
+
+        // 每个可点击项都能解析回操作
+        for item in &model {
+            if let TrayMenuItem::Action { id, .. } | TrayMenuItem::Check { id, .. } = item {
+                assert!(parse_menu_id(id).is_some(), "unparsed menu id {id}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_menu_model_without_frontmost_app() {
+        let model = build_menu_model(&TrayStatus {
+            frontmost: None,
+            rule_input: None,
+            is_rescanning: true,
+            pause: PauseStatus {
+                paused: true,
+                resume_at_ms: None,
+            },
+            ..status()
+        });
+        assert_eq!(model[0], TrayMenuItem::Label("当前应用：未知".to_string()));
+        assert_eq!(model[1], TrayMenuItem::Label("规则：未设置".to_string()));
+        assert!(matches!(
+            model[2],
+            TrayMenuItem::Submenu { enabled: false, .. }
+        ));
+        assert!(model.contains(&TrayMenuItem::Check {
+            id: TOGGLE_PAUSE_ID.to_string(),
+            text: "暂停自动切换".to_string(),
+            checked: true,
+        }));
+        assert!(model.contains(&action(RESCAN_ID, "正在扫描应用…", false)));
+    }
+
+    #[test]
+    fn test_parse_menu_id() {
+        assert_eq!(
+            parse_menu_id("set_input:com.apple.keylayout.ABC"),
+            Some(TrayCommand::SetInput("com.apple.keylayout.ABC".to_string()))
+        );
+        assert_eq!(parse_menu_id(QUIT_ID), Some(TrayCommand::Quit));
+        assert_eq!(parse_menu_id("unknown"), None);
+    }
+}
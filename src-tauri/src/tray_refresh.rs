@@ -0,0 +1,129 @@
+use crate::config::AppState;
+use std::sync::{Condvar, Mutex, MutexGuard};
+use tauri::{AppHandle, Manager};
+
+/// 需要刷新的托盘部分
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TrayRefresh {
+    pub menu: bool,
+    pub icon: bool,
+}
+
+impl TrayRefresh {
+    pub const ALL: Self = Self {
+        menu: true,
+        icon: true,
+    };
+    pub const ICON: Self = Self {
+        menu: false,
+        icon: true,
+    };
+
+    fn merge(&mut self, other: Self) {
+        self.menu |= other.menu;
+        self.icon |= other.icon;
+    }
+
+    fn is_empty(&self) -> bool {
+        !self.menu && !self.icon
+    }
+}
+
+#[derive(Default)]
+struct QueueState {
+    pending: TrayRefresh,
+    worker_started: bool,
+}
+
+/// 托盘刷新请求队列：单个工作线程按顺序处理，忙碌期间的多次请求合并为一次
+///
+/// 图标与菜单都在同一线程上更新，不会因乱序完成而停留在旧状态。每次处理都读取最新状态，因此只需保留“是否需要刷新”，与 `FocusCoalescer` 一样最新优先。
+#[derive(Default)]
+pub struct TrayRefreshQueue {
+    state: Mutex<QueueState>,
+    ready: Condvar,
+}
+
+impl TrayRefreshQueue {
+    fn lock(&self) -> MutexGuard<'_, QueueState> {
+        // 只记录刷新标记，锁中毒后继续使用即可
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 登记刷新请求，返回 `true` 表示工作线程尚未启动
+    fn request(&self, refresh: TrayRefresh) -> bool {
+        let mut state = self.lock();
+        state.pending.merge(refresh);
+        self.ready.notify_one();
+        !std::mem::replace(&mut state.worker_started, true)
+    }
+
+    /// 阻塞到有请求为止，取出合并后的请求
+    fn wait(&self) -> TrayRefresh {
+        let mut state = self.lock();
+        while state.pending.is_empty() {
+            state = self.ready.wait(state).unwrap_or_else(|e| e.into_inner());
+        }
+        std::mem::take(&mut state.pending)
+    }
+}
+
+/// 请求在托盘工作线程上刷新，首次请求时启动该线程
+///
+/// 读取输入法列表可能需要等待主线程，因此不在调用方线程执行。
+pub fn request(app: &AppHandle, refresh: TrayRefresh) {
+    if !app.state::<AppState>().tray_refresh.request(refresh) {
+        return;
+    }
+    let app = app.clone();
+    std::thread::spawn(move || loop {
+        let refresh = app.state::<AppState>().tray_refresh.wait();
+        if refresh.icon {
+            crate::tray_icon::update(&app);
+        }
+        if refresh.menu {
+            crate::tray_menu::update(&app);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_requests_are_coalesced_until_the_worker_takes_them() {
+        let queue = TrayRefreshQueue::default();
+        assert!(queue.request(TrayRefresh::ICON));
+        assert!(!queue.request(TrayRefresh::ICON));
+        assert!(!queue.request(TrayRefresh::ICON));
+
+        // 三次请求只刷新一次
+        assert_eq!(queue.wait(), TrayRefresh::ICON);
+        assert!(queue.lock().pending.is_empty());
+    }
+
+    #[test]
+    fn test_worker_wakes_for_later_requests() {
+        let queue = std::sync::Arc::new(TrayRefreshQueue::default());
+        let worker = {
+            let queue = queue.clone();
+            std::thread::spawn(move || queue.wait())
+        };
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        queue.request(TrayRefresh::ICON);
+        assert_eq!(worker.join().unwrap(), TrayRefresh::ICON);
+    }
+
+    #[test]
+    fn test_icon_and_menu_requests_are_merged() {
+        let queue = TrayRefreshQueue::default();
+        queue.request(TrayRefresh::ICON);
+        queue.request(TrayRefresh {
+            menu: true,
+            icon: false,
+        });
+        queue.request(TrayRefresh::ICON);
+        assert_eq!(queue.wait(), TrayRefresh::ALL);
+    }
+}
@@ -0,0 +1,57 @@
+use crate::test_support::skip_integration_test;
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Command, Stdio};
+
+/// 测试用的 Xvfb 虚拟显示，不依赖真实桌面会话
+pub struct VirtualDisplay {
+    child: Child,
+    display: String,
+}
+
+impl VirtualDisplay {
+    /// 启动 Xvfb，未安装或启动失败时返回 `None`，调用方应直接跳过测试；
+    /// 设置 `SMARTIME_REQUIRE_INTEGRATION=1` 时改为失败
+    ///
+    /// 由 Xvfb 通过 `-displayfd` 自行挑选空闲的显示编号，并行的测试各自拥有独立的服务器。
+    pub fn start() -> Option<Self> {
+        let mut child = match Command::new("Xvfb")
+            .args(["-displayfd", "1", "-nolisten", "tcp"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(e) => {
+                skip_integration_test(&format!("Xvfb unavailable: {e}"));
+                return None;
+            }
+        };
+
+        // 服务器就绪后才会写出显示编号；启动失败时输出直接关闭
+        let mut number = String::new();
+        if let Some(stdout) = child.stdout.take() {
+            let _ = BufReader::new(stdout).read_line(&mut number);
+        }
+        let virtual_display = Self {
+            child,
+            display: format!(":{}", number.trim()),
+        };
+        if number.trim().is_empty() || x11rb::connect(Some(&virtual_display.display)).is_err() {
+            skip_integration_test("Xvfb did not start");
+            return None;
+        }
+        Some(virtual_display)
+    }
+
+    pub fn display(&self) -> &str {
+        &self.display
+    }
+}
+
+impl Drop for VirtualDisplay {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}